@@ -3,6 +3,7 @@ pragma solidity ^0.8.20;
 
 import "forge-std/Script.sol";
 import "../src/AegisVault.sol";
+import "../src/AegisVaultKeccak.sol";
 import "../src/MockTokens.sol";
 import "../test/AegisVault.t.sol";
 import {SP1Verifier} from "@sp1-contracts/v3.0.0/SP1VerifierGroth16.sol";
@@ -10,6 +11,9 @@ import {SP1Verifier} from "@sp1-contracts/v3.0.0/SP1VerifierGroth16.sol";
 /// @title Deploy Script for Aegis Protocol
 /// @notice Deploys all contracts for local testing or testnet
 contract DeployScript is Script {
+    /// @notice Key hash of the zk-script LocalOracle default key (sha256 of its SEC1 public key)
+    bytes32 constant LOCAL_ORACLE_KEY_HASH = 0x308effbc4a098950786b4c99c61dc95784c4b2d68ca7e05fb3436a4ad673140e;
+
     function run() external {
         // Get private key from environment or use Anvil's default first account
         uint256 deployerPrivateKey;
@@ -32,6 +36,10 @@ contract DeployScript is Script {
         MockUSDC debtToken = new MockUSDC();
         console.log("MockUSDC deployed at:", address(debtToken));
 
+        console.log("Deploying MockWMNT...");
+        MockWMNT secondCollateralToken = new MockWMNT();
+        console.log("MockWMNT deployed at:", address(secondCollateralToken));
+
         // 2. Deploy SP1 verifier
         address verifier;
         bool useRealVerifier = vm.envOr("USE_REAL_SP1_VERIFIER", false);
@@ -54,23 +62,47 @@ contract DeployScript is Script {
         bytes32 depositVkey = keccak256("DEPOSIT_VKEY_V1");
         bytes32 borrowVkey = keccak256("BORROW_VKEY_V1");
 
-        // 4. Deploy AegisVault
-        console.log("Deploying AegisVault...");
-        AegisVault vault = new AegisVault(
-            address(verifier),
-            depositVkey,
-            borrowVkey,
-            address(collateralToken),
-            address(debtToken)
-        );
+        // 4. Deploy AegisVault (keccak256 tree for ZK programs built with `--features keccak`)
+        AegisVault vault;
+        if (vm.envOr("USE_KECCAK_TREE", false)) {
+            console.log("Deploying AegisVaultKeccak...");
+            vault = new AegisVaultKeccak(
+                address(verifier),
+                depositVkey,
+                borrowVkey,
+                address(collateralToken),
+                address(debtToken)
+            );
+        } else {
+            console.log("Deploying AegisVault...");
+            vault = new AegisVault(
+                address(verifier),
+                depositVkey,
+                borrowVkey,
+                address(collateralToken),
+                address(debtToken)
+            );
+        }
         console.log("AegisVault deployed at:", address(vault));
 
+        // Trust the price oracle (defaults to the zk-script local oracle test key)
+        bytes32 oracleKeyHash = vm.envOr("ORACLE_KEY_HASH", LOCAL_ORACLE_KEY_HASH);
+        vault.setOracleKeyHash(oracleKeyHash);
+        console.log("Oracle key hash:");
+        console.logBytes32(oracleKeyHash);
+
+        // List WMNT as a second collateral market (50% LTV) next to mETH
+        vault.setCollateralAsset(1, address(secondCollateralToken), 5000);
+        console.log("Listed WMNT as collateral slot 1");
+
         // 5. Setup: Mint tokens and fund vault
         console.log("\nSetting up test environment...");
         
         // Mint collateral to deployer (for testing deposits)
         collateralToken.mint(msg.sender, 1000 ether);
         console.log("Minted 1000 ETH to deployer");
+        secondCollateralToken.mint(msg.sender, 100_000 ether);
+        console.log("Minted 100000 WMNT to deployer");
         
         // Mint debt tokens and fund vault
         debtToken.mint(msg.sender, 10_000_000e6); // 10M USDC
@@ -86,6 +118,7 @@ contract DeployScript is Script {
         console.log("=================================");
         console.log("MockETH:", address(collateralToken));
         console.log("MockUSDC:", address(debtToken));
+        console.log("MockWMNT:", address(secondCollateralToken));
         if (useRealVerifier) {
             console.log("SP1Verifier (REAL):", verifier);
         } else {
@@ -103,6 +136,7 @@ contract DeployScript is Script {
         string memory addresses = string(abi.encodePacked(
             "COLLATERAL_TOKEN=", vm.toString(address(collateralToken)), "\n",
             "DEBT_TOKEN=", vm.toString(address(debtToken)), "\n",
+            "SECOND_COLLATERAL_TOKEN=", vm.toString(address(secondCollateralToken)), "\n",
             "VERIFIER=", vm.toString(address(verifier)), "\n",
             "VAULT=", vm.toString(address(vault)), "\n"
         ));
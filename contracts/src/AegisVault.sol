@@ -2,6 +2,7 @@
 pragma solidity ^0.8.20;
 
 import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+import "@openzeppelin/contracts/token/ERC20/extensions/IERC20Metadata.sol";
 import "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
 import "@openzeppelin/contracts/utils/cryptography/ECDSA.sol";
 import "@openzeppelin/contracts/utils/cryptography/MessageHashUtils.sol";
@@ -13,6 +14,33 @@ import "@sp1-contracts/ISP1Verifier.sol";
 contract AegisVault {
     using SafeERC20 for IERC20;
 
+    // ============ Types ============
+
+    /// @notice A collateral market: token, LTV weight and token decimals
+    struct CollateralAsset {
+        address token;
+        uint16 ltvBps;
+        uint8 decimals;
+    }
+
+    /// @notice Decoded public values of a borrow proof
+    struct BorrowPublicValues {
+        bytes32 nullifierHash;
+        bytes32 newCommitment;
+        address recipient;
+        uint128 borrowAmount;
+        bytes32 merkleRoot;
+        bytes32 oracleKeyHash;
+        uint64 priceTimestamp;
+        address[MAX_COLLATERAL_ASSETS] collateralAssets;
+        uint16[MAX_COLLATERAL_ASSETS] ltvWeightsBps;
+        uint8[MAX_COLLATERAL_ASSETS] collateralDecimals;
+        uint8 debtDecimals;
+        uint128 borrowIndex;
+        bytes32 blocklistRoot;
+        uint8 errorCode;
+    }
+
     // ============ State Variables ============
 
     /// @notice SP1 proof verifier contract
@@ -30,9 +58,86 @@ contract AegisVault {
     /// @notice Debt token (e.g., USDC)
     IERC20 public immutable DEBT_TOKEN;
 
+    /// @notice Decimals of the collateral token
+    uint8 public immutable COLLATERAL_DECIMALS;
+
+    /// @notice Decimals of the debt token (borrow proofs must use the same)
+    uint8 public immutable DEBT_DECIMALS;
+
+    /// @notice Collateral slots per note (must match MAX_COLLATERAL_ASSETS in zk-program/src/collateral.rs)
+    uint256 public constant MAX_COLLATERAL_ASSETS = 2;
+
+    /// @notice Most notes one batch deposit proof can create (must match MAX_BATCH_DEPOSITS in zk-program/src/types.rs)
+    uint256 public constant MAX_BATCH_DEPOSITS = 16;
+
+    /// @notice LTV weight of COLLATERAL_TOKEN when the vault is deployed (75%)
+    uint16 public constant DEFAULT_LTV_BPS = 7500;
+
+    /// @notice Basis points in 100%
+    uint16 public constant BPS_DENOMINATOR = 10_000;
+
+    /// @notice Error code of a proof that passed every check (see ErrorCode in zk-program/src/error.rs)
+    uint8 public constant SUCCESS = 0;
+
+    /// @notice Operation codes the program commits ahead of each output (see zk-program/src/operation.rs)
+    /// @dev Every operation is proven by the same program, so each entry point only takes its own
+    uint8 public constant OP_DEPOSIT = 0;
+    uint8 public constant OP_BORROW = 1;
+    uint8 public constant OP_TRANSFER = 2;
+    uint8 public constant OP_BATCH_DEPOSIT = 9;
+    uint8 public constant OP_SHIELD = 10;
+
+    /// @notice Collateral markets notes may hold, by slot (slot 0 starts as COLLATERAL_TOKEN)
+    /// @dev Borrow proofs commit the table they valued the note with; it must equal this one
+    CollateralAsset[MAX_COLLATERAL_ASSETS] public collateralAssets;
+
+    /// @notice Depth of the commitment tree (must match MERKLE_DEPTH in zk-program/src/merkle.rs)
+    uint32 public constant MERKLE_DEPTH = 20;
+
     /// @notice Current Merkle root of all commitments
     bytes32 public merkleRoot;
 
+    /// @notice Roots of empty subtrees at each level (level 0 = empty leaf)
+    bytes32[MERKLE_DEPTH] public zeros;
+
+    /// @notice Latest left-hand node at each level, used to append leaves
+    bytes32[MERKLE_DEPTH] public filledSubtrees;
+
+    /// @notice Index of the next leaf to insert
+    uint32 public nextLeafIndex;
+
+    /// @notice Number of recent roots accepted by borrow proofs
+    uint32 public constant ROOT_HISTORY_SIZE = 30;
+
+    /// @notice Ring buffer of recent Merkle roots
+    bytes32[ROOT_HISTORY_SIZE] public rootHistory;
+
+    /// @notice Slot of the current root in rootHistory
+    uint32 public currentRootIndex;
+
+    /// @notice Hash of the oracle key trusted to sign collateral prices (sha256 of its SEC1 public key)
+    bytes32 public oracleKeyHash;
+
+    /// @notice Maximum age of an oracle price accepted by borrow proofs
+    uint256 public constant MAX_PRICE_AGE = 1 hours;
+
+    /// @notice Fixed-point scale of the borrow index (1e18 = no interest accrued)
+    uint256 public constant INDEX_SCALE = 1e18;
+
+    /// @notice Global borrow index; private debt grows with it (notes store debt at their creation index)
+    uint256 public borrowIndex = INDEX_SCALE;
+
+    /// @notice Interest per second applied to the borrow index (1e18 scale)
+    uint256 public borrowRatePerSecond;
+
+    /// @notice Last time the borrow index was accrued
+    uint256 public lastAccrualTime;
+
+    /// @notice Root of the sorted sanctions blocklist (zero = screening off)
+    /// @dev Same tree as zk-program/src/blocklist.rs; when set, depositors and borrow recipients
+    ///      must prove they are not on it
+    bytes32 public blocklistRoot;
+
     /// @notice Mapping of spent nullifiers (prevents double-spending)
     mapping(bytes32 => bool) public nullifiers;
 
@@ -49,6 +154,7 @@ contract AegisVault {
 
     event Deposit(
         bytes32 indexed commitment,
+        uint256 leafIndex,
         uint256 timestamp
     );
 
@@ -57,6 +163,7 @@ contract AegisVault {
         bytes32 indexed newCommitment,
         address indexed recipient,
         uint256 borrowAmount,
+        uint256 leafIndex,
         uint256 timestamp
     );
 
@@ -66,20 +173,68 @@ contract AegisVault {
         address indexed actualUser,
         address relayer,
         uint256 borrowAmount,
+        uint256 leafIndex,
+        uint256 timestamp
+    );
+
+    event Shield(
+        bytes32 indexed commitment,
+        address indexed token,
+        uint256 amount,
+        uint256 leafIndex,
+        uint256 timestamp
+    );
+
+    event ShieldedTransfer(
+        bytes32 indexed nullifierHash,
+        bytes32 recipientCommitment,
+        uint256 recipientLeafIndex,
+        bytes32 changeCommitment,
+        uint256 changeLeafIndex,
         uint256 timestamp
     );
 
+    /// @notice Note contents encrypted to the owner's viewing key, so wallets can be restored
+    /// by scanning these events
+    event NoteEncrypted(bytes32 indexed commitment, uint256 leafIndex, bytes encryptedNote);
+
     event MerkleRootUpdated(bytes32 oldRoot, bytes32 newRoot);
 
+    event OracleKeyHashUpdated(bytes32 oldKeyHash, bytes32 newKeyHash);
+
+    event InterestAccrued(uint256 borrowIndex, uint256 timestamp);
+
+    event BorrowRateUpdated(uint256 oldRate, uint256 newRate);
+
+    event BlocklistRootUpdated(bytes32 oldRoot, bytes32 newRoot);
+
+    event CollateralAssetUpdated(uint256 indexed slot, address token, uint16 ltvBps, uint8 decimals);
+
     // ============ Errors ============
 
     error UnauthorizedCaller();
-    error InvalidProof();
+    error ProofRejected(uint8 errorCode);
     error NullifierAlreadySpent();
     error InsufficientLiquidity();
     error InvalidCommitment();
     error InvalidSignature();
     error SignatureAlreadyUsed();
+    error UnknownMerkleRoot();
+    error MerkleTreeFull();
+    error CollateralTokenMismatch();
+    error UntrustedOracle();
+    error StalePrice();
+    error PriceAssetMismatch();
+    error DecimalsMismatch();
+    error LtvWeightMismatch();
+    error StaleBorrowIndex();
+    error InvalidCollateralSlot();
+    error InvalidLtv();
+    error DuplicateCollateralAsset();
+    error UnscreenedAddress();
+    error EncryptedNoteCountMismatch();
+    error InvalidPublicValuesLength();
+    error WrongOperation(uint8 operation);
 
     // ============ Modifiers ============
 
@@ -106,126 +261,291 @@ contract AegisVault {
         borrowVkey = _borrowVkey;
         COLLATERAL_TOKEN = IERC20(_collateralToken);
         DEBT_TOKEN = IERC20(_debtToken);
+        uint8 collateralDecimals = IERC20Metadata(_collateralToken).decimals();
+        COLLATERAL_DECIMALS = collateralDecimals;
+        DEBT_DECIMALS = IERC20Metadata(_debtToken).decimals();
         owner = msg.sender;
+
+        collateralAssets[0] = CollateralAsset(_collateralToken, DEFAULT_LTV_BPS, collateralDecimals);
         
-        // Initialize with empty merkle root
-        merkleRoot = bytes32(0);
+        // Initialize with the root of an empty tree
+        bytes32 currentZero = bytes32(0);
+        for (uint32 i = 0; i < MERKLE_DEPTH; i++) {
+            zeros[i] = currentZero;
+            filledSubtrees[i] = currentZero;
+            currentZero = _hashPair(currentZero, currentZero);
+        }
+        merkleRoot = currentZero;
+        rootHistory[0] = currentZero;
+
+        lastAccrualTime = block.timestamp;
     }
 
     // ============ Core Functions ============
 
     /// @notice Deposit collateral and create a private commitment
-    /// @param amount Amount of collateral to deposit
+    /// @dev Pulls exactly the token amounts the note commits to, so a note can't claim more
     /// @param proof SP1 ZK proof that commitment is valid
     /// @param publicValues Public outputs from the ZK proof
+    /// @param encryptedNote Note contents encrypted to the owner's viewing key (not checked)
     function deposit(
-        uint256 amount,
         bytes calldata proof,
-        bytes calldata publicValues
+        bytes calldata publicValues,
+        bytes calldata encryptedNote
     ) external {
-        // Transfer collateral from user
-        COLLATERAL_TOKEN.safeTransferFrom(msg.sender, address(this), amount);
-
         // Verify the ZK proof
         VERIFIER.verifyProof(depositVkey, abi.encode(publicValues), proof);
 
-        // Decode the commitment from public values
-        // publicValues format: DepositOutput { commitment_hash: [u8; 32], is_valid: u8 }
+        // Decode the commitment from public values, which are OP_DEPOSIT, then
+        // output format: DepositOutput {
+        //   commitment_hash: [u8; 32]       offset: 0
+        //   collateral: [CollateralPosition; 2], 36 bytes per slot from offset 32 {
+        //     asset: [u8; 20]               offset: 0
+        //     amount: u128 (LE)             offset: 20
+        //   }
+        //   depositor_address: [u8; 20]     offset: 104
+        //   blocklist_root: [u8; 32]        offset: 124
+        //   error_code: u8                  offset: 156
+        // }
+        bytes calldata output = _outputOf(publicValues, OP_DEPOSIT, 157);
+
         bytes32 commitment;
-        uint8 isValid;
+        address depositor;
+        bytes32 screenedRoot;
+        uint8 errorCode;
         
         assembly {
             // Load commitment (first 32 bytes)
-            commitment := calldataload(publicValues.offset)
-            // Load is_valid (next byte)
-            isValid := byte(0, calldataload(add(publicValues.offset, 32)))
+            commitment := calldataload(output.offset)
+            // Load depositor (20 bytes at 104) and blocklist root
+            depositor := shr(96, calldataload(add(output.offset, 104)))
+            screenedRoot := calldataload(add(output.offset, 124))
+            // Load error_code (byte 156)
+            errorCode := byte(0, calldataload(add(output.offset, 156)))
         }
 
-        if (isValid != 1) revert InvalidProof();
+        if (errorCode != SUCCESS) revert ProofRejected(errorCode);
         if (commitment == bytes32(0)) revert InvalidCommitment();
 
+        // The screened address must be the one funding the deposit
+        _checkBlocklist(screenedRoot);
+        if (blocklistRoot != bytes32(0) && depositor != msg.sender) revert UnscreenedAddress();
+
+        // Pull every committed asset; notes may only hold listed collateral
+        _pullCollateral(output, 32);
+
         // Add commitment to tree
-        commitments.push(commitment);
         bytes32 oldRoot = merkleRoot;
-        merkleRoot = keccak256(abi.encodePacked(merkleRoot, commitment));
+        uint32 leafIndex = _insert(commitment);
 
-        emit Deposit(commitment, block.timestamp);
+        emit Deposit(commitment, leafIndex, block.timestamp);
+        emit NoteEncrypted(commitment, leafIndex, encryptedNote);
+        emit MerkleRootUpdated(oldRoot, merkleRoot);
+    }
+
+    /// @notice Deposit collateral into several notes with one proof
+    /// @dev Pulls the batch's total once; every note is funded by the same address
+    /// @param proof SP1 ZK proof that every commitment is valid
+    /// @param publicValues Public outputs from the ZK proof
+    /// @param encryptedNotes Each note encrypted to its owner's viewing key, in commitment order (not checked)
+    function batchDeposit(
+        bytes calldata proof,
+        bytes calldata publicValues,
+        bytes[] calldata encryptedNotes
+    ) external {
+        // Verify the ZK proof
+        VERIFIER.verifyProof(depositVkey, abi.encode(publicValues), proof);
+
+        // Public values are OP_BATCH_DEPOSIT, then
+        // output format: BatchDepositOutput {
+        //   commitment_hashes: [[u8; 32]; 16]    offset: 0
+        //   deposit_count: u8                    offset: 512
+        //   total_collateral: [CollateralPosition; 2], 36 bytes per slot from offset 513
+        //   depositor_address: [u8; 20]          offset: 585
+        //   blocklist_root: [u8; 32]             offset: 605
+        //   error_code: u8                       offset: 637
+        // }
+        bytes calldata output = _outputOf(publicValues, OP_BATCH_DEPOSIT, 638);
+
+        uint8 errorCode = uint8(output[637]);
+        if (errorCode != SUCCESS) revert ProofRejected(errorCode);
+
+        uint256 count = uint8(output[512]);
+        if (encryptedNotes.length != count) revert EncryptedNoteCountMismatch();
+
+        // The screened address must be the one funding the batch
+        address depositor = address(bytes20(output[585:605]));
+        _checkBlocklist(bytes32(output[605:637]));
+        if (blocklistRoot != bytes32(0) && depositor != msg.sender) revert UnscreenedAddress();
+
+        _pullCollateral(output, 513);
+
+        // Add every commitment to the tree
+        bytes32 oldRoot = merkleRoot;
+        for (uint256 i = 0; i < count; i++) {
+            bytes32 commitment = bytes32(output[i * 32:i * 32 + 32]);
+            if (commitment == bytes32(0)) revert InvalidCommitment();
+
+            uint32 leafIndex = _insert(commitment);
+
+            emit Deposit(commitment, leafIndex, block.timestamp);
+            emit NoteEncrypted(commitment, leafIndex, encryptedNotes[i]);
+        }
+
+        emit MerkleRootUpdated(oldRoot, merkleRoot);
+    }
+
+    /// @notice Lock tokens in a balance note that shielded transfers can spend
+    /// @dev Pulls exactly the committed token and amount; balance notes may only hold listed collateral
+    /// @param proof SP1 ZK proof that the commitment holds the token and amount
+    /// @param publicValues Public outputs from the ZK proof
+    /// @param encryptedNote Note contents encrypted to the owner's viewing key (not checked)
+    function shield(
+        bytes calldata proof,
+        bytes calldata publicValues,
+        bytes calldata encryptedNote
+    ) external {
+        // Verify the ZK proof
+        VERIFIER.verifyProof(depositVkey, abi.encode(publicValues), proof);
+
+        // Public values are OP_SHIELD, then
+        // output format: ShieldOutput {
+        //   commitment_hash: [u8; 32]       offset: 0
+        //   token_address: [u8; 20]         offset: 32
+        //   amount: u128 (LE)               offset: 52
+        //   depositor_address: [u8; 20]     offset: 68
+        //   blocklist_root: [u8; 32]        offset: 88
+        //   error_code: u8                  offset: 120
+        // }
+        bytes calldata output = _outputOf(publicValues, OP_SHIELD, 121);
+
+        uint8 errorCode = uint8(output[120]);
+        if (errorCode != SUCCESS) revert ProofRejected(errorCode);
+
+        bytes32 commitment = bytes32(output[0:32]);
+        if (commitment == bytes32(0)) revert InvalidCommitment();
+
+        // The screened address must be the one funding the note
+        address depositor = address(bytes20(output[68:88]));
+        _checkBlocklist(bytes32(output[88:120]));
+        if (blocklistRoot != bytes32(0) && depositor != msg.sender) revert UnscreenedAddress();
+
+        address token = address(bytes20(output[32:52]));
+        uint128 amount = _readUint128(output, 52);
+        if (!isCollateralToken(token)) revert CollateralTokenMismatch();
+        IERC20(token).safeTransferFrom(msg.sender, address(this), amount);
+
+        // Add commitment to tree
+        bytes32 oldRoot = merkleRoot;
+        uint32 leafIndex = _insert(commitment);
+
+        emit Shield(commitment, token, amount, leafIndex, block.timestamp);
+        emit NoteEncrypted(commitment, leafIndex, encryptedNote);
+        emit MerkleRootUpdated(oldRoot, merkleRoot);
+    }
+
+    /// @notice Spend a balance note into a recipient note and a change note
+    /// @dev No tokens move; the token and amounts stay hidden in the notes
+    /// @param proof SP1 ZK proof that the transfer is valid
+    /// @param publicValues Public outputs from the ZK proof
+    /// @param encryptedRecipientNote Recipient note encrypted to the recipient's viewing key (not checked)
+    /// @param encryptedChangeNote Change note encrypted to the sender's viewing key (not checked)
+    function transfer(
+        bytes calldata proof,
+        bytes calldata publicValues,
+        bytes calldata encryptedRecipientNote,
+        bytes calldata encryptedChangeNote
+    ) external {
+        // Verify the ZK proof
+        VERIFIER.verifyProof(borrowVkey, abi.encode(publicValues), proof);
+
+        // Public values are OP_TRANSFER, then
+        // output format: TransferOutput {
+        //   nullifier_hash: [u8; 32]              offset: 0
+        //   recipient_commitment_hash: [u8; 32]   offset: 32
+        //   change_commitment_hash: [u8; 32]      offset: 64
+        //   merkle_root: [u8; 32]                 offset: 96
+        //   error_code: u8                        offset: 128
+        // }
+        bytes calldata output = _outputOf(publicValues, OP_TRANSFER, 129);
+
+        uint8 errorCode = uint8(output[128]);
+        if (errorCode != SUCCESS) revert ProofRejected(errorCode);
+
+        // Spent note must have been proven against a root we actually had
+        if (!isKnownRoot(bytes32(output[96:128]))) revert UnknownMerkleRoot();
+
+        bytes32 nullifierHash = bytes32(output[0:32]);
+        if (nullifiers[nullifierHash]) revert NullifierAlreadySpent();
+        nullifiers[nullifierHash] = true;
+
+        // Add both new notes to the tree
+        bytes32 recipientCommitment = bytes32(output[32:64]);
+        bytes32 changeCommitment = bytes32(output[64:96]);
+        bytes32 oldRoot = merkleRoot;
+        uint32 recipientLeafIndex = _insert(recipientCommitment);
+        uint32 changeLeafIndex = _insert(changeCommitment);
+
+        emit ShieldedTransfer(
+            nullifierHash, recipientCommitment, recipientLeafIndex, changeCommitment, changeLeafIndex, block.timestamp
+        );
+        emit NoteEncrypted(recipientCommitment, recipientLeafIndex, encryptedRecipientNote);
+        emit NoteEncrypted(changeCommitment, changeLeafIndex, encryptedChangeNote);
         emit MerkleRootUpdated(oldRoot, merkleRoot);
     }
 
     /// @notice Borrow funds against hidden collateral
     /// @param proof SP1 ZK proof that borrow is valid
     /// @param publicValues Public outputs from the ZK proof
+    /// @param encryptedNote New note encrypted to the owner's viewing key (not checked)
     function borrow(
         bytes calldata proof,
-        bytes calldata publicValues
+        bytes calldata publicValues,
+        bytes calldata encryptedNote
     ) external {
         // Verify the ZK proof first
         VERIFIER.verifyProof(borrowVkey, abi.encode(publicValues), proof);
 
         // Decode public values from proof
-        // BorrowOutput format from Rust:
-        // - nullifier_hash: [u8; 32]          offset: 0
-        // - new_commitment_hash: [u8; 32]     offset: 32
-        // - recipient_address: [u8; 20]       offset: 64
-        // - borrow_amount: u128               offset: 84
-        // - is_valid: u8                      offset: 100
-        
-        bytes32 nullifierHash;
-        bytes32 newCommitment;
-        address recipient;
-        uint128 borrowAmount;
-        uint8 isValid;
-
-        // Decode from calldata - must handle dynamic bytes carefully
-        require(publicValues.length >= 101, "Invalid public values length");
-        
-        assembly {
-            //Load nullifier (bytes 0-31 of publicValues)
-            nullifierHash := calldataload(publicValues.offset)
-            
-            // Load new commitment (bytes 32-63)
-            newCommitment := calldataload(add(publicValues.offset, 32))
-            
-            // Load recipient address (bytes 64-83)
-            // calldataload loads 32 bytes, but we only want the first 20
-            let addrWord := calldataload(add(publicValues.offset, 64))
-            recipient := shr(96, addrWord) // Shift right 96 bits (12 bytes) to get address
-        }
-        
-        // Borrow amount: bytes 84-99 (16 bytes, little-endian u128)
-        // Read byte by byte from calldata and convert to big-endian
-        for (uint i = 0; i < 16; i++) {
-            // Safe cast: i is loop counter 0-15, fits in uint128
-            // forge-lint: disable-next-line(unsafe-typecast)
-            borrowAmount |= uint128(uint8(publicValues[84 + i])) << (8 * uint128(i));
-        }
-        
-        // Is valid: byte 100
-        isValid = uint8(publicValues[100]);
+        BorrowPublicValues memory out = _decodeBorrowOutput(publicValues);
 
         // Validate proof result
-        if (isValid != 1) revert InvalidProof();
+        if (out.errorCode != SUCCESS) revert ProofRejected(out.errorCode);
+
+        // Old note must have been proven against a root we actually had
+        if (!isKnownRoot(out.merkleRoot)) revert UnknownMerkleRoot();
+
+        // Prices must come from our oracle and be recent
+        _checkOraclePrice(out);
+
+        // LTV must have been computed with our collateral table and token decimals
+        _checkCollateralMarkets(out);
+
+        // Recipient must have been screened against the current blocklist
+        _checkBlocklist(out.blocklistRoot);
+
+        // Debt must have been accrued to the current borrow index
+        if (out.borrowIndex != borrowIndex) revert StaleBorrowIndex();
 
         // Check nullifier not already spent
-        if (nullifiers[nullifierHash]) revert NullifierAlreadySpent();
+        if (nullifiers[out.nullifierHash]) revert NullifierAlreadySpent();
 
         // Check contract has enough liquidity
         uint256 balance = DEBT_TOKEN.balanceOf(address(this));
-        if (balance < borrowAmount) revert InsufficientLiquidity();
+        if (balance < out.borrowAmount) revert InsufficientLiquidity();
 
         // Mark nullifier as spent
-        nullifiers[nullifierHash] = true;
+        nullifiers[out.nullifierHash] = true;
 
         // Add new commitment to tree
-        commitments.push(newCommitment);
         bytes32 oldRoot = merkleRoot;
-        merkleRoot = keccak256(abi.encodePacked(merkleRoot, newCommitment));
+        uint32 leafIndex = _insert(out.newCommitment);
 
         // Transfer borrowed funds to recipient
-        DEBT_TOKEN.safeTransfer(recipient, borrowAmount);
+        DEBT_TOKEN.safeTransfer(out.recipient, out.borrowAmount);
 
-        emit Borrow(nullifierHash, newCommitment, recipient, borrowAmount, block.timestamp);
+        emit Borrow(out.nullifierHash, out.newCommitment, out.recipient, out.borrowAmount, leafIndex, block.timestamp);
+        emit NoteEncrypted(out.newCommitment, leafIndex, encryptedNote);
         emit MerkleRootUpdated(oldRoot, merkleRoot);
     }
 
@@ -234,17 +554,20 @@ contract AegisVault {
     /// @param proof SP1 ZK proof that borrow is valid
     /// @param publicValues Public outputs from the ZK proof
     /// @param nonce Unique nonce to prevent replay attacks
+    /// @param encryptedNote New note encrypted to the owner's viewing key (signed, so a relayer can't swap it)
     function borrowViaRelayer(
         bytes calldata userSignature,
         bytes calldata proof,
         bytes calldata publicValues,
-        uint256 nonce
+        uint256 nonce,
+        bytes calldata encryptedNote
     ) external {
         // Create message hash that user signed
         bytes32 messageHash = keccak256(abi.encodePacked(
             address(this),  // Contract address
             proof,
             publicValues,
+            encryptedNote,
             nonce
         ));
         
@@ -263,68 +586,279 @@ contract AegisVault {
         VERIFIER.verifyProof(borrowVkey, abi.encode(publicValues), proof);
 
         // Decode public values (same format as regular borrow)
-        bytes32 nullifierHash;
-        bytes32 newCommitment;
-        address recipient;
-        uint128 borrowAmount;
-        uint8 isValid;
-        
-        assembly {
-            nullifierHash := calldataload(publicValues.offset)
-            newCommitment := calldataload(add(publicValues.offset, 32))
-            
-            let addrWord := calldataload(add(publicValues.offset, 64))
-            recipient := shr(96, addrWord)
-        }
-        
-        for (uint i = 0; i < 16; i++) {
-            // Safe cast: i is loop counter 0-15, fits in uint128
-            // forge-lint: disable-next-line(unsafe-typecast)
-            borrowAmount |= uint128(uint8(publicValues[84 + i])) << (8 * uint128(i));
-        }
-        
-        isValid = uint8(publicValues[100]);
+        BorrowPublicValues memory out = _decodeBorrowOutput(publicValues);
 
         // Validate proof result
-        if (isValid != 1) revert InvalidProof();
+        if (out.errorCode != SUCCESS) revert ProofRejected(out.errorCode);
+
+        // Old note must have been proven against a root we actually had
+        if (!isKnownRoot(out.merkleRoot)) revert UnknownMerkleRoot();
+
+        // Prices must come from our oracle and be recent
+        _checkOraclePrice(out);
+
+        // LTV must have been computed with our collateral table and token decimals
+        _checkCollateralMarkets(out);
+
+        // Recipient must have been screened against the current blocklist
+        _checkBlocklist(out.blocklistRoot);
+
+        // Debt must have been accrued to the current borrow index
+        if (out.borrowIndex != borrowIndex) revert StaleBorrowIndex();
 
         // Check nullifier not already spent
-        if (nullifiers[nullifierHash]) revert NullifierAlreadySpent();
+        if (nullifiers[out.nullifierHash]) revert NullifierAlreadySpent();
 
         // Check contract has enough liquidity
         uint256 balance = DEBT_TOKEN.balanceOf(address(this));
-        if (balance < borrowAmount) revert InsufficientLiquidity();
+        if (balance < out.borrowAmount) revert InsufficientLiquidity();
 
         // Mark nullifier as spent
-        nullifiers[nullifierHash] = true;
+        nullifiers[out.nullifierHash] = true;
 
         // Add new commitment to tree
-        commitments.push(newCommitment);
         bytes32 oldRoot = merkleRoot;
-        merkleRoot = keccak256(abi.encodePacked(merkleRoot, newCommitment));
+        uint32 leafIndex = _insert(out.newCommitment);
 
         // Transfer borrowed funds to recipient (could be different from signer)
-        DEBT_TOKEN.safeTransfer(recipient, borrowAmount);
+        DEBT_TOKEN.safeTransfer(out.recipient, out.borrowAmount);
 
         // Emit special event showing relayer was used
         emit BorrowViaRelayer(
-            nullifierHash,
-            newCommitment,
+            out.nullifierHash,
+            out.newCommitment,
             actualUser,      // Real user (from signature)
             msg.sender,      // Relayer address (visible on-chain)
-            borrowAmount,
+            out.borrowAmount,
+            leafIndex,
             block.timestamp
         );
+        emit NoteEncrypted(out.newCommitment, leafIndex, encryptedNote);
         emit MerkleRootUpdated(oldRoot, merkleRoot);
     }
 
+    // ============ Internal Functions ============
+
+    /// @notice Decode BorrowOutput public values committed by the Rust program
+    /// @dev OP_BORROW, then the output's layout (bincode, fixed-size fields, offsets from the output):
+    /// - nullifier_hash: [u8; 32]          offset: 0
+    /// - new_commitment_hash: [u8; 32]     offset: 32
+    /// - recipient_address: [u8; 20]       offset: 64
+    /// - borrow_amount: u128 (LE)          offset: 84
+    /// - merkle_root: [u8; 32]             offset: 100
+    /// - oracle_key_hash: [u8; 32]         offset: 132
+    /// - price_timestamp: u64 (LE)         offset: 164
+    /// - collateral_assets: [[u8; 20]; 2]  offset: 172
+    /// - ltv_weights_bps: [u16 (LE); 2]    offset: 212
+    /// - collateral_decimals: [u8; 2]      offset: 216
+    /// - debt_decimals: u8                 offset: 218
+    /// - borrow_index: u128 (LE)           offset: 219
+    /// - blocklist_root: [u8; 32]          offset: 235
+    /// - error_code: u8                    offset: 267
+    function _decodeBorrowOutput(
+        bytes calldata publicValues
+    ) internal pure returns (BorrowPublicValues memory out) {
+        bytes calldata output = _outputOf(publicValues, OP_BORROW, 268);
+
+        bytes32 nullifierHash;
+        bytes32 newCommitment;
+        address recipient;
+        bytes32 provenRoot;
+        bytes32 signerKeyHash;
+
+        assembly {
+            nullifierHash := calldataload(output.offset)
+            newCommitment := calldataload(add(output.offset, 32))
+
+            // calldataload loads 32 bytes, but we only want the first 20
+            let addrWord := calldataload(add(output.offset, 64))
+            recipient := shr(96, addrWord) // Shift right 96 bits (12 bytes) to get address
+
+            provenRoot := calldataload(add(output.offset, 100))
+            signerKeyHash := calldataload(add(output.offset, 132))
+        }
+
+        out.nullifierHash = nullifierHash;
+        out.newCommitment = newCommitment;
+        out.recipient = recipient;
+        out.merkleRoot = provenRoot;
+        out.oracleKeyHash = signerKeyHash;
+
+        // Borrow amount: bytes 84-99 (16 bytes, little-endian u128)
+        // Read byte by byte from calldata and convert to big-endian
+        for (uint i = 0; i < 16; i++) {
+            // Safe cast: i is loop counter 0-15, fits in uint128
+            // forge-lint: disable-next-line(unsafe-typecast)
+            out.borrowAmount |= uint128(uint8(output[84 + i])) << (8 * uint128(i));
+        }
+
+        // Price timestamp: bytes 164-171 (8 bytes, little-endian u64)
+        for (uint i = 0; i < 8; i++) {
+            // Safe cast: i is loop counter 0-7, fits in uint64
+            // forge-lint: disable-next-line(unsafe-typecast)
+            out.priceTimestamp |= uint64(uint8(output[164 + i])) << (8 * uint64(i));
+        }
+
+        // Collateral table: 20-byte assets, then little-endian u16 weights, then decimals
+        for (uint256 slot = 0; slot < MAX_COLLATERAL_ASSETS; slot++) {
+            uint256 assetOffset = 172 + slot * 20;
+            out.collateralAssets[slot] = address(bytes20(output[assetOffset:assetOffset + 20]));
+            out.ltvWeightsBps[slot] =
+                uint16(uint8(output[212 + slot * 2])) | (uint16(uint8(output[213 + slot * 2])) << 8);
+            out.collateralDecimals[slot] = uint8(output[216 + slot]);
+        }
+
+        out.debtDecimals = uint8(output[218]);
+
+        // Borrow index: bytes 219-234 (16 bytes, little-endian u128)
+        out.borrowIndex = _readUint128(output, 219);
+
+        out.blocklistRoot = bytes32(output[235:267]);
+
+        out.errorCode = uint8(output[267]);
+    }
+
+    /// @notice The output in `publicValues`, checked to be of `operation` and `outputLength` bytes long
+    /// @dev The program commits the operation code, then the output; output offsets start after the code
+    function _outputOf(
+        bytes calldata publicValues,
+        uint8 operation,
+        uint256 outputLength
+    ) internal pure returns (bytes calldata output) {
+        if (publicValues.length != 1 + outputLength) revert InvalidPublicValuesLength();
+        if (uint8(publicValues[0]) != operation) revert WrongOperation(uint8(publicValues[0]));
+        return publicValues[1:];
+    }
+
+    /// @notice Pull the collateral positions encoded from `offset` (36 bytes per slot) from the caller
+    /// @dev Notes may only hold listed collateral
+    function _pullCollateral(bytes calldata publicValues, uint256 offset) internal {
+        for (uint256 slot = 0; slot < MAX_COLLATERAL_ASSETS; slot++) {
+            uint256 slotOffset = offset + slot * 36;
+            address token = address(bytes20(publicValues[slotOffset:slotOffset + 20]));
+            uint128 amount = _readUint128(publicValues, slotOffset + 20);
+
+            if (amount == 0) continue;
+            if (!isCollateralToken(token)) revert CollateralTokenMismatch();
+
+            IERC20(token).safeTransferFrom(msg.sender, address(this), amount);
+        }
+    }
+
+    /// @notice Read a little-endian u128 at `offset`
+    function _readUint128(bytes calldata data, uint256 offset) internal pure returns (uint128 value) {
+        for (uint i = 0; i < 16; i++) {
+            // Safe cast: i is loop counter 0-15, fits in uint128
+            // forge-lint: disable-next-line(unsafe-typecast)
+            value |= uint128(uint8(data[offset + i])) << (8 * uint128(i));
+        }
+    }
+
+    /// @notice Reject prices not signed by the trusted oracle or too old
+    /// @dev priceTimestamp is the oldest of the prices the proof used
+    function _checkOraclePrice(BorrowPublicValues memory out) internal view {
+        if (oracleKeyHash == bytes32(0) || out.oracleKeyHash != oracleKeyHash) revert UntrustedOracle();
+        if (out.priceTimestamp > block.timestamp || block.timestamp - out.priceTimestamp > MAX_PRICE_AGE) {
+            revert StalePrice();
+        }
+    }
+
+    /// @notice Reject LTV checks done with another collateral table or other token decimals
+    function _checkCollateralMarkets(BorrowPublicValues memory out) internal view {
+        for (uint256 slot = 0; slot < MAX_COLLATERAL_ASSETS; slot++) {
+            CollateralAsset memory market = collateralAssets[slot];
+            if (out.collateralAssets[slot] != market.token) revert PriceAssetMismatch();
+            if (out.ltvWeightsBps[slot] != market.ltvBps) revert LtvWeightMismatch();
+            if (out.collateralDecimals[slot] != market.decimals) revert DecimalsMismatch();
+        }
+        if (out.debtDecimals != DEBT_DECIMALS) revert DecimalsMismatch();
+    }
+
+    /// @notice Reject operations not screened against the current blocklist, if one is set
+    function _checkBlocklist(bytes32 screenedRoot) internal view {
+        if (blocklistRoot != bytes32(0) && screenedRoot != blocklistRoot) revert UnscreenedAddress();
+    }
+
+    /// @notice Grow the borrow index by the interest accrued since the last accrual
+    /// @dev Anyone may call this (e.g. a keeper); borrows don't accrue implicitly so that
+    ///      proofs generated against the current index stay valid until the next accrual
+    function accrueInterest() public {
+        uint256 elapsed = block.timestamp - lastAccrualTime;
+        if (elapsed == 0) return;
+
+        borrowIndex += (borrowIndex * borrowRatePerSecond * elapsed) / INDEX_SCALE;
+        lastAccrualTime = block.timestamp;
+
+        emit InterestAccrued(borrowIndex, block.timestamp);
+    }
+
+    /// @notice Append a commitment to the tree and return its leaf index
+    /// @dev Same incremental tree as zk-program/src/merkle.rs, so roots match byte-for-byte
+    function _insert(bytes32 leaf) internal returns (uint32 index) {
+        index = nextLeafIndex;
+        if (index >= uint32(1) << MERKLE_DEPTH) revert MerkleTreeFull();
+
+        uint32 currentIndex = index;
+        bytes32 currentHash = leaf;
+        for (uint32 i = 0; i < MERKLE_DEPTH; i++) {
+            if (currentIndex % 2 == 0) {
+                filledSubtrees[i] = currentHash;
+                currentHash = _hashPair(currentHash, zeros[i]);
+            } else {
+                currentHash = _hashPair(filledSubtrees[i], currentHash);
+            }
+            currentIndex /= 2;
+        }
+
+        nextLeafIndex = index + 1;
+        commitments.push(leaf);
+        merkleRoot = currentHash;
+        _recordRoot(currentHash);
+    }
+
+    /// @notice Hash two child nodes into their parent: sha256(left || right)
+    /// @dev Must match hash_pair in zk-program/src/merkle.rs (AegisVaultKeccak overrides it)
+    function _hashPair(bytes32 left, bytes32 right) internal pure virtual returns (bytes32) {
+        return sha256(abi.encodePacked(left, right));
+    }
+
+    /// @notice Store a new root in the history ring buffer
+    function _recordRoot(bytes32 root) internal {
+        currentRootIndex = (currentRootIndex + 1) % ROOT_HISTORY_SIZE;
+        rootHistory[currentRootIndex] = root;
+    }
+
     // ============ View Functions ============
 
+    /// @notice Check whether a root is one of the last ROOT_HISTORY_SIZE roots
+    function isKnownRoot(bytes32 root) public view returns (bool) {
+        if (root == bytes32(0)) return false;
+
+        uint32 i = currentRootIndex;
+        do {
+            if (rootHistory[i] == root) return true;
+            if (i == 0) i = ROOT_HISTORY_SIZE;
+            i--;
+        } while (i != currentRootIndex);
+
+        return false;
+    }
+
     /// @notice Get the total number of commitments
     function getCommitmentCount() external view returns (uint256) {
         return commitments.length;
     }
 
+    /// @notice Check whether a token is listed in the collateral table
+    function isCollateralToken(address token) public view returns (bool) {
+        if (token == address(0)) return false;
+
+        for (uint256 slot = 0; slot < MAX_COLLATERAL_ASSETS; slot++) {
+            if (collateralAssets[slot].token == token) return true;
+        }
+        return false;
+    }
+
     /// @notice Get a commitment by index
     function getCommitment(uint256 index) external view returns (bytes32) {
         return commitments[index];
@@ -352,6 +886,44 @@ contract AegisVault {
         DEBT_TOKEN.safeTransferFrom(msg.sender, address(this), amount);
     }
 
+    /// @notice Set the oracle key trusted to sign collateral prices
+    function setOracleKeyHash(bytes32 _oracleKeyHash) external onlyOwner {
+        emit OracleKeyHashUpdated(oracleKeyHash, _oracleKeyHash);
+        oracleKeyHash = _oracleKeyHash;
+    }
+
+    /// @notice List `token` as collateral in `slot` with an LTV weight (token zero clears the slot)
+    /// @dev Slot 0 can't be cleared: borrow proofs take the oracle key from its price.
+    ///      Notes holding a delisted token can't borrow against it until it is listed again.
+    function setCollateralAsset(uint256 slot, address token, uint16 ltvBps) external onlyOwner {
+        if (slot >= MAX_COLLATERAL_ASSETS || (slot == 0 && token == address(0))) revert InvalidCollateralSlot();
+        if (ltvBps > BPS_DENOMINATOR || (token == address(0) && ltvBps != 0)) revert InvalidLtv();
+        for (uint256 other = 0; other < MAX_COLLATERAL_ASSETS; other++) {
+            if (other != slot && token != address(0) && collateralAssets[other].token == token) {
+                revert DuplicateCollateralAsset();
+            }
+        }
+
+        uint8 decimals = token == address(0) ? 0 : IERC20Metadata(token).decimals();
+        collateralAssets[slot] = CollateralAsset(token, ltvBps, decimals);
+
+        emit CollateralAssetUpdated(slot, token, ltvBps, decimals);
+    }
+
+    /// @notice Set the sanctions blocklist root (zero turns screening off)
+    /// @dev Proofs screened against the previous list stop being accepted
+    function setBlocklistRoot(bytes32 _blocklistRoot) external onlyOwner {
+        emit BlocklistRootUpdated(blocklistRoot, _blocklistRoot);
+        blocklistRoot = _blocklistRoot;
+    }
+
+    /// @notice Set the per-second borrow rate (accrues at the old rate first)
+    function setBorrowRate(uint256 _borrowRatePerSecond) external onlyOwner {
+        accrueInterest();
+        emit BorrowRateUpdated(borrowRatePerSecond, _borrowRatePerSecond);
+        borrowRatePerSecond = _borrowRatePerSecond;
+    }
+
     /// @notice Update verification keys (emergency use only)
     function updateVkeys(bytes32 _depositVkey, bytes32 _borrowVkey) external onlyOwner {
         depositVkey = _depositVkey;
@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "./AegisVault.sol";
+
+/// @title AegisVaultKeccak - AegisVault with a keccak256 commitment tree
+/// @notice For ZK programs built with `--features keccak`, which hash Merkle nodes, commitments
+/// and nullifiers with keccak256, so the vault can recompute any of them cheaply
+contract AegisVaultKeccak is AegisVault {
+    constructor(
+        address _verifier,
+        bytes32 _depositVkey,
+        bytes32 _borrowVkey,
+        address _collateralToken,
+        address _debtToken
+    ) AegisVault(_verifier, _depositVkey, _borrowVkey, _collateralToken, _debtToken) {}
+
+    /// @notice Hash two child nodes into their parent: keccak256(left || right)
+    /// @dev Must match hash_pair in zk-program/src/merkle.rs built with the keccak feature
+    function _hashPair(bytes32 left, bytes32 right) internal pure override returns (bytes32) {
+        return keccak256(abi.encodePacked(left, right));
+    }
+}
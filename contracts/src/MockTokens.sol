@@ -41,3 +41,17 @@ contract MockUSDC is ERC20, Ownable {
         return 6;
     }
 }
+
+/// @title MockWMNT - Mock second collateral token (simulates wrapped MNT)
+/// @notice For testing purposes only
+contract MockWMNT is ERC20, Ownable {
+    constructor() ERC20("Mock Wrapped MNT", "WMNT") Ownable(msg.sender) {
+        // Mint initial supply to deployer
+        _mint(msg.sender, 1000000 * 10**decimals());
+    }
+
+    /// @notice Mint tokens for testing
+    function mint(address to, uint256 amount) external onlyOwner {
+        _mint(to, amount);
+    }
+}
@@ -3,6 +3,7 @@ pragma solidity ^0.8.20;
 
 import "forge-std/Test.sol";
 import "../src/AegisVault.sol";
+import "../src/AegisVaultKeccak.sol";
 import "../src/MockTokens.sol";
 
 /// @title AegisVault Tests
@@ -11,6 +12,7 @@ contract AegisVaultTest is Test {
     AegisVault public vault;
     MockETH public collateral;
     MockUSDC public debt;
+    MockWMNT public wmnt;
 
     address public owner = address(this);
     address public alice = address(0x1);
@@ -23,10 +25,31 @@ contract AegisVaultTest is Test {
     bytes32 public depositVkey = keccak256("DEPOSIT_VKEY");
     bytes32 public borrowVkey = keccak256("BORROW_VKEY");
 
+    // Mock oracle key hash trusted by the vault
+    bytes32 public oracleKeyHash = keccak256("ORACLE_KEY");
+
+    // Error codes committed by the Rust program (ErrorCode in zk-program/src/error.rs)
+    uint8 internal constant SUCCESS = 0;
+    uint8 internal constant LTV_EXCEEDED = 8;
+    uint8 internal constant BLOCKLISTED = 13;
+    uint8 internal constant BATCH_MISMATCH = 22;
+
+    // Operation codes committed ahead of each output (zk-program/src/operation.rs)
+    uint8 internal constant OP_DEPOSIT = 0;
+    uint8 internal constant OP_BORROW = 1;
+    uint8 internal constant OP_TRANSFER = 2;
+    uint8 internal constant OP_DISCLOSURE = 7;
+    uint8 internal constant OP_BATCH_DEPOSIT = 9;
+    uint8 internal constant OP_SHIELD = 10;
+
+    // Commitment and nullifier hash layout version (HASH_VERSION in zk-program/src/hashing.rs)
+    uint8 internal constant HASH_VERSION = 1;
+
     function setUp() public {
         // Deploy mock tokens
         collateral = new MockETH();
         debt = new MockUSDC();
+        wmnt = new MockWMNT();
 
         // Deploy mock verifier
         verifier = new MockSP1Verifier();
@@ -40,8 +63,12 @@ contract AegisVaultTest is Test {
             address(debt)
         );
 
+        // Trust the mock oracle
+        vault.setOracleKeyHash(oracleKeyHash);
+
         // Mint tokens to test users
         collateral.mint(alice, 100 ether);
+        wmnt.mint(alice, 10_000 ether);
         debt.mint(owner, 1000000e6); // 1M USDC
 
         // Fund the vault with debt tokens
@@ -65,12 +92,12 @@ contract AegisVaultTest is Test {
         // Create mock proof and public values
         bytes memory proof = hex"00"; // Dummy proof
         
-        // Mock public values: commitment_hash (32 bytes) + is_valid (1 byte)
+        // Mock public values: commitment_hash + collateral slots + error_code
         bytes32 commitment = keccak256("test_commitment");
-        bytes memory publicValues = abi.encodePacked(commitment, uint8(1));
+        bytes memory publicValues = _encodeDepositOutput(commitment, 10 ether, address(collateral), SUCCESS);
 
         // Perform deposit
-        vault.deposit(depositAmount, proof, publicValues);
+        vault.deposit(proof, publicValues, "");
 
         // Verify state changes
         assertEq(vault.getCommitmentCount(), 1);
@@ -80,6 +107,179 @@ contract AegisVaultTest is Test {
         vm.stopPrank();
     }
 
+    function testDepositPullsCommittedAmount() public {
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 1);
+
+        // Committing to 1,000 ETH pulls 1,000 ETH, so a 1 wei approval can't back the note
+        bytes memory publicValues = _encodeDepositOutput(keccak256("inflated"), 1000 ether, address(collateral), SUCCESS);
+
+        vm.expectRevert();
+        vault.deposit(hex"00", publicValues, "");
+        vm.stopPrank();
+
+        assertEq(vault.getCommitmentCount(), 0);
+    }
+
+    function testDepositPublishesEncryptedNote() public {
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 1 ether);
+
+        bytes32 commitment = keccak256("encrypted");
+        bytes memory encryptedNote = hex"02aabbcc";
+
+        vm.expectEmit(true, false, false, true, address(vault));
+        emit AegisVault.NoteEncrypted(commitment, 0, encryptedNote);
+        vault.deposit(hex"00", _encodeDepositOutput(commitment, 1 ether, address(collateral), SUCCESS), encryptedNote);
+        vm.stopPrank();
+    }
+
+    function testMultiCollateralDeposit() public {
+        vault.setCollateralAsset(1, address(wmnt), 5000);
+
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 1 ether);
+        wmnt.approve(address(vault), 2000 ether);
+
+        // One note backed by 1 mETH and 2,000 WMNT
+        bytes memory publicValues = abi.encodePacked(
+            OP_DEPOSIT,
+            keccak256("two_assets"),
+            address(collateral),
+            _toLittleEndian(1 ether, 16),
+            address(wmnt),
+            _toLittleEndian(2000 ether, 16),
+            address(0),
+            bytes32(0),
+            uint8(1)
+        );
+        vault.deposit(hex"00", publicValues, "");
+        vm.stopPrank();
+
+        assertEq(vault.getCommitmentCount(), 1);
+        assertEq(collateral.balanceOf(address(vault)), 1 ether);
+        assertEq(wmnt.balanceOf(address(vault)), 2000 ether);
+    }
+
+    function testSetCollateralAsset() public {
+        vault.setCollateralAsset(1, address(wmnt), 5000);
+
+        (address token, uint16 ltvBps, uint8 decimals) = vault.collateralAssets(1);
+        assertEq(token, address(wmnt));
+        assertEq(ltvBps, 5000);
+        assertEq(decimals, 18);
+        assertTrue(vault.isCollateralToken(address(wmnt)));
+
+        // Clearing a slot delists the token
+        vault.setCollateralAsset(1, address(0), 0);
+        assertFalse(vault.isCollateralToken(address(wmnt)));
+
+        vm.expectRevert(AegisVault.DuplicateCollateralAsset.selector);
+        vault.setCollateralAsset(1, address(collateral), 5000);
+
+        vm.expectRevert(AegisVault.InvalidCollateralSlot.selector);
+        vault.setCollateralAsset(0, address(0), 0);
+
+        vm.expectRevert(AegisVault.InvalidCollateralSlot.selector);
+        vault.setCollateralAsset(2, address(wmnt), 5000);
+
+        vm.expectRevert(AegisVault.InvalidLtv.selector);
+        vault.setCollateralAsset(1, address(wmnt), 10_001);
+
+        vm.prank(alice);
+        vm.expectRevert(AegisVault.UnauthorizedCaller.selector);
+        vault.setCollateralAsset(1, address(wmnt), 5000);
+    }
+
+    function testDepositRevertsOnTokenMismatch() public {
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 1 ether);
+
+        bytes memory publicValues = _encodeDepositOutput(keccak256("wrong_token"), 1 ether, address(debt), SUCCESS);
+
+        vm.expectRevert(AegisVault.CollateralTokenMismatch.selector);
+        vault.deposit(hex"00", publicValues, "");
+        vm.stopPrank();
+    }
+
+    function testBatchDepositCreatesEveryCommitment() public {
+        bytes32[] memory commitments = new bytes32[](3);
+        bytes[] memory encryptedNotes = new bytes[](3);
+        for (uint256 i = 0; i < 3; i++) {
+            commitments[i] = keccak256(abi.encode("batch", i));
+            encryptedNotes[i] = abi.encode("note", i);
+        }
+
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 6 ether);
+
+        // The total is pulled once, then each note gets its own leaf
+        vm.expectEmit(true, false, false, true, address(vault));
+        emit AegisVault.NoteEncrypted(commitments[2], 2, encryptedNotes[2]);
+        vault.batchDeposit(hex"00", _encodeBatchDepositOutput(commitments, 6 ether, SUCCESS), encryptedNotes);
+        vm.stopPrank();
+
+        assertEq(vault.getCommitmentCount(), 3);
+        assertEq(vault.getCollateralBalance(), 6 ether);
+        for (uint256 i = 0; i < 3; i++) {
+            assertEq(vault.getCommitment(i), commitments[i]);
+        }
+    }
+
+    function testBatchDepositRevertsOnRejectedProof() public {
+        bytes32[] memory commitments = new bytes32[](2);
+        commitments[0] = keccak256("batch0");
+        commitments[1] = keccak256("batch1");
+
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 2 ether);
+
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.ProofRejected.selector, BATCH_MISMATCH));
+        vault.batchDeposit(hex"00", _encodeBatchDepositOutput(commitments, 2 ether, BATCH_MISMATCH), new bytes[](2));
+        vm.stopPrank();
+
+        assertEq(vault.getCommitmentCount(), 0);
+    }
+
+    function testBatchDepositRevertsOnEncryptedNoteCountMismatch() public {
+        bytes32[] memory commitments = new bytes32[](2);
+        commitments[0] = keccak256("batch0");
+        commitments[1] = keccak256("batch1");
+
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 2 ether);
+
+        // Every note needs its ciphertext, or its owner can't find it
+        vm.expectRevert(AegisVault.EncryptedNoteCountMismatch.selector);
+        vault.batchDeposit(hex"00", _encodeBatchDepositOutput(commitments, 2 ether, SUCCESS), new bytes[](1));
+        vm.stopPrank();
+    }
+
+    function testDepositRejectsBatchPublicValues() public {
+        bytes32[] memory commitments = new bytes32[](1);
+        commitments[0] = keccak256("batch0");
+
+        // Batch and single deposits share a verification key; a one-note batch that pulls nothing
+        // must not pass as a deposit whose commitment is read from the batch's first entry
+        bytes memory publicValues = _encodeBatchDepositOutput(commitments, 0, SUCCESS);
+
+        vm.prank(alice);
+        vm.expectRevert(AegisVault.InvalidPublicValuesLength.selector);
+        vault.deposit(hex"00", publicValues, "");
+
+        assertEq(vault.getCommitmentCount(), 0);
+    }
+
+    function testDepositRejectsOtherOperation() public {
+        // A deposit-sized output committed by another operation
+        bytes memory publicValues = _encodeDepositOutput(keccak256("other_op"), 0, address(collateral), SUCCESS);
+        publicValues[0] = bytes1(OP_BATCH_DEPOSIT);
+
+        vm.prank(alice);
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.WrongOperation.selector, OP_BATCH_DEPOSIT));
+        vault.deposit(hex"00", publicValues, "");
+    }
+
     function testBorrowWithValidProof() public {
         // First, setup a deposit (alice deposits)
         vm.startPrank(alice);
@@ -88,8 +288,8 @@ contract AegisVaultTest is Test {
         
         bytes32 depositCommitment = keccak256("alice_deposit");
         bytes memory depositProof = hex"00";
-        bytes memory depositPublicValues = abi.encodePacked(depositCommitment, uint8(1));
-        vault.deposit(depositAmount, depositProof, depositPublicValues);
+        bytes memory depositPublicValues = _encodeDepositOutput(depositCommitment, 10 ether, address(collateral), SUCCESS);
+        vault.deposit(depositProof, depositPublicValues, "");
         vm.stopPrank();
 
         // Now bob borrows (using alice's hidden collateral via ZK proof)
@@ -105,7 +305,8 @@ contract AegisVaultTest is Test {
             // Safe cast: borrowAmount is within uint128 range for testing
             // forge-lint: disable-next-line(unsafe-typecast)
             uint128(borrowAmount),
-            1 // is_valid
+            vault.merkleRoot(),
+            SUCCESS
         );
         bytes memory borrowProof = hex"01";
 
@@ -113,7 +314,7 @@ contract AegisVaultTest is Test {
 
         // Execute borrow
         vm.prank(bob);
-        vault.borrow(borrowProof, borrowPublicValues);
+        vault.borrow(borrowProof, borrowPublicValues, "");
 
         // Verify borrow succeeded
         assertEq(debt.balanceOf(bob), bobBalanceBefore + borrowAmount);
@@ -127,8 +328,8 @@ contract AegisVaultTest is Test {
         collateral.approve(address(vault), 10 ether);
         bytes memory depositProof = hex"00";
         bytes32 depositCommitment = keccak256("commitment1");
-        bytes memory depositPublicValues = abi.encodePacked(depositCommitment, uint8(1));
-        vault.deposit(10 ether, depositProof, depositPublicValues);
+        bytes memory depositPublicValues = _encodeDepositOutput(depositCommitment, 10 ether, address(collateral), SUCCESS);
+        vault.deposit(depositProof, depositPublicValues, "");
         vm.stopPrank();
 
         // First borrow
@@ -137,20 +338,428 @@ contract AegisVaultTest is Test {
         uint128 borrowAmt = 1000e6;
         
         bytes memory borrowPublicValues1 = _encodeBorrowOutput(
-            nullifier, newCommitment1, bob, borrowAmt, 1
+            nullifier, newCommitment1, bob, borrowAmt, vault.merkleRoot(), SUCCESS
         );
         
-        vault.borrow(hex"01", borrowPublicValues1);
+        vault.borrow(hex"01", borrowPublicValues1, "");
 
         // Attempt second borrow with same nullifier (should fail)
         bytes32 newCommitment2 = keccak256("commitment3");
         bytes memory borrowPublicValues2 = _encodeBorrowOutput(
             nullifier, // Same nullifier!
-            newCommitment2, bob, borrowAmt, 1
+            newCommitment2, bob, borrowAmt, vault.merkleRoot(), SUCCESS
+        );
+
+        vm.expectRevert(AegisVault.NullifierAlreadySpent.selector);
+        vault.borrow(hex"01", borrowPublicValues2, "");
+    }
+
+    function testBorrowRevertsOnUnknownRoot() public {
+        // Setup: alice deposits
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 10 ether);
+        bytes memory depositPublicValues = _encodeDepositOutput(keccak256("commitment1"), 10 ether, address(collateral), SUCCESS);
+        vault.deposit(hex"00", depositPublicValues, "");
+        vm.stopPrank();
+
+        // Proof against a root the vault never had
+        bytes memory borrowPublicValues = _encodeBorrowOutput(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, keccak256("fake_root"), SUCCESS
+        );
+
+        vm.expectRevert(AegisVault.UnknownMerkleRoot.selector);
+        vault.borrow(hex"01", borrowPublicValues, "");
+    }
+
+    function testRejectedProofsRevertWithErrorCode() public {
+        _depositForBorrow();
+
+        // The program's reason is surfaced so callers can tell users why
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 1 ether);
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.ProofRejected.selector, BLOCKLISTED));
+        vault.deposit(hex"00", _encodeDepositOutput(keccak256("blocked"), 1 ether, address(collateral), BLOCKLISTED), "");
+        vm.stopPrank();
+
+        bytes memory borrowPublicValues = _encodeBorrowOutput(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot(), LTV_EXCEEDED
+        );
+
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.ProofRejected.selector, LTV_EXCEEDED));
+        vault.borrow(hex"01", borrowPublicValues, "");
+    }
+
+    function testEntryPointsRejectDisclosureProofs() public {
+        _depositForBorrow();
+
+        // Disclosure outputs hold prover-chosen fields; whatever their shape, their code gives them away
+        bytes memory depositPublicValues = _encodeDepositOutput(keccak256("disclosed"), 0, address(collateral), SUCCESS);
+        depositPublicValues[0] = bytes1(OP_DISCLOSURE);
+        bytes memory batchPublicValues = _encodeBatchDepositOutput(new bytes32[](1), 0, SUCCESS);
+        batchPublicValues[0] = bytes1(OP_DISCLOSURE);
+        bytes memory borrowPublicValues = _encodeBorrowOutput(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot(), SUCCESS
+        );
+        borrowPublicValues[0] = bytes1(OP_DISCLOSURE);
+
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.WrongOperation.selector, OP_DISCLOSURE));
+        vault.deposit(hex"00", depositPublicValues, "");
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.WrongOperation.selector, OP_DISCLOSURE));
+        vault.batchDeposit(hex"00", batchPublicValues, new bytes[](1));
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.WrongOperation.selector, OP_DISCLOSURE));
+        vault.borrow(hex"01", borrowPublicValues, "");
+
+        bytes memory shieldPublicValues = _encodeShieldOutput(keccak256("disclosed"), 0, SUCCESS);
+        shieldPublicValues[0] = bytes1(OP_DISCLOSURE);
+        bytes memory transferPublicValues = _encodeTransferOutput(
+            keccak256("nullifier1"), keccak256("recipient"), keccak256("change"), vault.merkleRoot(), SUCCESS
+        );
+        transferPublicValues[0] = bytes1(OP_DISCLOSURE);
+
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.WrongOperation.selector, OP_DISCLOSURE));
+        vault.shield(hex"00", shieldPublicValues, "");
+        vm.expectRevert(abi.encodeWithSelector(AegisVault.WrongOperation.selector, OP_DISCLOSURE));
+        vault.transfer(hex"01", transferPublicValues, "", "");
+    }
+
+    function testShieldCreatesBalanceNote() public {
+        bytes32 commitment = keccak256("balance_note");
+
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 3 ether);
+        vm.expectEmit(true, true, false, true, address(vault));
+        emit AegisVault.Shield(commitment, address(collateral), 3 ether, 0, block.timestamp);
+        vault.shield(hex"00", _encodeShieldOutput(commitment, 3 ether, SUCCESS), "");
+        vm.stopPrank();
+
+        assertEq(vault.getCommitmentCount(), 1);
+        assertEq(vault.getCommitment(0), commitment);
+        assertEq(vault.getCollateralBalance(), 3 ether);
+    }
+
+    function testShieldRevertsOnUnlistedToken() public {
+        bytes memory publicValues = _encodeShieldOutput(keccak256("balance_note"), 3 ether, SUCCESS);
+        // Token lives at output offset 32, after the operation code
+        bytes20 token = bytes20(address(wmnt));
+        for (uint256 i = 0; i < 20; i++) {
+            publicValues[33 + i] = token[i];
+        }
+
+        vm.startPrank(alice);
+        wmnt.approve(address(vault), 3 ether);
+        vm.expectRevert(AegisVault.CollateralTokenMismatch.selector);
+        vault.shield(hex"00", publicValues, "");
+        vm.stopPrank();
+    }
+
+    function testTransferSpendsBalanceNote() public {
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 3 ether);
+        vault.shield(hex"00", _encodeShieldOutput(keccak256("balance_note"), 3 ether, SUCCESS), "");
+        vm.stopPrank();
+
+        bytes32 nullifier = keccak256("balance_nullifier");
+        bytes memory publicValues = _encodeTransferOutput(
+            nullifier, keccak256("recipient"), keccak256("change"), vault.merkleRoot(), SUCCESS
         );
 
+        // Anyone may submit it: no tokens move, both new notes join the tree
+        vm.prank(bob);
+        vault.transfer(hex"01", publicValues, "", "");
+
+        assertTrue(vault.isNullifierSpent(nullifier));
+        assertEq(vault.getCommitmentCount(), 3);
+        assertEq(vault.getCommitment(1), keccak256("recipient"));
+        assertEq(vault.getCommitment(2), keccak256("change"));
+        assertEq(vault.getCollateralBalance(), 3 ether);
+
+        // The spent note can't be spent again
         vm.expectRevert(AegisVault.NullifierAlreadySpent.selector);
-        vault.borrow(hex"01", borrowPublicValues2);
+        vault.transfer(hex"01", publicValues, "", "");
+    }
+
+    function testTransferRevertsOnUnknownRootOrOtherOperation() public {
+        vm.expectRevert(AegisVault.UnknownMerkleRoot.selector);
+        vault.transfer(
+            hex"01",
+            _encodeTransferOutput(keccak256("nullifier1"), keccak256("recipient"), keccak256("change"), keccak256("fake_root"), SUCCESS),
+            "",
+            ""
+        );
+
+        // A borrow can't pass as a transfer, whatever its length
+        bytes memory borrowPublicValues = _encodeBorrowOutput(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot(), SUCCESS
+        );
+        vm.expectRevert(AegisVault.InvalidPublicValuesLength.selector);
+        vault.transfer(hex"01", borrowPublicValues, "", "");
+    }
+
+    /// @notice On-chain tree must match the golden vectors in zk-program/src/merkle.rs
+    function testBorrowRevertsOnUntrustedOracle() public {
+        _depositForBorrow();
+
+        AegisVault.BorrowPublicValues memory out = _borrowValues(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot()
+        );
+        out.oracleKeyHash = keccak256("OTHER_ORACLE");
+
+        vm.expectRevert(AegisVault.UntrustedOracle.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+    }
+
+    function testBorrowRevertsOnStalePrice() public {
+        _depositForBorrow();
+        vm.warp(block.timestamp + 2 hours);
+
+        // Signed just over MAX_PRICE_AGE ago
+        AegisVault.BorrowPublicValues memory out = _borrowValues(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot()
+        );
+        out.priceTimestamp = uint64(block.timestamp - vault.MAX_PRICE_AGE() - 1);
+
+        vm.expectRevert(AegisVault.StalePrice.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+
+        // Timestamps from the future are rejected too
+        out.priceTimestamp = uint64(block.timestamp + 1);
+
+        vm.expectRevert(AegisVault.StalePrice.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+    }
+
+    function testBorrowRevertsOnPriceAssetMismatch() public {
+        _depositForBorrow();
+
+        AegisVault.BorrowPublicValues memory out = _borrowValues(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot()
+        );
+        out.collateralAssets[0] = address(debt);
+
+        vm.expectRevert(AegisVault.PriceAssetMismatch.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+
+        // A second market the vault never listed
+        out.collateralAssets[0] = address(collateral);
+        out.collateralAssets[1] = address(wmnt);
+
+        vm.expectRevert(AegisVault.PriceAssetMismatch.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+    }
+
+    function testBorrowRevertsOnLtvWeightMismatch() public {
+        _depositForBorrow();
+
+        // LTV computed with a 90% weight instead of the vault's 75%
+        AegisVault.BorrowPublicValues memory out = _borrowValues(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot()
+        );
+        out.ltvWeightsBps[0] = 9000;
+
+        vm.expectRevert(AegisVault.LtvWeightMismatch.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+    }
+
+    function testBorrowAgainstMultiCollateralTable() public {
+        vault.setCollateralAsset(1, address(wmnt), 5000);
+        _depositForBorrow();
+
+        // Proof valued the note with both listed markets
+        AegisVault.BorrowPublicValues memory out = _borrowValues(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot()
+        );
+        out.collateralAssets[1] = address(wmnt);
+        out.ltvWeightsBps[1] = 5000;
+        out.collateralDecimals[1] = wmnt.decimals();
+
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+        assertEq(debt.balanceOf(bob), 1000e6);
+    }
+
+    function testBorrowRevertsOnDecimalsMismatch() public {
+        _depositForBorrow();
+
+        // LTV computed as if the collateral had 8 decimals (e.g. WBTC)
+        AegisVault.BorrowPublicValues memory out = _borrowValues(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot()
+        );
+        out.collateralDecimals[0] = 8;
+
+        vm.expectRevert(AegisVault.DecimalsMismatch.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+
+        out.collateralDecimals[0] = 18;
+        out.debtDecimals = 18;
+
+        vm.expectRevert(AegisVault.DecimalsMismatch.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+    }
+
+    function testAccrueInterestGrowsIndex() public {
+        // 0.01% per second, 1% over 100 seconds
+        vault.setBorrowRate(1e14);
+        assertEq(vault.borrowIndex(), vault.INDEX_SCALE());
+
+        vm.warp(block.timestamp + 100);
+        vault.accrueInterest();
+
+        assertEq(vault.borrowIndex(), 1.01e18);
+    }
+
+    function testBorrowRevertsOnStaleBorrowIndex() public {
+        _depositForBorrow();
+
+        // Proof generated before interest accrued
+        AegisVault.BorrowPublicValues memory out = _borrowValues(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot()
+        );
+
+        vault.setBorrowRate(1e14);
+        vm.warp(block.timestamp + 100);
+        vault.accrueInterest();
+        out.priceTimestamp = uint64(block.timestamp);
+
+        vm.expectRevert(AegisVault.StaleBorrowIndex.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+
+        // Re-proving against the accrued index succeeds
+        // forge-lint: disable-next-line(unsafe-typecast)
+        out.borrowIndex = uint128(vault.borrowIndex());
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+        assertTrue(vault.isNullifierSpent(keccak256("nullifier1")));
+    }
+
+    function testDepositScreenedAgainstBlocklist() public {
+        bytes32 blocklist = keccak256("BLOCKLIST");
+        vault.setBlocklistRoot(blocklist);
+
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 1 ether);
+
+        // Unscreened deposits, stale lists and screening someone else are all rejected
+        vm.expectRevert(AegisVault.UnscreenedAddress.selector);
+        vault.deposit(hex"00", _encodeDepositOutput(keccak256("screened"), 1 ether, address(collateral), SUCCESS), "");
+
+        vm.expectRevert(AegisVault.UnscreenedAddress.selector);
+        vault.deposit(
+            hex"00",
+            _encodeScreenedDepositOutput(keccak256("screened"), 1 ether, address(collateral), alice, keccak256("OLD"), SUCCESS),
+            ""
+        );
+
+        vm.expectRevert(AegisVault.UnscreenedAddress.selector);
+        vault.deposit(
+            hex"00",
+            _encodeScreenedDepositOutput(keccak256("screened"), 1 ether, address(collateral), bob, blocklist, SUCCESS),
+            ""
+        );
+
+        vault.deposit(
+            hex"00",
+            _encodeScreenedDepositOutput(keccak256("screened"), 1 ether, address(collateral), alice, blocklist, SUCCESS),
+            ""
+        );
+        vm.stopPrank();
+
+        assertEq(vault.getCommitmentCount(), 1);
+    }
+
+    function testBorrowRevertsOnUnscreenedRecipient() public {
+        _depositForBorrow();
+
+        bytes32 blocklist = keccak256("BLOCKLIST");
+        vault.setBlocklistRoot(blocklist);
+
+        AegisVault.BorrowPublicValues memory out = _borrowValues(
+            keccak256("nullifier1"), keccak256("commitment2"), bob, 1000e6, vault.merkleRoot()
+        );
+
+        vm.expectRevert(AegisVault.UnscreenedAddress.selector);
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+
+        out.blocklistRoot = blocklist;
+        vault.borrow(hex"01", _encodeBorrowPublicValues(out), "");
+        assertTrue(vault.isNullifierSpent(keccak256("nullifier1")));
+
+        vm.prank(alice);
+        vm.expectRevert(AegisVault.UnauthorizedCaller.selector);
+        vault.setBlocklistRoot(bytes32(0));
+    }
+
+    function testMerkleRootsMatchRustVectors() public {
+        assertEq(
+            vault.merkleRoot(),
+            bytes32(hex"cddba7b592e3133393c16194fac7431abf2f5485ed711db282183c819e08ebaa")
+        );
+
+        bytes32[3] memory expectedRoots = [
+            bytes32(hex"32fda0577aed68c184536c80f66155ad02a055795c58a9dfead941807cd56041"),
+            bytes32(hex"cdb37bdb53b5bea9676dc8276d9a29fd8ebbfee0837f807680004136e53a2006"),
+            bytes32(hex"0282acb72e7d08b2d19e1f628a296eb4901b9f37f26db76f708946f9f650947a")
+        ];
+
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 3 ether);
+        for (uint8 i = 0; i < 3; i++) {
+            // Leaf i is 32 bytes of (i + 1), same as the Rust test
+            bytes32 leaf = bytes32(uint256(i + 1) * (type(uint256).max / 255));
+            vault.deposit(hex"00", _encodeDepositOutput(leaf, 1 ether, address(collateral), SUCCESS), "");
+            assertEq(vault.merkleRoot(), expectedRoots[i]);
+            assertTrue(vault.isKnownRoot(expectedRoots[i]));
+        }
+        vm.stopPrank();
+
+        assertEq(vault.nextLeafIndex(), 3);
+    }
+
+    /// @notice Keccak tree must match the keccak golden vectors in zk-program/src/merkle.rs
+    function testKeccakMerkleRootsMatchRustVectors() public {
+        AegisVaultKeccak keccakVault =
+            new AegisVaultKeccak(address(verifier), depositVkey, borrowVkey, address(collateral), address(debt));
+
+        assertEq(keccakVault.zeros(1), keccak256(abi.encodePacked(bytes32(0), bytes32(0))));
+        assertEq(
+            keccakVault.merkleRoot(),
+            bytes32(hex"c65e9645644786b620e2dd2ad648ddfcbf4a7e5b1a3a4ecfe7f64667a3f0b7e2")
+        );
+
+        bytes32[3] memory expectedRoots = [
+            bytes32(hex"19826032261ced570c978de1d7b0b68afff152212435511e9bdcaa85bcfb3338"),
+            bytes32(hex"6794826b979b3797f39b77ad2708a684307b05f2f0ef9084cff662f29964a352"),
+            bytes32(hex"20c1e3143b3494398cf6782a7895148ac5c2bd855fb539fc9a3accd409ec25c9")
+        ];
+
+        vm.startPrank(alice);
+        collateral.approve(address(keccakVault), 3 ether);
+        for (uint8 i = 0; i < 3; i++) {
+            bytes32 leaf = bytes32(uint256(i + 1) * (type(uint256).max / 255));
+            keccakVault.deposit(hex"00", _encodeDepositOutput(leaf, 1 ether, address(collateral), SUCCESS), "");
+            assertEq(keccakVault.merkleRoot(), expectedRoots[i]);
+        }
+        vm.stopPrank();
+    }
+
+    /// @notice Commitments and nullifiers from a keccak build can be recomputed on-chain
+    /// (golden vectors in zk-program/src/crypto.rs)
+    function testKeccakHashesMatchRustVectors() public pure {
+        // Rust fills each field with one repeated byte
+        bytes32 secretKey = bytes32(type(uint256).max / 255);
+        bytes32 ownerKey = sha256(abi.encodePacked(secretKey, "OWNER_KEY"));
+        bytes20 asset = bytes20(uint160(type(uint160).max / 255 * 0xEE));
+        bytes32 salt = bytes32(2 * (type(uint256).max / 255));
+
+        bytes32 commitment = _keccakDomainHash(
+            "aegis.balance_commitment", abi.encodePacked(ownerKey, asset, _toLittleEndian(1000, 16), salt)
+        );
+        assertEq(commitment, bytes32(hex"225772b9f7b3d8207f6709234e75718ac264e72c488e422f35de5d02bf9f989e"));
+
+        bytes32 nullifier = _keccakDomainHash(
+            "aegis.nullifier",
+            abi.encodePacked(
+                bytes32(3 * (type(uint256).max / 255)), bytes32(4 * (type(uint256).max / 255)), _toLittleEndian(3, 8)
+            )
+        );
+        assertEq(nullifier, bytes32(hex"a29f13e71af16d12903fd423eb3a263bce967c9c2dcdfa4998f666999c1c3660"));
     }
 
     function testGetters() public view {
@@ -159,43 +768,185 @@ contract AegisVaultTest is Test {
         assertEq(vault.getCommitmentCount(), 0);
     }
 
+    // Helper function to encode an unscreened single-asset deposit output like Rust does (1 + 157 bytes)
+    function _encodeDepositOutput(
+        bytes32 commitment,
+        uint128 amount,
+        address token,
+        uint8 errorCode
+    ) internal pure returns (bytes memory) {
+        return _encodeScreenedDepositOutput(commitment, amount, token, address(0), bytes32(0), errorCode);
+    }
+
+    // Deposit output whose depositor was screened against `blocklist`
+    function _encodeScreenedDepositOutput(
+        bytes32 commitment,
+        uint128 amount,
+        address token,
+        address depositor,
+        bytes32 blocklist,
+        uint8 errorCode
+    ) internal pure returns (bytes memory) {
+        // Collateral in slot 0, slot 1 empty; u128 is serialized little-endian
+        return abi.encodePacked(
+            OP_DEPOSIT,
+            commitment,
+            token,
+            _toLittleEndian(amount, 16),
+            address(0),
+            _toLittleEndian(0, 16),
+            depositor,
+            blocklist,
+            errorCode
+        );
+    }
+
+    // Unscreened batch deposit output with `total` of the collateral token in slot 0 (1 + 638 bytes)
+    function _encodeBatchDepositOutput(
+        bytes32[] memory commitments,
+        uint128 total,
+        uint8 errorCode
+    ) internal view returns (bytes memory publicValues) {
+        publicValues = abi.encodePacked(OP_BATCH_DEPOSIT);
+
+        // Unused commitment entries are zero
+        for (uint256 i = 0; i < vault.MAX_BATCH_DEPOSITS(); i++) {
+            publicValues = abi.encodePacked(publicValues, i < commitments.length ? commitments[i] : bytes32(0));
+        }
+
+        // Safe cast: tests batch at most MAX_BATCH_DEPOSITS notes
+        // forge-lint: disable-next-line(unsafe-typecast)
+        uint8 count = uint8(commitments.length);
+
+        return abi.encodePacked(
+            publicValues,
+            count,
+            address(collateral),
+            _toLittleEndian(total, 16),
+            address(0),
+            _toLittleEndian(0, 16),
+            address(0),
+            bytes32(0),
+            errorCode
+        );
+    }
+
+    // Unscreened shield of `amount` of the collateral token (1 + 121 bytes)
+    function _encodeShieldOutput(
+        bytes32 commitment,
+        uint128 amount,
+        uint8 errorCode
+    ) internal view returns (bytes memory) {
+        return abi.encodePacked(
+            OP_SHIELD, commitment, address(collateral), _toLittleEndian(amount, 16), address(0), bytes32(0), errorCode
+        );
+    }
+
+    // Transfer output: nullifier, recipient and change commitments, proven root (1 + 129 bytes)
+    function _encodeTransferOutput(
+        bytes32 nullifier,
+        bytes32 recipientCommitment,
+        bytes32 changeCommitment,
+        bytes32 root,
+        uint8 errorCode
+    ) internal pure returns (bytes memory) {
+        return abi.encodePacked(OP_TRANSFER, nullifier, recipientCommitment, changeCommitment, root, errorCode);
+    }
+
+    // Alice deposits 10 ETH so there is a note to borrow against
+    function _depositForBorrow() internal {
+        vm.startPrank(alice);
+        collateral.approve(address(vault), 10 ether);
+        bytes memory depositPublicValues = _encodeDepositOutput(keccak256("commitment1"), 10 ether, address(collateral), SUCCESS);
+        vault.deposit(hex"00", depositPublicValues, "");
+        vm.stopPrank();
+    }
+
+    // Borrow public values as a valid proof priced by the trusted oracle just now would commit them
+    function _borrowValues(
+        bytes32 nullifier,
+        bytes32 newCommitment,
+        address recipient,
+        uint128 amount,
+        bytes32 root
+    ) internal view returns (AegisVault.BorrowPublicValues memory out) {
+        out.nullifierHash = nullifier;
+        out.newCommitment = newCommitment;
+        out.recipient = recipient;
+        out.borrowAmount = amount;
+        out.merkleRoot = root;
+        out.oracleKeyHash = oracleKeyHash;
+        out.priceTimestamp = uint64(block.timestamp);
+        out.collateralAssets[0] = address(collateral);
+        out.ltvWeightsBps[0] = vault.DEFAULT_LTV_BPS();
+        out.collateralDecimals[0] = collateral.decimals();
+        out.debtDecimals = debt.decimals();
+        // Safe cast: the index stays far below 2^128 in tests
+        // forge-lint: disable-next-line(unsafe-typecast)
+        out.borrowIndex = uint128(vault.borrowIndex());
+        out.errorCode = SUCCESS;
+    }
+
     // Helper function to encode borrow output like Rust does
     function _encodeBorrowOutput(
         bytes32 nullifier,
         bytes32 newCommitment,
         address recipient,
         uint128 amount,
-        uint8 isValid
+        bytes32 root,
+        uint8 errorCode
+    ) internal view returns (bytes memory) {
+        AegisVault.BorrowPublicValues memory out = _borrowValues(nullifier, newCommitment, recipient, amount, root);
+        out.errorCode = errorCode;
+        return _encodeBorrowPublicValues(out);
+    }
+
+    // Serialize borrow public values: OP_BORROW, then the bincode layout of BorrowOutput (1 + 268 bytes)
+    function _encodeBorrowPublicValues(
+        AegisVault.BorrowPublicValues memory out
     ) internal pure returns (bytes memory) {
-        bytes memory result = new bytes(101);
-        
-        // Copy nullifier (bytes 0-31)
-        for (uint i = 0; i < 32; i++) {
-            result[i] = nullifier[i];
-        }
-        
-        // Copy new commitment (bytes 32-63)
-        for (uint i = 0; i < 32; i++) {
-            result[32 + i] = newCommitment[i];
-        }
-        
-        // Copy recipient address (bytes 64-83)
-        bytes20 recipientBytes = bytes20(recipient);
-        for (uint i = 0; i < 20; i++) {
-            result[64 + i] = recipientBytes[i];
+        // Integers are serialized little-endian like Rust
+        return abi.encodePacked(
+            OP_BORROW,
+            abi.encodePacked(
+                out.nullifierHash, out.newCommitment, out.recipient, _toLittleEndian(out.borrowAmount, 16), out.merkleRoot
+            ),
+            abi.encodePacked(out.oracleKeyHash, _toLittleEndian(out.priceTimestamp, 8)),
+            _encodeCollateralTable(out),
+            abi.encodePacked(out.debtDecimals, _toLittleEndian(out.borrowIndex, 16), out.blocklistRoot, out.errorCode)
+        );
+    }
+
+    // Fixed arrays have no length prefix: all assets, then all weights, then all decimals
+    function _encodeCollateralTable(
+        AegisVault.BorrowPublicValues memory out
+    ) internal pure returns (bytes memory assets) {
+        bytes memory weights;
+        bytes memory decimals;
+        for (uint256 slot = 0; slot < out.collateralAssets.length; slot++) {
+            assets = abi.encodePacked(assets, out.collateralAssets[slot]);
+            weights = abi.encodePacked(weights, _toLittleEndian(out.ltvWeightsBps[slot], 2));
+            decimals = abi.encodePacked(decimals, out.collateralDecimals[slot]);
         }
-        
-        // Copy amount as u128 (bytes 84-99, little-endian like Rust)
-        for (uint i = 0; i < 16; i++) {
-            // Safe cast: extracting single byte from amount
+        return abi.encodePacked(assets, weights, decimals);
+    }
+
+    // Little-endian encoding of the low `size` bytes of value
+    /// @notice Domain-separated hash as zk-program/src/hashing.rs computes it with the keccak feature:
+    /// keccak256(version || tag length || tag || fields)
+    function _keccakDomainHash(bytes memory tag, bytes memory fields) internal pure returns (bytes32) {
+        // Safe cast: domain tags are a few dozen bytes
+        // forge-lint: disable-next-line(unsafe-typecast)
+        return keccak256(abi.encodePacked(HASH_VERSION, uint8(tag.length), tag, fields));
+    }
+
+    function _toLittleEndian(uint256 value, uint256 size) internal pure returns (bytes memory out) {
+        out = new bytes(size);
+        for (uint i = 0; i < size; i++) {
+            // Safe cast: extracting single byte from value
             // forge-lint: disable-next-line(unsafe-typecast)
-            result[84 + i] = bytes1(uint8(amount >> (8 * i)));
+            out[i] = bytes1(uint8(value >> (8 * i)));
         }
-        
-        // Set is_valid (byte 100)
-        result[100] = bytes1(isValid);
-        
-        return result;
     }
 }
 
@@ -1,9 +1,18 @@
 use sp1_build::{build_program_with_args, BuildArgs};
 
 fn main() {
-    // Build the ZK program
-    build_program_with_args(
-        "../zk-program",
-        BuildArgs::default(),
-    );
+    // Build the ZK program (`--features strict` builds it to abort on invalid inputs,
+    // `--features poseidon` or `keccak` to hash notes with Poseidon or keccak256; each
+    // changes its verification key)
+    let mut args = BuildArgs::default();
+    for (env_var, feature) in [
+        ("CARGO_FEATURE_STRICT", "strict"),
+        ("CARGO_FEATURE_POSEIDON", "poseidon"),
+        ("CARGO_FEATURE_KECCAK", "keccak"),
+    ] {
+        if std::env::var(env_var).is_ok() {
+            args.features.push(feature.to_string());
+        }
+    }
+    build_program_with_args("../zk-program", args);
 }
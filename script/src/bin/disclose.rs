@@ -0,0 +1,428 @@
+use ethers::{
+    contract::abigen,
+    core::types::{Address, U256},
+    providers::{Http, Provider},
+};
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{utils, HashableKey, ProverClient, SP1ProofWithPublicValues, SP1Stdin};
+use std::env;
+use std::error::Error;
+use std::sync::Arc;
+
+// Embed the compiled SP1 ELF
+const ELF: &[u8] = include_bytes!("../../../zk-program/target/elf-compilation/riscv32im-succinct-zkvm-elf/release/zk-program");
+
+// Share note hashing and Merkle paths with the ZK program
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/collateral.rs"]
+mod collateral;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/error.rs"]
+mod error;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/hashing.rs"]
+mod hashing;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/math.rs"]
+mod math;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/merkle.rs"]
+mod merkle;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/operation.rs"]
+mod operation;
+#[path = "../proof_output.rs"]
+mod proof_output;
+#[path = "../vault_tree.rs"]
+mod vault_tree;
+
+use collateral::{single_asset, CollateralMarket, CollateralPosition, MAX_COLLATERAL_ASSETS};
+use error::ErrorCode;
+use merkle::MERKLE_DEPTH;
+use operation::OP_DISCLOSURE;
+use proof_output::read_output;
+
+/// Token decimals of the local market (18-decimal collateral, 6-decimal debt)
+const COLLATERAL_DECIMALS: u8 = 18;
+const DEBT_DECIMALS: u8 = 6;
+
+// Data structures matching the ZK program
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+enum DisclosureStatement {
+    MinCollateral {
+        asset: [u8; 20],
+        min_amount: u128,
+    },
+    MaxLtv {
+        markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+        max_ltv_bps: u16,
+        debt_decimals: u8,
+        borrow_index: u128,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DisclosureInput {
+    user_secret_key: [u8; 32],
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    debt_amount: u128,
+    note_borrow_index: u128,
+    note_salt: [u8; 32],
+    statement: DisclosureStatement,
+    challenge: [u8; 32],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DisclosureOutput {
+    statement: DisclosureStatement,
+    owner_key: [u8; 32],
+    challenge: [u8; 32],
+    merkle_root: [u8; 32],
+    error_code: ErrorCode,
+}
+
+// What an auditor checks a disclosure against
+abigen!(
+    AegisVault,
+    r#"[
+        function collateralAssets(uint256 slot) external view returns (address token, uint16 ltvBps, uint8 decimals)
+        function DEBT_DECIMALS() external view returns (uint8)
+        function isKnownRoot(bytes32 root) external view returns (bool)
+    ]"#
+);
+
+fn main() {
+    utils::setup_logger();
+
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 2 {
+        eprintln!("Usage:");
+        eprintln!("  disclose collateral <collateral_eth> <debt_usd> <min_eth> <output_file>");
+        eprintln!("  disclose ltv <collateral_eth> <debt_usd> <max_ltv_pct> <eth_price_usd> <output_file>");
+        eprintln!("  disclose verify <proof_file>");
+        eprintln!();
+        eprintln!("Examples:");
+        eprintln!("  disclose collateral 10 5000 5 audit-collateral.proof");
+        eprintln!("  disclose ltv 10 5000 25 2500 audit-ltv.proof");
+        eprintln!("  disclose verify audit-ltv.proof");
+        std::process::exit(1);
+    }
+
+    let operation = &args[1];
+
+    match operation.as_str() {
+        "collateral" => {
+            if args.len() < 6 {
+                eprintln!("Usage: disclose collateral <collateral_eth> <debt_usd> <min_eth> <output_file>");
+                std::process::exit(1);
+            }
+
+            let collateral_eth: u128 = args[2].parse().expect("Invalid collateral");
+            let debt_usd: u128 = args[3].parse().expect("Invalid debt");
+            let min_eth: u128 = args[4].parse().expect("Invalid minimum collateral");
+            let output_file = &args[5];
+
+            let statement = DisclosureStatement::MinCollateral {
+                asset: collateral_token_from_env(),
+                min_amount: min_eth * 1_000_000_000_000_000_000u128,
+            };
+            prove_disclosure(collateral_eth, debt_usd, statement, output_file);
+        }
+        "ltv" => {
+            if args.len() < 7 {
+                eprintln!("Usage: disclose ltv <collateral_eth> <debt_usd> <max_ltv_pct> <eth_price_usd> <output_file>");
+                std::process::exit(1);
+            }
+
+            let collateral_eth: u128 = args[2].parse().expect("Invalid collateral");
+            let debt_usd: u128 = args[3].parse().expect("Invalid debt");
+            let max_ltv_pct: u16 = args[4].parse().expect("Invalid max LTV");
+            let eth_price: u128 = args[5].parse().expect("Invalid price");
+            let output_file = &args[6];
+
+            let mut markets = [CollateralMarket::default(); MAX_COLLATERAL_ASSETS];
+            markets[0] = CollateralMarket {
+                asset: collateral_token_from_env(),
+                price_usd: eth_price * 1_000_000u128, // 6 decimals
+                weight_bps: 0,
+                decimals: COLLATERAL_DECIMALS,
+            };
+            let statement = DisclosureStatement::MaxLtv {
+                markets,
+                max_ltv_bps: max_ltv_pct * 100,
+                debt_decimals: DEBT_DECIMALS,
+                borrow_index: math::INDEX_SCALE,
+            };
+            prove_disclosure(collateral_eth, debt_usd, statement, output_file);
+        }
+        "verify" => {
+            if args.len() < 3 {
+                eprintln!("Usage: disclose verify <proof_file>");
+                std::process::exit(1);
+            }
+
+            verify_disclosure(&args[2]);
+        }
+        _ => {
+            eprintln!("Unknown operation: {}", operation);
+            eprintln!("Use 'collateral', 'ltv' or 'verify'");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Collateral token the vault expects, read from COLLATERAL_TOKEN in the environment
+fn collateral_token_from_env() -> [u8; 20] {
+    let mut token = [0u8; 20];
+    match env::var("COLLATERAL_TOKEN") {
+        Ok(address) => {
+            let bytes = hex::decode(address.trim_start_matches("0x")).expect("Invalid COLLATERAL_TOKEN");
+            token.copy_from_slice(&bytes);
+        }
+        Err(_) => eprintln!("⚠️  COLLATERAL_TOKEN not set, using the zero address"),
+    }
+    token
+}
+
+/// Auditor's challenge, read from AUDIT_CHALLENGE (32 bytes hex) in the environment
+fn challenge_from_env() -> Option<[u8; 32]> {
+    let challenge = env::var("AUDIT_CHALLENGE").ok()?;
+    let bytes = hex::decode(challenge.trim_start_matches("0x")).expect("Invalid AUDIT_CHALLENGE");
+    Some(bytes.try_into().expect("AUDIT_CHALLENGE must be 32 bytes"))
+}
+
+/// Node at RPC_URL (default http://127.0.0.1:8545) and the vault at VAULT, read from the environment
+fn vault_from_env() -> (Arc<Provider<Http>>, Address) {
+    let rpc_url = env::var("RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8545".to_string());
+    let vault_address: Address = env::var("VAULT").expect("VAULT must be set").parse().expect("Invalid VAULT");
+    let provider = Provider::<Http>::try_from(rpc_url.as_str()).expect("Invalid RPC_URL");
+    (Arc::new(provider), vault_address)
+}
+
+/// Leaf index, Merkle path and root of a note in the vault's commitment tree, rebuilt from the
+/// vault's events (VAULT and RPC_URL in the environment)
+fn vault_merkle_proof(commitment: &[u8; 32]) -> (u64, [[u8; 32]; MERKLE_DEPTH], [u8; 32]) {
+    let (provider, vault_address) = vault_from_env();
+
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start the async runtime");
+    let tree = runtime
+        .block_on(vault_tree::sync_tree(provider, vault_address))
+        .unwrap_or_else(|err| {
+            eprintln!("\n❌ Error: Failed to rebuild the vault's commitment tree: {}", err);
+            std::process::exit(1);
+        });
+
+    let Some(leaf_index) = tree.position(commitment) else {
+        eprintln!("\n❌ Error: Note 0x{} is not in the vault", hex::encode(commitment));
+        std::process::exit(1);
+    };
+    println!("   Note index: {} of {}", leaf_index, tree.len());
+
+    (leaf_index, tree.path(leaf_index).expect("leaf is in the tree"), tree.root())
+}
+
+fn describe(statement: &DisclosureStatement) -> String {
+    match statement {
+        DisclosureStatement::MinCollateral { asset, min_amount } => format!(
+            "collateral >= {} wei of 0x{}",
+            min_amount,
+            hex::encode(asset)
+        ),
+        DisclosureStatement::MaxLtv { markets, max_ltv_bps, debt_decimals, borrow_index } => {
+            // The holder picks every field, so show them all (weights are replaced by the bound)
+            let mut description = format!(
+                "LTV <= {}% of {}-decimal debt at borrow index {}, valuing collateral at",
+                *max_ltv_bps as f64 / 100.0,
+                debt_decimals,
+                borrow_index
+            );
+            for market in markets {
+                description.push_str(&format!(
+                    "\n     ${} per 0x{} ({} decimals)",
+                    market.price_usd as f64 / 1_000_000.0,
+                    hex::encode(market.asset),
+                    market.decimals
+                ));
+            }
+            description
+        }
+    }
+}
+
+/// Check a disclosure is about the vault: its root must be one of the vault's, and an LTV statement
+/// may only value listed collateral at the vault's decimals, against debt of the vault's decimals
+async fn check_against_vault(result: &DisclosureOutput) -> Result<(), Box<dyn Error>> {
+    let (provider, vault_address) = vault_from_env();
+    let vault = AegisVault::new(vault_address, provider);
+
+    if !vault.is_known_root(result.merkle_root).call().await? {
+        return Err("Merkle root is not one of the vault's roots".into());
+    }
+
+    if let DisclosureStatement::MaxLtv { markets, debt_decimals, .. } = result.statement {
+        let mut listed = Vec::new();
+        for slot in 0..MAX_COLLATERAL_ASSETS {
+            let (token, _, decimals) = vault.collateral_assets(U256::from(slot)).call().await?;
+            listed.push((token.0, decimals));
+        }
+
+        // Leaving a market out only makes the LTV look worse, so empty slots are fine
+        for market in markets.iter().filter(|market| market.asset != [0u8; 20]) {
+            if !listed.contains(&(market.asset, market.decimals)) {
+                return Err(format!(
+                    "0x{} at {} decimals is not one of the vault's markets",
+                    hex::encode(market.asset),
+                    market.decimals
+                )
+                .into());
+            }
+        }
+
+        if debt_decimals != vault.debt_decimals().call().await? {
+            return Err(format!("debt decimals {} are not the vault's", debt_decimals).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn prove_disclosure(collateral_eth: u128, debt_usd: u128, statement: DisclosureStatement, output_file: &str) {
+    println!("\n🔍 Generating Disclosure Proof");
+    println!("========================================");
+    println!("Statement: {}", describe(&statement));
+    println!("Output: {}\n", output_file);
+
+    let client = ProverClient::from_env();
+    let (pk, vk) = client.setup(ELF);
+
+    println!("📋 Verification Key: {}", vk.bytes32());
+
+    // Fixed secret and salt matching generate_proof's borrow note (in production, restore the
+    // note by scanning with the wallet)
+    let secret_key = [1u8; 32];
+    let salt = [43u8; 32];
+    let collateral = single_asset(collateral_token_from_env(), collateral_eth * 1_000_000_000_000_000_000u128);
+    let debt_amount = debt_usd * 1_000_000u128;
+
+    // Prove the note against the vault's tree, so the auditor can check the root is the vault's
+    let commitment = crypto::hash_commitment(&secret_key, &collateral, debt_amount, math::INDEX_SCALE, &salt);
+    let (leaf_index, merkle_path, merkle_root) = vault_merkle_proof(&commitment);
+
+    let challenge = challenge_from_env().unwrap_or_else(|| {
+        eprintln!("⚠️  AUDIT_CHALLENGE not set, answering the zero challenge");
+        [0u8; 32]
+    });
+
+    let disclosure_input = DisclosureInput {
+        user_secret_key: secret_key,
+        collateral,
+        debt_amount,
+        note_borrow_index: math::INDEX_SCALE,
+        note_salt: salt,
+        statement,
+        challenge,
+        merkle_root,
+        merkle_path,
+        leaf_index,
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_DISCLOSURE);
+    stdin.write(&disclosure_input);
+
+    // Execute first so a false statement fails fast
+    println!("🔨 Executing program...");
+    let (mut output, report) = client.execute(ELF, &stdin).run().unwrap_or_else(|err| {
+        // Strict builds panic on a false statement instead of committing its error code
+        eprintln!("\n❌ Error: Statement rejected during execution: {}", err);
+        std::process::exit(1);
+    });
+    let result: DisclosureOutput =
+        read_output(&mut output, OP_DISCLOSURE).expect("proof of another operation");
+
+    println!("✅ Execution complete:");
+    println!("   Cycles: {}", report.total_instruction_count());
+    println!("   Result: {}", result.error_code);
+
+    if !result.error_code.is_success() {
+        eprintln!("\n❌ Error: Statement rejected: {}", result.error_code);
+        std::process::exit(1);
+    }
+
+    // Auditors verify off-chain, so a compressed proof is enough
+    println!("\n🔨 Generating compressed proof...");
+    let start = std::time::Instant::now();
+    let proof = client.prove(&pk, &stdin)
+        .compressed()
+        .run()
+        .expect("proving failed");
+
+    println!("✅ Proof generated in {:?}", start.elapsed());
+
+    client.verify(&proof, &vk).expect("verification failed");
+    proof.save(output_file).expect("failed to save proof");
+    println!("\n💾 Proof saved to: {}", output_file);
+
+    println!("\n========================================");
+    println!("✅ Disclosure proof ready for the auditor!");
+    println!("========================================\n");
+}
+
+fn verify_disclosure(proof_file: &str) {
+    println!("\n🔍 Verifying Disclosure Proof");
+    println!("========================================");
+    println!("Proof: {}\n", proof_file);
+
+    let client = ProverClient::from_env();
+    let (_, vk) = client.setup(ELF);
+
+    let proof = SP1ProofWithPublicValues::load(proof_file).expect("failed to load proof");
+    client.verify(&proof, &vk).expect("verification failed");
+
+    // Every operation is proven by the same program, so the proof must say it is a disclosure
+    let mut public_values = proof.public_values.clone();
+    let Some(result) = read_output::<DisclosureOutput>(&mut public_values, OP_DISCLOSURE) else {
+        eprintln!("\n❌ Error: Proof is not of a disclosure!");
+        std::process::exit(1);
+    };
+
+    println!("✅ Proof verified:");
+    println!("   Statement: {}", describe(&result.statement));
+    println!("   Holder: 0x{}", hex::encode(result.owner_key));
+    println!("   Challenge: 0x{}", hex::encode(result.challenge));
+    println!("   Merkle Root: 0x{}", hex::encode(result.merkle_root));
+
+    if !result.error_code.is_success() {
+        eprintln!("\n❌ Error: Proof does not attest the statement: {}", result.error_code);
+        std::process::exit(1);
+    }
+
+    // A proof answering another request could be replayed
+    if let Some(challenge) = challenge_from_env() {
+        if result.challenge != challenge {
+            eprintln!("\n❌ Error: Proof answers a different challenge!");
+            std::process::exit(1);
+        }
+    }
+
+    // Only the vault can say the note and its valuation are real
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start the async runtime");
+    if let Err(err) = runtime.block_on(check_against_vault(&result)) {
+        eprintln!("\n❌ Error: {}", err);
+        std::process::exit(1);
+    }
+    println!("\n✅ Root and markets are the vault's");
+    if matches!(result.statement, DisclosureStatement::MaxLtv { .. }) {
+        println!("⚠️  Prices are the holder's; check them against the market");
+    }
+    println!("\n========================================");
+    println!("✅ Statement holds for a note of this holder!");
+    println!("========================================\n");
+}
@@ -1,35 +1,104 @@
+use ethers::{
+    core::types::Address,
+    providers::{Http, Provider},
+};
 use serde::{Deserialize, Serialize};
 use sp1_sdk::{utils, HashableKey, ProverClient, SP1Stdin};
 use std::env;
 use std::fs;
+use std::sync::Arc;
 
 // Embed the compiled SP1 ELF
 const ELF: &[u8] = include_bytes!("../../../zk-program/target/elf-compilation/riscv32im-succinct-zkvm-elf/release/zk-program");
 
+// Share note hashing, Merkle paths and note encryption with the ZK program and wallets
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/blocklist.rs"]
+mod blocklist;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/collateral.rs"]
+mod collateral;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/error.rs"]
+mod error;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/hashing.rs"]
+mod hashing;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/math.rs"]
+mod math;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/merkle.rs"]
+mod merkle;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/operation.rs"]
+mod operation;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/oracle.rs"]
+mod oracle;
+#[path = "../local_oracle.rs"]
+mod local_oracle;
+#[path = "../proof_output.rs"]
+mod proof_output;
+#[allow(dead_code)]
+#[path = "../note_encryption.rs"]
+mod note_encryption;
+#[path = "../vault_tree.rs"]
+mod vault_tree;
+
+use blocklist::BlocklistProof;
+use collateral::{single_asset, CollateralPosition, MAX_COLLATERAL_ASSETS};
+use error::ErrorCode;
+use local_oracle::{LocalOracle, SignedPrice};
+use merkle::MERKLE_DEPTH;
+use operation::{OP_BORROW, OP_DEPOSIT};
+use proof_output::read_output;
+use note_encryption::{encrypt_note, NotePlaintext};
+
+/// Token decimals of the local market (18-decimal collateral, 6-decimal debt)
+const COLLATERAL_DECIMALS: u8 = 18;
+const DEBT_DECIMALS: u8 = 6;
+
 #[derive(Serialize, Deserialize, Debug)]
 struct DepositInput {
     user_secret_key: [u8; 32],
-    collateral_amount: u128,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
     note_salt: [u8; 32],
+    depositor_address: [u8; 20],
+    blocklist: Option<BlocklistProof>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct DepositOutput {
     commitment_hash: [u8; 32],
-    is_valid: u8,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    depositor_address: [u8; 20],
+    blocklist_root: [u8; 32],
+    error_code: ErrorCode,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct BorrowInput {
     user_secret_key: [u8; 32],
-    collateral_amount: u128,
-    collateral_price_usd: u128,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
     existing_debt: u128,
+    note_borrow_index: u128,
+    borrow_index: u128,
     new_borrow_amount: u128,
-    max_ltv_bps: u16,
+    ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
     old_note_salt: [u8; 32],
     new_note_salt: [u8; 32],
     recipient_address: [u8; 20],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+    blocklist: Option<BlocklistProof>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -38,7 +107,16 @@ struct BorrowOutput {
     new_commitment_hash: [u8; 32],
     recipient_address: [u8; 20],
     borrow_amount: u128,
-    is_valid: u8,
+    merkle_root: [u8; 32],
+    oracle_key_hash: [u8; 32],
+    price_timestamp: u64,
+    collateral_assets: [[u8; 20]; MAX_COLLATERAL_ASSETS],
+    ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    borrow_index: u128,
+    blocklist_root: [u8; 32],
+    error_code: ErrorCode,
 }
 
 fn main() {
@@ -91,6 +169,43 @@ fn main() {
     }
 }
 
+/// Collateral token the vault expects, read from COLLATERAL_TOKEN in the environment
+fn collateral_token_from_env() -> [u8; 20] {
+    let mut token = [0u8; 20];
+    match env::var("COLLATERAL_TOKEN") {
+        Ok(address) => {
+            let bytes = hex::decode(address.trim_start_matches("0x")).expect("Invalid COLLATERAL_TOKEN");
+            token.copy_from_slice(&bytes);
+        }
+        Err(_) => eprintln!("⚠️  COLLATERAL_TOKEN not set, committing to the zero address"),
+    }
+    token
+}
+
+/// Leaf index, Merkle path and root of a note in the vault's commitment tree, rebuilt from the
+/// vault's events (VAULT and RPC_URL in the environment)
+fn vault_merkle_proof(commitment: &[u8; 32]) -> (u64, [[u8; 32]; MERKLE_DEPTH], [u8; 32]) {
+    let rpc_url = env::var("RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8545".to_string());
+    let vault_address: Address = env::var("VAULT").expect("VAULT must be set").parse().expect("Invalid VAULT");
+    let provider = Provider::<Http>::try_from(rpc_url.as_str()).expect("Invalid RPC_URL");
+
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start the async runtime");
+    let tree = runtime
+        .block_on(vault_tree::sync_tree(Arc::new(provider), vault_address))
+        .unwrap_or_else(|err| {
+            eprintln!("\n❌ Error: Failed to rebuild the vault's commitment tree: {}", err);
+            std::process::exit(1);
+        });
+
+    let Some(leaf_index) = tree.position(commitment) else {
+        eprintln!("\n❌ Error: Note 0x{} is not in the vault", hex::encode(commitment));
+        std::process::exit(1);
+    };
+    println!("   Note index: {} of {}", leaf_index, tree.len());
+
+    (leaf_index, tree.path(leaf_index).expect("leaf is in the tree"), tree.root())
+}
+
 fn generate_deposit_proof(amount_eth: u128, output_file: &str) {
     println!("\n🔐 Generating Deposit Proof");
     println!("========================================");
@@ -106,29 +221,38 @@ fn generate_deposit_proof(amount_eth: u128, output_file: &str) {
     let secret_key = [1u8; 32];
     let amount_wei = amount_eth * 1_000_000_000_000_000_000u128;
     let salt = [42u8; 32];
+    let collateral_token = collateral_token_from_env();
 
     let deposit_input = DepositInput {
         user_secret_key: secret_key,
-        collateral_amount: amount_wei,
+        collateral: single_asset(collateral_token, amount_wei),
         note_salt: salt,
+        depositor_address: [0u8; 20],
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&0u8); // Operation type: 0 = deposit
+    stdin.write(&OP_DEPOSIT);
     stdin.write(&deposit_input);
 
-    // Execute to get output
+    // Execute first so invalid inputs fail before any proving time is spent
     println!("🔨 Executing program...");
-    let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
-    let result: DepositOutput = output.read();
+    let (mut output, report) = client
+        .execute(ELF, &stdin)
+        .run()
+        .unwrap_or_else(|err| exit_on_execution_error("Deposit", err));
+    let result: DepositOutput = read_output(&mut output, OP_DEPOSIT).expect("proof of another operation");
     
     println!("✅ Execution complete:");
     println!("   Cycles: {}", report.total_instruction_count());
-    println!("   Valid: {}", result.is_valid);
+    println!("   Result: {}", result.error_code);
     println!("   Commitment: {:?}...", &result.commitment_hash[..8]);
+    for position in result.collateral.iter().filter(|position| position.amount > 0) {
+        println!("   Committed: {} wei of 0x{}", position.amount, hex::encode(position.asset));
+    }
 
-    if result.is_valid != 1 {
-        eprintln!("\n❌ Error: Deposit validation failed!");
+    if !result.error_code.is_success() {
+        eprintln!("\n❌ Error: Deposit rejected: {}", result.error_code);
         std::process::exit(1);
     }
 
@@ -169,6 +293,15 @@ fn generate_deposit_proof(amount_eth: u128, output_file: &str) {
     fs::write(&commitment_file, &commitment_hex).expect("failed to save commitment");
     println!("💾 Commitment saved to: {}", commitment_file);
 
+    // Save the note encrypted to our own viewing key (the vault's encryptedNote argument)
+    let note = NotePlaintext::Position {
+        collateral: deposit_input.collateral,
+        debt_amount: 0,
+        borrow_index: math::INDEX_SCALE,
+        salt,
+    };
+    save_encrypted_note(output_file, &secret_key, &result.commitment_hash, &note);
+
     println!("\n========================================");
     println!("✅ Deposit proof ready for on-chain verification!");
     println!("========================================\n");
@@ -209,36 +342,62 @@ fn generate_borrow_proof(collateral_eth: u128, borrow_usd: u128, output_file: &s
     let old_salt = [42u8; 32];
     let new_salt = [43u8; 32];
     let recipient = [0x12u8; 20];
+    let collateral_token = collateral_token_from_env();
+    let collateral = single_asset(collateral_token, collateral_wei);
+
+    // The old note is the deposit made with the same secret and salt, proven against the vault's tree
+    let old_commitment = crypto::hash_commitment(&secret_key, &collateral, 0, math::INDEX_SCALE, &old_salt);
+    let (leaf_index, merkle_path, merkle_root) = vault_merkle_proof(&old_commitment);
+
+    // Price signed by the local oracle stand-in (ORACLE_PRIVATE_KEY overrides the test key)
+    // The collateral token is the vault's first market; the second slot is unused
+    let oracle = LocalOracle::from_env();
+    let prices = [oracle.sign_price(eth_price_scaled, collateral_token), SignedPrice::default()];
+    println!("   Oracle: 0x{} (vault ORACLE_KEY_HASH)", hex::encode(oracle.key_hash()));
 
     let borrow_input = BorrowInput {
         user_secret_key: secret_key,
-        collateral_amount: collateral_wei,
-        collateral_price_usd: eth_price_scaled,
+        collateral,
+        prices,
         existing_debt: 0,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
         new_borrow_amount: borrow_scaled,
-        max_ltv_bps: 7500,
+        ltv_weights_bps: [7500, 0],
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
         old_note_salt: old_salt,
         new_note_salt: new_salt,
         recipient_address: recipient,
+        merkle_root,
+        merkle_path,
+        leaf_index,
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&1u8); // Operation type: 1 = borrow
+    stdin.write(&OP_BORROW);
     stdin.write(&borrow_input);
 
-    // Execute to get output
+    // Execute first so invalid inputs fail before any proving time is spent
     println!("\n🔨 Executing program...");
-    let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
-    let result: BorrowOutput = output.read();
+    let (mut output, report) = client
+        .execute(ELF, &stdin)
+        .run()
+        .unwrap_or_else(|err| exit_on_execution_error("Borrow", err));
+    let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
     
     println!("✅ Execution complete:");
     println!("   Cycles: {}", report.total_instruction_count());
-    println!("   Valid: {}", result.is_valid);
+    println!("   Result: {}", result.error_code);
     println!("   Nullifier: {:?}...", &result.nullifier_hash[..8]);
     println!("   New Commitment: {:?}...", &result.new_commitment_hash[..8]);
+    println!("   Merkle Root: {:?}...", &result.merkle_root[..8]);
+    println!("   Oracle Key Hash: 0x{}", hex::encode(result.oracle_key_hash));
+    println!("   Price Timestamp: {}", result.price_timestamp);
 
-    if result.is_valid != 1 {
-        eprintln!("\n❌ Error: Borrow validation failed!");
+    if !result.error_code.is_success() {
+        eprintln!("\n❌ Error: Borrow rejected: {}", result.error_code);
         std::process::exit(1);
     }
 
@@ -284,7 +443,32 @@ fn generate_borrow_proof(collateral_eth: u128, borrow_usd: u128, output_file: &s
     fs::write(&commitment_file, &commitment_hex).expect("failed to save commitment");
     println!("💾 Commitment saved to: {}", commitment_file);
 
+    let note = NotePlaintext::Position {
+        collateral,
+        debt_amount: borrow_scaled,
+        borrow_index: math::INDEX_SCALE,
+        salt: new_salt,
+    };
+    save_encrypted_note(output_file, &secret_key, &result.new_commitment_hash, &note);
+
     println!("\n========================================");
     println!("✅ Borrow proof ready for on-chain verification!");
     println!("========================================\n");
 }
+
+/// Strict builds of the program panic on invalid inputs instead of committing an error code,
+/// so the execution itself fails; report that rather than starting a proof
+fn exit_on_execution_error(operation: &str, err: impl std::fmt::Display) -> ! {
+    eprintln!("\n❌ Error: {} rejected during execution: {}", operation, err);
+    std::process::exit(1);
+}
+
+/// Encrypt a note to the owner's own address and save it hex-encoded next to the proof
+fn save_encrypted_note(output_file: &str, secret_key: &[u8; 32], commitment: &[u8; 32], note: &NotePlaintext) {
+    let address = crypto::derive_public_address(secret_key);
+    let encrypted_note = encrypt_note(&address.viewing_public_key, commitment, note);
+
+    let note_file = format!("{}.note", output_file);
+    fs::write(&note_file, hex::encode(encrypted_note.to_bytes())).expect("failed to save encrypted note");
+    println!("💾 Encrypted note saved to: {}", note_file);
+}
@@ -5,22 +5,135 @@ use std::time::Instant;
 // Embed the compiled SP1 ELF
 const ELF: &[u8] = include_bytes!("../../../zk-program/target/elf-compilation/riscv32im-succinct-zkvm-elf/release/zk-program");
 
+// Share note hashing and Merkle paths with the ZK program
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/collateral.rs"]
+mod collateral;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/error.rs"]
+mod error;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/hashing.rs"]
+mod hashing;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/math.rs"]
+mod math;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/merkle.rs"]
+mod merkle;
+#[allow(dead_code)]
+#[path = "../../../zk-program/src/operation.rs"]
+mod operation;
+#[path = "../proof_output.rs"]
+mod proof_output;
+#[allow(dead_code)]
+#[path = "../note_encryption.rs"]
+mod note_encryption;
+#[allow(dead_code)]
+#[path = "../wallet.rs"]
+mod wallet;
+
+use crypto::PublicAddress;
+use error::ErrorCode;
+use merkle::MERKLE_DEPTH;
+use operation::OP_TRANSFER;
+use proof_output::read_output;
+use note_encryption::{encrypt_note_with_ephemeral_secret, random_ephemeral_secret, EncryptedNote, NotePlaintext};
+use wallet::{stealth_note_key, Wallet};
+
+const TOKEN_ADDRESS: [u8; 20] = [0x1u8; 20];
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct TransferInput {
-    sender_secret: [u8; 32],
-    sender_balance: u128,
-    transfer_amount: u128,
+    sender_secret_key: [u8; 32],
     token_address: [u8; 20],
-    recipient_address: [u8; 20],
-    memo: [u8; 32],
-    nonce: u64,
+    balance: u128,
+    note_salt: [u8; 32],
+    note_shared_secret: [u8; 32],
+    transfer_amount: u128,
+    recipient_owner_key: [u8; 32],
+    recipient_note_salt: [u8; 32],
+    change_shared_secret: [u8; 32],
+    change_note_salt: [u8; 32],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct TransferOutput {
-    transfer_hash: [u8; 32],
-    sender_commitment: [u8; 32],
-    is_valid: u8,
+    nullifier_hash: [u8; 32],
+    recipient_commitment_hash: [u8; 32],
+    change_commitment_hash: [u8; 32],
+    merkle_root: [u8; 32],
+    error_code: ErrorCode,
+}
+
+/// Transfer `amount` to `recipient` out of a note holding `balance` that was sent to `sender`
+/// and committed alone in a fresh tree
+/// Returns the proof input and the (commitment, encrypted note) pairs published for the
+/// recipient and change notes
+fn shielded_transfer(
+    sender: &Wallet,
+    balance: u128,
+    amount: u128,
+    recipient: &PublicAddress,
+    nonce: u8,
+) -> (TransferInput, Vec<([u8; 32], EncryptedNote)>) {
+    let note_salt = [nonce; 32];
+    let recipient_note_salt = [nonce.wrapping_add(100); 32];
+    let change_note_salt = [nonce.wrapping_add(200); 32];
+
+    // Every note, change included, gets a one-time owner key from a fresh ephemeral secret
+    let recipient_ephemeral_secret = random_ephemeral_secret();
+    let change_ephemeral_secret = random_ephemeral_secret();
+    let note_key = stealth_note_key(&sender.address(), &random_ephemeral_secret());
+    let recipient_key = stealth_note_key(recipient, &recipient_ephemeral_secret);
+    let change_key = stealth_note_key(&sender.address(), &change_ephemeral_secret);
+
+    let commitment = crypto::hash_balance_commitment(
+        &note_key.announcement.owner_key,
+        &TOKEN_ADDRESS,
+        balance,
+        &note_salt,
+    );
+    let mut tree = merkle::IncrementalMerkleTree::new();
+    let leaf_index = tree.insert(commitment).expect("tree is full");
+
+    let input = TransferInput {
+        sender_secret_key: sender.spending_key(),
+        token_address: TOKEN_ADDRESS,
+        balance,
+        note_salt,
+        note_shared_secret: sender.scan(&note_key.announcement).expect("note was sent to the sender"),
+        transfer_amount: amount,
+        recipient_owner_key: recipient_key.announcement.owner_key,
+        recipient_note_salt,
+        change_shared_secret: change_key.shared_secret,
+        change_note_salt,
+        merkle_root: tree.root(),
+        merkle_path: tree.path(leaf_index).expect("leaf was just inserted"),
+        leaf_index,
+    };
+
+    // Encrypt both new notes to their owners under the ephemeral key of their owner key,
+    // so they can be found and spent from keys alone
+    let published = [
+        (recipient, &recipient_ephemeral_secret, &recipient_key, amount, recipient_note_salt),
+        (&sender.address(), &change_ephemeral_secret, &change_key, balance.saturating_sub(amount), change_note_salt),
+    ]
+    .map(|(owner, ephemeral_secret, note_key, amount, salt)| {
+        let commitment =
+            crypto::hash_balance_commitment(&note_key.announcement.owner_key, &TOKEN_ADDRESS, amount, &salt);
+        let note = NotePlaintext::Balance { token: TOKEN_ADDRESS, amount, salt };
+        let encrypted =
+            encrypt_note_with_ephemeral_secret(ephemeral_secret, &owner.viewing_public_key, &commitment, &note);
+        (commitment, encrypted)
+    });
+    (input, published.to_vec())
 }
 
 fn main() {
@@ -43,92 +156,137 @@ fn main() {
 
     println!("📋 Verification Key: {}\n", vk.bytes32());
 
+    // Recipients only share their public address
+    let alice = Wallet::new([1u8; 32]);
+    let bob = Wallet::new([2u8; 32]);
+
     // Test 1: Valid transfer (execution only)
     println!("[Test 1/3] Valid Transfer - Execution");
     println!("-----------------------------------");
     
-    let transfer1 = TransferInput {
-        sender_secret: [1u8; 32],
-        sender_balance: 1000_000_000, // 1000 tokens
-        transfer_amount: 100_000_000,  // 100 tokens
-        token_address: [0x1u8; 20],
-        recipient_address: [0x2u8; 20],
-        memo: [0u8; 32],
-        nonce: 1,
-    };
+    let (transfer1, published1) = shielded_transfer(&alice, 1_000_000_000, 100_000_000, &bob.address(), 1); // 100 of 1000 tokens
 
     let mut stdin1 = SP1Stdin::new();
     stdin1.write(&2u8); // Operation type: 2 = transfer
     stdin1.write(&transfer1);
 
-    println!("  📤 Sender Balance: {} tokens", transfer1.sender_balance / 1_000_000);
+    println!("  📤 Sender Balance: {} tokens", transfer1.balance / 1_000_000);
     println!("  💸 Transfer Amount: {} tokens", transfer1.transfer_amount / 1_000_000);
-    println!("  📍 Recipient: {:?}...\n", &transfer1.recipient_address[..4]);
+    println!("  📍 Recipient Key: 0x{}...\n", hex::encode(&transfer1.recipient_owner_key[..4]));
 
     let start = Instant::now();
     let proof1 = client.prove(&pk, &stdin1).run().expect("proving failed");
     let mut output1 = proof1.public_values.clone();
-    let result1: TransferOutput = output1.read();
+    let result1: TransferOutput = read_output(&mut output1, OP_TRANSFER).expect("proof of another operation");
 
     println!("  ✅ Execution: {:?}", start.elapsed());
-    println!("  ✅ Valid: {}", result1.is_valid);
-    println!("  🔐 Transfer Hash: 0x{}", hex::encode(&result1.transfer_hash[..8]));
-    println!("  🔒 Sender Commitment: 0x{}", hex::encode(&result1.sender_commitment[..8]));
+    println!("  ✅ Result: {}", result1.error_code);
+    println!("  🚫 Nullifier: 0x{}", hex::encode(&result1.nullifier_hash[..8]));
+    println!("  🔐 Recipient Note: 0x{}", hex::encode(&result1.recipient_commitment_hash[..8]));
+    println!("  🔒 Change Note: 0x{}", hex::encode(&result1.change_commitment_hash[..8]));
+
+    assert_eq!(result1.error_code, ErrorCode::Success, "Valid transfer should pass");
+    assert_eq!(
+        result1.recipient_commitment_hash,
+        crypto::hash_balance_commitment(
+            &transfer1.recipient_owner_key,
+            &TOKEN_ADDRESS,
+            transfer1.transfer_amount,
+            &transfer1.recipient_note_salt,
+        ),
+        "Recipient should be able to open their note"
+    );
 
-    assert_eq!(result1.is_valid, 1, "Valid transfer should pass");
+    assert_eq!(
+        [published1[0].0, published1[1].0],
+        [result1.recipient_commitment_hash, result1.change_commitment_hash],
+        "Published notes should match the proven commitments"
+    );
+
+    // Bob restores his note from the published ciphertexts with his keys alone, then spends it
+    let found = bob.scan_notes(&published1);
+    assert_eq!(found.len(), 1, "Bob should find only his note");
+    assert_eq!(found[0].commitment, result1.recipient_commitment_hash);
+    let change = alice.scan_notes(&published1);
+    assert_eq!(change.len(), 1, "Alice should find only her change");
+    assert_eq!(change[0].commitment, result1.change_commitment_hash);
+    println!("  🔎 Bob found {} note(s) by trial decryption", found.len());
+
+    let NotePlaintext::Balance { amount, salt, .. } = found[0].note else {
+        panic!("transfers create balance notes");
+    };
+    let mut tree = merkle::IncrementalMerkleTree::new();
+    let leaf_index = tree.insert(found[0].commitment).expect("tree is full");
+    let (mut respend, _) = shielded_transfer(&bob, 0, 40_000_000, &alice.address(), 4);
+    respend.balance = amount;
+    respend.note_salt = salt;
+    respend.note_shared_secret = found[0].shared_secret;
+    respend.merkle_root = tree.root();
+    respend.merkle_path = tree.path(leaf_index).expect("leaf was just inserted");
+    respend.leaf_index = leaf_index;
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_TRANSFER);
+    stdin.write(&respend);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: TransferOutput = read_output(&mut output, OP_TRANSFER).expect("proof of another operation");
+
+    println!("  ✅ Bob spends received note: {}", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::Success, "Recipient should be able to spend their note");
 
     // Test 2: Insufficient balance
     println!("\n[Test 2/3] Insufficient Balance - Should Fail");
     println!("-----------------------------------");
     
-    let transfer2 = TransferInput {
-        sender_secret: [1u8; 32],
-        sender_balance: 50_000_000,    // 50 tokens
-        transfer_amount: 100_000_000,   // 100 tokens (more than balance!)
-        token_address: [0x1u8; 20],
-        recipient_address: [0x2u8; 20],
-        memo: [0u8; 32],
-        nonce: 2,
-    };
+    let (transfer2, _) = shielded_transfer(&alice, 50_000_000, 100_000_000, &bob.address(), 2); // 100 of 50 tokens (more than balance!)
 
     let mut stdin2 = SP1Stdin::new();
     stdin2.write(&2u8);
     stdin2.write(&transfer2);
 
-    println!("  📤 Sender Balance: {} tokens", transfer2.sender_balance / 1_000_000);
+    println!("  📤 Sender Balance: {} tokens", transfer2.balance / 1_000_000);
     println!("  💸 Transfer Amount: {} tokens (TOO MUCH!)\n", transfer2.transfer_amount / 1_000_000);
 
     let start = Instant::now();
     let proof2 = client.prove(&pk, &stdin2).run().expect("proving failed");
     let mut output2 = proof2.public_values.clone();
-    let result2: TransferOutput = output2.read();
+    let result2: TransferOutput = read_output(&mut output2, OP_TRANSFER).expect("proof of another operation");
 
     println!("  ✅ Execution: {:?}", start.elapsed());
-    println!("  ❌ Valid: {} (expected 0)", result2.is_valid);
+    println!("  ❌ Rejected: {}", result2.error_code);
     println!("  ✅ Correctly rejected insufficient balance!");
 
-    assert_eq!(result2.is_valid, 0, "Insufficient balance should fail");
+    assert_eq!(result2.error_code, ErrorCode::InsufficientBalance, "Insufficient balance should fail");
+
+    // Claiming a larger balance than the note commits to breaks membership
+    let mut forged = transfer2.clone();
+    forged.balance = 1_000_000_000;
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_TRANSFER);
+    stdin.write(&forged);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: TransferOutput = read_output(&mut output, OP_TRANSFER).expect("proof of another operation");
+
+    println!("  ❌ Forged balance rejected: {}", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::MembershipFailed, "Uncommitted balance should fail");
 
     // Test 3: Generate real Groth16 proof using SP1 Network
     println!("\n[Test 3/3] Generate Groth16 Proof (SP1 Network)");
     println!("-----------------------------------");
     
-    let transfer3 = TransferInput {
-        sender_secret: [1u8; 32],
-        sender_balance: 1000_000_000, // 1000 tokens
-        transfer_amount: 250_000_000,  // 250 tokens
-        token_address: [0x1u8; 20],
-        recipient_address: [0x3u8; 20],
-        memo: *b"Private transfer with ZK proof!!", // 32 bytes
-        nonce: 3,
-    };
+    let (transfer3, _) = shielded_transfer(&alice, 1_000_000_000, 250_000_000, &Wallet::new([3u8; 32]).address(), 3); // 250 of 1000 tokens
 
     let mut stdin3 = SP1Stdin::new();
     stdin3.write(&2u8);
     stdin3.write(&transfer3);
 
     println!("  💸 Transfer: 250 tokens");
-    println!("  📝 Memo: \"Private transfer with ZK proof!!\"");
+    println!("  🔑 Recipient and change notes hidden behind commitments");
     println!("  🌐 Using SP1 Network Prover (cloud-based)");
     println!("  ⏱️  Expected time: ~30-60 seconds\n");
 
@@ -157,19 +315,21 @@ fn main() {
 
     // Decode output
     let mut output3 = groth16_proof.public_values.clone();
-    let result3: TransferOutput = output3.read();
+    let result3: TransferOutput = read_output(&mut output3, OP_TRANSFER).expect("proof of another operation");
 
-    println!("  🔐 Transfer Hash: 0x{}", hex::encode(&result3.transfer_hash[..16]));
-    println!("  🔒 Sender Commitment: 0x{}", hex::encode(&result3.sender_commitment[..16]));
-    println!("  ✅ Valid: {}", result3.is_valid);
+    println!("  🚫 Nullifier: 0x{}", hex::encode(&result3.nullifier_hash[..16]));
+    println!("  🔐 Recipient Note: 0x{}", hex::encode(&result3.recipient_commitment_hash[..16]));
+    println!("  🔒 Change Note: 0x{}", hex::encode(&result3.change_commitment_hash[..16]));
+    println!("  ✅ Result: {}", result3.error_code);
 
     println!("\n========================================");
     println!("✅ All Transfer Tests Complete!");
     println!("========================================");
     println!("\n💡 Key Features Demonstrated:");
     println!("  ✅ Private transfers (amounts hidden)");
-    println!("  ✅ Balance validation in ZK");
-    println!("  ✅ Transfer hash commitment");
+    println!("  ✅ Spent note proven in the commitment tree");
+    println!("  ✅ Recipient and change notes as public commitments");
+    println!("  ✅ One-time owner keys and encrypted notes found by viewing-key scanning");
     println!("  ✅ Real Groth16 proofs (384 bytes)");
     println!("  ✅ Fast execution (<60s with network prover)");
     println!("  ✅ Ready for on-chain verification\n");
@@ -12,31 +12,102 @@ use std::{error::Error, sync::Arc};
 // Embed the compiled SP1 ELF
 const ELF: &[u8] = include_bytes!("../../zk-program/target/elf-compilation/riscv32im-succinct-zkvm-elf/release/zk-program");
 
+// Share note collateral, hashing, debt math, Merkle paths and oracle messages with the ZK program
+#[allow(dead_code)]
+#[path = "../../zk-program/src/blocklist.rs"]
+mod blocklist;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/collateral.rs"]
+mod collateral;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/error.rs"]
+mod error;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/hashing.rs"]
+mod hashing;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/math.rs"]
+mod math;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/merkle.rs"]
+mod merkle;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/operation.rs"]
+mod operation;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/oracle.rs"]
+mod oracle;
+#[path = "local_oracle.rs"]
+mod local_oracle;
+#[path = "proof_output.rs"]
+mod proof_output;
+#[allow(dead_code)]
+#[path = "note_encryption.rs"]
+mod note_encryption;
+#[allow(dead_code)]
+#[path = "wallet.rs"]
+mod wallet;
+#[path = "vault_tree.rs"]
+mod vault_tree;
+
+use blocklist::BlocklistProof;
+use collateral::{single_asset, CollateralPosition, MAX_COLLATERAL_ASSETS};
+use error::ErrorCode;
+use local_oracle::{LocalOracle, SignedPrice};
+use merkle::MERKLE_DEPTH;
+use operation::{OP_BORROW, OP_DEPOSIT};
+use proof_output::read_output;
+use note_encryption::{encrypt_note, EncryptedNote, NotePlaintext};
+use vault_tree::sync_tree;
+use wallet::{OwnedNote, Wallet};
+
+/// Decimals of the deployed MockUSDC debt token (collateral decimals come from the vault)
+const DEBT_DECIMALS: u8 = 6;
+
+/// Local price of collateral markets other than the vault's main token ($0.80, e.g. WMNT)
+const OTHER_COLLATERAL_PRICE: u128 = 800_000;
+
 // Data structures matching ZK program
 #[derive(Serialize, Deserialize, Debug)]
 struct DepositInput {
     user_secret_key: [u8; 32],
-    collateral_amount: u128,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
     note_salt: [u8; 32],
+    depositor_address: [u8; 20],
+    blocklist: Option<BlocklistProof>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct DepositOutput {
     commitment_hash: [u8; 32],
-    is_valid: u8,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    depositor_address: [u8; 20],
+    blocklist_root: [u8; 32],
+    error_code: ErrorCode,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct BorrowInput {
     user_secret_key: [u8; 32],
-    collateral_amount: u128,
-    collateral_price_usd: u128,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
     existing_debt: u128,
+    note_borrow_index: u128,
+    borrow_index: u128,
     new_borrow_amount: u128,
-    max_ltv_bps: u16,
+    ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
     old_note_salt: [u8; 32],
     new_note_salt: [u8; 32],
     recipient_address: [u8; 20],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+    blocklist: Option<BlocklistProof>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -45,7 +116,16 @@ struct BorrowOutput {
     new_commitment_hash: [u8; 32],
     recipient_address: [u8; 20],
     borrow_amount: u128,
-    is_valid: u8,
+    merkle_root: [u8; 32],
+    oracle_key_hash: [u8; 32],
+    price_timestamp: u64,
+    collateral_assets: [[u8; 20]; MAX_COLLATERAL_ASSETS],
+    ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    borrow_index: u128,
+    blocklist_root: [u8; 32],
+    error_code: ErrorCode,
 }
 
 // Generate contract bindings
@@ -60,12 +140,16 @@ abigen!(
 abigen!(
     AegisVault,
     r#"[
-        function deposit(uint256 amount, bytes calldata proof, bytes calldata publicValues) external
-        function borrow(bytes calldata proof, bytes calldata publicValues) external
+        function deposit(bytes calldata proof, bytes calldata publicValues, bytes calldata encryptedNote) external
+        function borrow(bytes calldata proof, bytes calldata publicValues, bytes calldata encryptedNote) external
         function getCommitmentCount() external view returns (uint256)
         function getCollateralBalance() external view returns (uint256)
         function getDebtBalance() external view returns (uint256)
         function isNullifierSpent(bytes32 nullifierHash) external view returns (bool)
+        function setOracleKeyHash(bytes32 oracleKeyHash) external
+        function borrowIndex() external view returns (uint256)
+        function collateralAssets(uint256 slot) external view returns (address token, uint16 ltvBps, uint8 decimals)
+        event NoteEncrypted(bytes32 indexed commitment, uint256 leafIndex, bytes encryptedNote)
     ]"#
 );
 
@@ -84,6 +168,7 @@ pub struct IntegrationTest {
     collateral_address: Address,
     debt_address: Address,
     prover_client: ProverClient,
+    wallet: Wallet,
 }
 
 impl IntegrationTest {
@@ -110,6 +195,7 @@ impl IntegrationTest {
             collateral_address: collateral_addr.parse()?,
             debt_address: debt_addr.parse()?,
             prover_client,
+            wallet: Wallet::new([1u8; 32]),
         })
     }
 
@@ -120,11 +206,11 @@ impl IntegrationTest {
 
         // Step 1: Deposit
         println!("[STEP 1] Generating deposit proof and submitting...");
-        let (commitment, secret_key, salt) = self.test_deposit().await?;
+        self.test_deposit().await?;
         
-        // Step 2: Borrow
+        // Step 2: Borrow (the note is restored from the vault's events, not kept in memory)
         println!("\n[STEP 2] Generating borrow proof and submitting...");
-        self.test_borrow(secret_key, salt, commitment).await?;
+        self.test_borrow().await?;
 
         println!("\n========================================");
         println!("  ✅ Integration Test Complete!");
@@ -133,27 +219,64 @@ impl IntegrationTest {
         Ok(())
     }
 
-    async fn test_deposit(&self) -> Result<([u8; 32], [u8; 32], [u8; 32]), Box<dyn Error>> {
-        let secret_key = [1u8; 32];
+    /// Restore this wallet's unspent position notes by trial-decrypting every published note
+    async fn scan_position_notes(&self) -> Result<Vec<(u64, OwnedNote)>, Box<dyn Error>> {
+        let vault = AegisVault::new(self.vault_address, self.client.clone());
+
+        let mut published = Vec::new();
+        let mut leaf_indices = Vec::new();
+        for event in vault.note_encrypted_filter().from_block(0u64).query().await? {
+            // Anyone can publish bytes; skip payloads that aren't even well-formed
+            if let Some(encrypted) = EncryptedNote::from_bytes(&event.encrypted_note) {
+                published.push((event.commitment, encrypted));
+                leaf_indices.push(event.leaf_index.as_u64());
+            }
+        }
+
+        let nullifier_key = crypto::derive_nullifier_key(&self.wallet.spending_key());
+        let mut unspent = Vec::new();
+        for owned in self.wallet.scan_notes(&published) {
+            if !matches!(owned.note, NotePlaintext::Position { .. }) {
+                continue;
+            }
+
+            let leaf_index = leaf_indices[owned.position];
+            let nullifier_hash = crypto::hash_nullifier(&nullifier_key, &owned.commitment, leaf_index);
+            if !vault.is_nullifier_spent(nullifier_hash).call().await? {
+                unspent.push((leaf_index, owned));
+            }
+        }
+
+        Ok(unspent)
+    }
+
+    async fn test_deposit(&self) -> Result<(), Box<dyn Error>> {
+        let secret_key = self.wallet.spending_key();
         let collateral_amount = 10_000_000_000_000_000_000u128; // 10 ETH
         let salt = [42u8; 32];
+        let collateral_token: [u8; 20] = self.collateral_address.0;
 
         println!("  💰 Depositing 10 ETH (amount will be hidden)...");
 
         // Generate ZK proof
         let deposit_input = DepositInput {
             user_secret_key: secret_key,
-            collateral_amount,
+            collateral: single_asset(collateral_token, collateral_amount),
             note_salt: salt,
+            depositor_address: self.client.address().0,
+            blocklist: None, // Screening is only needed once the vault sets a blocklist root
         };
 
         let mut stdin = SP1Stdin::new();
-        stdin.write(&0u8); // Operation: deposit
+        stdin.write(&OP_DEPOSIT);
         stdin.write(&deposit_input);
 
         // Execute to get output
         let (mut output, report) = self.prover_client.execute(ELF, &stdin).run()?;
-        let result: DepositOutput = output.read();
+        let result: DepositOutput = read_output(&mut output, OP_DEPOSIT).expect("proof of another operation");
+        if !result.error_code.is_success() {
+            return Err(format!("deposit rejected: {}", result.error_code).into());
+        }
         
         println!("  ✓ ZK proof generated ({} cycles)", report.total_instruction_count());
         println!("  ✓ Commitment: 0x{}", hex::encode(&result.commitment_hash[..8]));
@@ -161,10 +284,28 @@ impl IntegrationTest {
         // For local testing with MockVerifier, we use a dummy proof
         let proof = vec![0u8]; // Mock proof
         
-        // Encode public values (commitment + is_valid)
-        let mut public_values = Vec::new();
+        // Encode public values for Solidity (158 bytes total): operation code (1) + commitment (32)
+        // + per slot: token (20) and amount as little-endian u128 (16)
+        // + depositor (20) + blocklist root (32) + error code (1)
+        let mut public_values = vec![OP_DEPOSIT];
         public_values.extend_from_slice(&result.commitment_hash);
-        public_values.push(result.is_valid);
+        for position in &result.collateral {
+            public_values.extend_from_slice(&position.asset);
+            public_values.extend_from_slice(&position.amount.to_le_bytes());
+        }
+        public_values.extend_from_slice(&result.depositor_address);
+        public_values.extend_from_slice(&result.blocklist_root);
+        public_values.push(result.error_code as u8);
+
+        // Encrypt the note to our own viewing key so it can be restored from the chain
+        let note = NotePlaintext::Position {
+            collateral: deposit_input.collateral,
+            debt_amount: 0,
+            borrow_index: math::INDEX_SCALE,
+            salt,
+        };
+        let encrypted_note =
+            encrypt_note(&self.wallet.address().viewing_public_key, &result.commitment_hash, &note);
 
         // Approve collateral
         let collateral = MockETH::new(self.collateral_address, self.client.clone());
@@ -178,11 +319,7 @@ impl IntegrationTest {
         // Submit deposit
         let vault = AegisVault::new(self.vault_address, self.client.clone());
         let deposit_tx = vault
-            .deposit(
-                U256::from(collateral_amount),
-                proof.into(),
-                public_values.into(),
-            )
+            .deposit(proof.into(), public_values.into(), encrypted_note.to_bytes().into())
             .send()
             .await?
             .await?;
@@ -196,16 +333,11 @@ impl IntegrationTest {
         println!("  ✓ Vault now has {} commitments", commitment_count);
         println!("  ✓ Vault collateral balance: {} ETH", collateral_balance / U256::from(10u128.pow(18)));
 
-        Ok((result.commitment_hash, secret_key, salt))
+        Ok(())
     }
 
-    async fn test_borrow(
-        &self,
-        secret_key: [u8; 32],
-        old_salt: [u8; 32],
-        _commitment: [u8; 32],
-    ) -> Result<(), Box<dyn Error>> {
-        let collateral_amount = 10_000_000_000_000_000_000u128; // 10 ETH
+    async fn test_borrow(&self) -> Result<(), Box<dyn Error>> {
+        let secret_key = self.wallet.spending_key();
         let collateral_price = 2500_000_000u128; // $2500 with 6 decimals
         let borrow_amount = 5000_000_000u128; // 5000 USDC
         let new_salt = [43u8; 32];
@@ -220,33 +352,92 @@ impl IntegrationTest {
         println!("     Price: $2500/ETH");
         println!("     LTV: 20% (safe)");
 
+        // Restore our latest unspent note from the chain using only the wallet's keys
+        let (leaf_index, owned) = self
+            .scan_position_notes()
+            .await?
+            .pop()
+            .ok_or("no unspent note found for this wallet")?;
+        let NotePlaintext::Position { collateral, debt_amount, borrow_index: note_borrow_index, salt: old_salt } =
+            owned.note
+        else {
+            unreachable!("scan_position_notes only returns position notes");
+        };
+
+        println!("     Restored note 0x{} from its encrypted payload", hex::encode(&owned.commitment[..8]));
+
+        // Rebuild the vault's tree for a path to the restored note
+        let tree = sync_tree(self.client.clone(), self.vault_address).await?;
+        let merkle_path = tree.path(leaf_index).ok_or("missing Merkle path")?;
+        let merkle_root = tree.root();
+
+        println!("     Note index: {} of {}", leaf_index, tree.len());
+
+        // Sign a price for every market the vault lists and make sure it trusts the oracle
+        let oracle = LocalOracle::from_env();
+        let vault = AegisVault::new(self.vault_address, self.client.clone());
+        vault.set_oracle_key_hash(oracle.key_hash()).send().await?.await?;
+
+        let mut prices: [SignedPrice; MAX_COLLATERAL_ASSETS] = Default::default();
+        let mut ltv_weights_bps = [0u16; MAX_COLLATERAL_ASSETS];
+        let mut collateral_decimals = [0u8; MAX_COLLATERAL_ASSETS];
+        for slot in 0..MAX_COLLATERAL_ASSETS {
+            let (token, ltv_bps, decimals) = vault.collateral_assets(U256::from(slot)).call().await?;
+            if token == Address::zero() {
+                continue;
+            }
+
+            let price_usd = if token == self.collateral_address {
+                collateral_price
+            } else {
+                OTHER_COLLATERAL_PRICE
+            };
+            prices[slot] = oracle.sign_price(price_usd, token.0);
+            ltv_weights_bps[slot] = ltv_bps;
+            collateral_decimals[slot] = decimals;
+        }
+
+        // Debt accrues from the note's index up to the vault's current borrow index
+        let borrow_index = vault.borrow_index().call().await?.as_u128();
+
         // Generate ZK proof
         let borrow_input = BorrowInput {
             user_secret_key: secret_key,
-            collateral_amount,
-            collateral_price_usd: collateral_price,
-            existing_debt: 0,
+            collateral,
+            prices,
+            existing_debt: debt_amount,
+            note_borrow_index,
+            borrow_index,
             new_borrow_amount: borrow_amount,
-            max_ltv_bps: 7500, // 75%
+            ltv_weights_bps,
+            collateral_decimals,
+            debt_decimals: DEBT_DECIMALS,
             old_note_salt: old_salt,
             new_note_salt: new_salt,
             recipient_address: recipient_bytes,
+            merkle_root,
+            merkle_path,
+            leaf_index,
+            blocklist: None,
         };
 
         let mut stdin = SP1Stdin::new();
-        stdin.write(&1u8); // Operation: borrow
+        stdin.write(&OP_BORROW);
         stdin.write(&borrow_input);
 
         // Execute to get output
         let (mut output, report) = self.prover_client.execute(ELF, &stdin).run()?;
-        let result: BorrowOutput = output.read();
+        let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
+        if !result.error_code.is_success() {
+            return Err(format!("borrow rejected: {}", result.error_code).into());
+        }
 
         println!("  ✓ ZK proof generated ({} cycles)", report.total_instruction_count());
         println!("  ✓ Nullifier: 0x{}", hex::encode(&result.nullifier_hash[..8]));
         println!("  ✓ New commitment: 0x{}", hex::encode(&result.new_commitment_hash[..8]));
 
-        // Encode public values for Solidity (101 bytes total)
-        let mut public_values = vec![0u8; 101];
+        // Encode public values for Solidity (268 bytes total)
+        let mut public_values = vec![0u8; 268];
         public_values[0..32].copy_from_slice(&result.nullifier_hash);
         public_values[32..64].copy_from_slice(&result.new_commitment_hash);
         public_values[64..84].copy_from_slice(&result.recipient_address);
@@ -255,7 +446,33 @@ impl IntegrationTest {
         for i in 0..16 {
             public_values[84 + i] = ((result.borrow_amount >> (8 * i)) & 0xFF) as u8;
         }
-        public_values[100] = result.is_valid;
+        public_values[100..132].copy_from_slice(&result.merkle_root);
+        public_values[132..164].copy_from_slice(&result.oracle_key_hash);
+        public_values[164..172].copy_from_slice(&result.price_timestamp.to_le_bytes());
+        for slot in 0..MAX_COLLATERAL_ASSETS {
+            public_values[172 + 20 * slot..192 + 20 * slot].copy_from_slice(&result.collateral_assets[slot]);
+            public_values[212 + 2 * slot..214 + 2 * slot].copy_from_slice(&result.ltv_weights_bps[slot].to_le_bytes());
+            public_values[216 + slot] = result.collateral_decimals[slot];
+        }
+        public_values[218] = result.debt_decimals;
+        public_values[219..235].copy_from_slice(&result.borrow_index.to_le_bytes());
+        public_values[235..267].copy_from_slice(&result.blocklist_root);
+        public_values[267] = result.error_code as u8;
+
+        // The operation code comes first (269 bytes total)
+        public_values.insert(0, OP_BORROW);
+
+        // Publish the new note (accrued debt plus the new loan, recorded at the current index)
+        let accrued_debt = math::accrue_debt(debt_amount, note_borrow_index, borrow_index)
+            .map_err(|err| format!("cannot accrue note debt: {:?}", err))?;
+        let new_note = NotePlaintext::Position {
+            collateral,
+            debt_amount: accrued_debt + borrow_amount,
+            borrow_index,
+            salt: new_salt,
+        };
+        let encrypted_note =
+            encrypt_note(&self.wallet.address().viewing_public_key, &result.new_commitment_hash, &new_note);
 
         // Check balance before
         let debt_token = MockUSDC::new(self.debt_address, self.client.clone());
@@ -263,9 +480,8 @@ impl IntegrationTest {
 
         // Submit borrow
         let proof = vec![1u8]; // Mock proof
-        let vault = AegisVault::new(self.vault_address, self.client.clone());
         let borrow_tx = vault
-            .borrow(proof.into(), public_values.into())
+            .borrow(proof.into(), public_values.into(), encrypted_note.to_bytes().into())
             .send()
             .await?
             .await?;
@@ -0,0 +1,83 @@
+// Local stand-in for the price oracle: signs test prices with a fixed key
+// Message encoding and key hashing are shared with the ZK program via `oracle`
+use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::oracle;
+
+/// Oracle key used when ORACLE_PRIVATE_KEY is not set (local runs only)
+const DEFAULT_ORACLE_KEY: [u8; 32] = [0x0Au8; 32];
+
+/// Oracle-signed collateral price (matches the ZK program; unused slots are the default)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SignedPrice {
+    pub price_usd: u128,
+    pub timestamp: u64,
+    pub asset: [u8; 20],
+    pub oracle_public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Signs prices the way the production oracle would
+pub struct LocalOracle {
+    signing_key: SigningKey,
+}
+
+impl LocalOracle {
+    /// Create an oracle from a raw secp256k1 private key
+    pub fn new(private_key: [u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_slice(&private_key).expect("invalid oracle private key"),
+        }
+    }
+
+    /// Load the key from ORACLE_PRIVATE_KEY (hex), falling back to the local test key
+    pub fn from_env() -> Self {
+        match std::env::var("ORACLE_PRIVATE_KEY") {
+            Ok(hex_key) => {
+                let bytes = hex::decode(hex_key.trim_start_matches("0x"))
+                    .expect("ORACLE_PRIVATE_KEY must be hex");
+                let key: [u8; 32] = bytes
+                    .try_into()
+                    .expect("ORACLE_PRIVATE_KEY must be 32 bytes");
+                Self::new(key)
+            }
+            Err(_) => Self::new(DEFAULT_ORACLE_KEY),
+        }
+    }
+
+    /// SEC1-compressed public key (33 bytes)
+    pub fn public_key(&self) -> Vec<u8> {
+        self.signing_key.verifying_key().to_sec1_bytes().to_vec()
+    }
+
+    /// Key hash committed by borrow proofs (configure the vault with this value)
+    pub fn key_hash(&self) -> [u8; 32] {
+        oracle::hash_oracle_key(&self.public_key())
+    }
+
+    /// Sign a price for `asset` at the current time
+    pub fn sign_price(&self, price_usd: u128, asset: [u8; 20]) -> SignedPrice {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock before unix epoch")
+            .as_secs();
+
+        self.sign_price_at(price_usd, timestamp, asset)
+    }
+
+    /// Sign a price for `asset` at an explicit timestamp
+    pub fn sign_price_at(&self, price_usd: u128, timestamp: u64, asset: [u8; 20]) -> SignedPrice {
+        let message = oracle::price_message(price_usd, timestamp, &asset);
+        let signature: Signature = self.signing_key.sign(&message);
+
+        SignedPrice {
+            price_usd,
+            timestamp,
+            asset,
+            oracle_public_key: self.public_key(),
+            signature: signature.to_bytes().to_vec(),
+        }
+    }
+}
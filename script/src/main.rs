@@ -5,31 +5,114 @@ use std::time::Instant;
 // Embed the compiled SP1 ELF for the zk-program.
 const ELF: &[u8] = include_bytes!("../../zk-program/target/elf-compilation/riscv32im-succinct-zkvm-elf/release/zk-program");
 
+// Share note hashing and Merkle paths with the ZK program
+#[allow(dead_code)]
+#[path = "../../zk-program/src/blocklist.rs"]
+mod blocklist;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/collateral.rs"]
+mod collateral;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/error.rs"]
+mod error;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/hashing.rs"]
+mod hashing;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/math.rs"]
+mod math;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/merkle.rs"]
+mod merkle;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/operation.rs"]
+mod operation;
+#[allow(dead_code)]
+#[path = "../../zk-program/src/oracle.rs"]
+mod oracle;
+mod local_oracle;
+mod proof_output;
+
+use blocklist::{Blocklist, BlocklistProof};
+use collateral::{single_asset, CollateralMarket, CollateralPosition, MAX_COLLATERAL_ASSETS};
+use error::ErrorCode;
+use local_oracle::{LocalOracle, SignedPrice};
+use merkle::MERKLE_DEPTH;
+use operation::*;
+use proof_output::read_output;
+
+/// Collateral token address used in local runs (the vault checks it on-chain)
+const COLLATERAL_TOKEN: [u8; 20] = [0xEEu8; 20];
+
+/// Second collateral token used to exercise multi-asset notes (e.g. WMNT, 18 decimals)
+const SECOND_COLLATERAL_TOKEN: [u8; 20] = [0xDDu8; 20];
+
+/// Token decimals of the local market (18-decimal collateral, 6-decimal debt)
+const COLLATERAL_DECIMALS: u8 = 18;
+const DEBT_DECIMALS: u8 = 6;
+
+/// Most notes a join-split can spend or create (matching the ZK program)
+const MAX_SPLIT_NOTES: usize = 2;
+
+/// Most deposits one batch deposit proof can create (must match the ZK program)
+const MAX_BATCH_DEPOSITS: usize = 16;
+
 // Data structures matching the ZK program
 #[derive(Serialize, Deserialize, Debug)]
 struct DepositInput {
     user_secret_key: [u8; 32],
-    collateral_amount: u128,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
     note_salt: [u8; 32],
+    depositor_address: [u8; 20],
+    blocklist: Option<BlocklistProof>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct DepositOutput {
     commitment_hash: [u8; 32],
-    is_valid: u8,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    depositor_address: [u8; 20],
+    blocklist_root: [u8; 32],
+    error_code: ErrorCode,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BatchDepositInput {
+    deposits: Vec<DepositInput>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BatchDepositOutput {
+    commitment_hashes: [[u8; 32]; MAX_BATCH_DEPOSITS],
+    deposit_count: u8,
+    total_collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    depositor_address: [u8; 20],
+    blocklist_root: [u8; 32],
+    error_code: ErrorCode,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct BorrowInput {
     user_secret_key: [u8; 32],
-    collateral_amount: u128,
-    collateral_price_usd: u128,
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
     existing_debt: u128,
+    note_borrow_index: u128,
+    borrow_index: u128,
     new_borrow_amount: u128,
-    max_ltv_bps: u16,
+    ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
     old_note_salt: [u8; 32],
     new_note_salt: [u8; 32],
     recipient_address: [u8; 20],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+    blocklist: Option<BlocklistProof>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -38,7 +121,274 @@ struct BorrowOutput {
     new_commitment_hash: [u8; 32],
     recipient_address: [u8; 20],
     borrow_amount: u128,
-    is_valid: u8,
+    merkle_root: [u8; 32],
+    oracle_key_hash: [u8; 32],
+    price_timestamp: u64,
+    collateral_assets: [[u8; 20]; MAX_COLLATERAL_ASSETS],
+    ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    borrow_index: u128,
+    blocklist_root: [u8; 32],
+    error_code: ErrorCode,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct TransferInput {
+    sender_secret_key: [u8; 32],
+    token_address: [u8; 20],
+    balance: u128,
+    note_salt: [u8; 32],
+    note_shared_secret: [u8; 32],
+    transfer_amount: u128,
+    recipient_owner_key: [u8; 32],
+    recipient_note_salt: [u8; 32],
+    change_shared_secret: [u8; 32],
+    change_note_salt: [u8; 32],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RepayInput {
+    user_secret_key: [u8; 32],
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    existing_debt: u128,
+    note_borrow_index: u128,
+    borrow_index: u128,
+    repay_amount: u128,
+    old_note_salt: [u8; 32],
+    new_note_salt: [u8; 32],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RepayOutput {
+    nullifier_hash: [u8; 32],
+    new_commitment_hash: [u8; 32],
+    repay_amount: u128,
+    merkle_root: [u8; 32],
+    borrow_index: u128,
+    error_code: ErrorCode,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct WithdrawInput {
+    user_secret_key: [u8; 32],
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    existing_debt: u128,
+    note_borrow_index: u128,
+    borrow_index: u128,
+    withdraw_asset: [u8; 20],
+    withdraw_amount: u128,
+    prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
+    ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    old_note_salt: [u8; 32],
+    new_note_salt: [u8; 32],
+    recipient_address: [u8; 20],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct WithdrawOutput {
+    nullifier_hash: [u8; 32],
+    new_commitment_hash: [u8; 32],
+    recipient_address: [u8; 20],
+    withdraw_asset: [u8; 20],
+    withdraw_amount: u128,
+    merkle_root: [u8; 32],
+    oracle_key_hash: [u8; 32],
+    price_timestamp: u64,
+    markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    borrow_index: u128,
+    error_code: ErrorCode,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LiquidationInput {
+    user_secret_key: [u8; 32],
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    debt_amount: u128,
+    note_borrow_index: u128,
+    borrow_index: u128,
+    note_salt: [u8; 32],
+    prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
+    liquidation_thresholds_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    recipient_address: [u8; 20],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LiquidationOutput {
+    nullifier_hash: [u8; 32],
+    seized_collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    debt_to_repay: u128,
+    markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    oracle_key_hash: [u8; 32],
+    price_timestamp: u64,
+    recipient_address: [u8; 20],
+    merkle_root: [u8; 32],
+    borrow_index: u128,
+    error_code: ErrorCode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SpentNote {
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    debt_amount: u128,
+    note_borrow_index: u128,
+    salt: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CreatedNote {
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    debt_amount: u128,
+    salt: [u8; 32],
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct JoinSplitInput {
+    user_secret_key: [u8; 32],
+    spent_count: u8,
+    spent_notes: [SpentNote; MAX_SPLIT_NOTES],
+    created_count: u8,
+    created_notes: [CreatedNote; MAX_SPLIT_NOTES],
+    borrow_index: u128,
+    prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
+    ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    merkle_root: [u8; 32],
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct JoinSplitOutput {
+    nullifier_hashes: [[u8; 32]; MAX_SPLIT_NOTES],
+    new_commitment_hashes: [[u8; 32]; MAX_SPLIT_NOTES],
+    markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+    debt_decimals: u8,
+    oracle_key_hash: [u8; 32],
+    price_timestamp: u64,
+    merkle_root: [u8; 32],
+    borrow_index: u128,
+    error_code: ErrorCode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+enum DisclosureStatement {
+    MinCollateral {
+        asset: [u8; 20],
+        min_amount: u128,
+    },
+    MaxLtv {
+        markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+        max_ltv_bps: u16,
+        debt_decimals: u8,
+        borrow_index: u128,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DisclosureInput {
+    user_secret_key: [u8; 32],
+    collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    debt_amount: u128,
+    note_borrow_index: u128,
+    note_salt: [u8; 32],
+    statement: DisclosureStatement,
+    challenge: [u8; 32],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DisclosureOutput {
+    statement: DisclosureStatement,
+    owner_key: [u8; 32],
+    challenge: [u8; 32],
+    merkle_root: [u8; 32],
+    error_code: ErrorCode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+enum LegacyNote {
+    Position {
+        collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+        debt_amount: u128,
+        borrow_index: u128,
+    },
+    #[allow(dead_code)]
+    Balance {
+        token: [u8; 20],
+        amount: u128,
+        shared_secret: [u8; 32],
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MigrationInput {
+    user_secret_key: [u8; 32],
+    note: LegacyNote,
+    note_salt: [u8; 32],
+    new_note_salt: [u8; 32],
+    merkle_root: [u8; 32],
+    merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    leaf_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MigrationOutput {
+    nullifier_hash: [u8; 32],
+    new_commitment_hash: [u8; 32],
+    merkle_root: [u8; 32],
+    error_code: ErrorCode,
+}
+
+/// Build the tree holding a single note at index 0 and return its (root, path)
+fn single_note_tree(commitment: &[u8; 32]) -> ([u8; 32], [[u8; 32]; MERKLE_DEPTH]) {
+    let mut tree = merkle::IncrementalMerkleTree::new();
+    let index = tree.insert(*commitment).expect("tree is full");
+    let path = tree.path(index).expect("leaf was just inserted");
+    (tree.root(), path)
+}
+
+/// Sign the local market's collateral price with the local oracle at the current time
+/// (the collateral token is the only market; the second slot is unused)
+fn signed_prices(price_usd: u128) -> [SignedPrice; MAX_COLLATERAL_ASSETS] {
+    [
+        LocalOracle::from_env().sign_price(price_usd, COLLATERAL_TOKEN),
+        SignedPrice::default(),
+    ]
+}
+
+/// Local market table valuing the collateral token at `price_usd` with weight `weight_bps`
+fn local_markets(price_usd: u128, weight_bps: u16) -> [CollateralMarket; MAX_COLLATERAL_ASSETS] {
+    [
+        CollateralMarket {
+            asset: COLLATERAL_TOKEN,
+            price_usd,
+            weight_bps,
+            decimals: COLLATERAL_DECIMALS,
+        },
+        CollateralMarket::default(),
+    ]
 }
 
 fn main() {
@@ -98,12 +448,14 @@ fn test_deposit() {
 
     let deposit_input = DepositInput {
         user_secret_key: secret_key,
-        collateral_amount,
+        collateral: single_asset(COLLATERAL_TOKEN, collateral_amount),
         note_salt: salt,
+        depositor_address: [0u8; 20],
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&0u8); // Operation type: 0 = deposit
+    stdin.write(&OP_DEPOSIT);
     stdin.write(&deposit_input);
 
     let client = ProverClient::from_env();
@@ -113,11 +465,16 @@ fn test_deposit() {
     println!("   Execution: {} cycles", report.total_instruction_count());
 
     // Read output
-    let result: DepositOutput = output.read();
-    println!("   ✅ Valid: {}", result.is_valid);
+    let result: DepositOutput = read_output(&mut output, OP_DEPOSIT).expect("proof of another operation");
+    println!("   ✅ Result: {}", result.error_code);
     println!("   📝 Commitment: {:?}...", &result.commitment_hash[..8]);
 
-    assert_eq!(result.is_valid, 1, "Deposit should be valid");
+    assert_eq!(result.error_code, ErrorCode::Success, "Deposit should be valid");
+    assert_eq!(
+        result.collateral,
+        single_asset(COLLATERAL_TOKEN, collateral_amount),
+        "Committed collateral should be public"
+    );
 
     // Generate proof
     let (pk, vk) = client.setup(ELF);
@@ -151,20 +508,31 @@ fn test_safe_borrow() {
     println!("   LTV: 20% (computed in ZK)");
     println!("   Max LTV: 75%");
 
+    let old_commitment = crypto::hash_commitment(&secret_key, &single_asset(COLLATERAL_TOKEN, collateral_amount), 0, math::INDEX_SCALE, &old_salt);
+    let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
     let borrow_input = BorrowInput {
         user_secret_key: secret_key,
-        collateral_amount,
-        collateral_price_usd: collateral_price,
+        collateral: single_asset(COLLATERAL_TOKEN, collateral_amount),
+        prices: signed_prices(collateral_price),
         existing_debt: 0,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
         new_borrow_amount: borrow_amount,
-        max_ltv_bps: 7500, // 75%
+        ltv_weights_bps: [7500, 0], // 75%
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
         old_note_salt: old_salt,
         new_note_salt: new_salt,
         recipient_address: recipient,
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&1u8); // Operation type: 1 = borrow
+    stdin.write(&OP_BORROW);
     stdin.write(&borrow_input);
 
     let client = ProverClient::from_env();
@@ -174,13 +542,27 @@ fn test_safe_borrow() {
     println!("   Execution: {} cycles", report.total_instruction_count());
 
     // Read output
-    let result: BorrowOutput = output.read();
-    println!("   ✅ Valid: {}", result.is_valid);
+    let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
+    println!("   ✅ Result: {}", result.error_code);
     println!("   🔒 Nullifier: {:?}...", &result.nullifier_hash[..8]);
     println!("   📝 New Commitment: {:?}...", &result.new_commitment_hash[..8]);
     println!("   💸 Borrow Amount: {} USDC", result.borrow_amount / 1_000_000);
-
-    assert_eq!(result.is_valid, 1, "Borrow should be valid (safe LTV)");
+    println!("   🔮 Oracle Key Hash: {:?}...", &result.oracle_key_hash[..8]);
+    println!("   ⏱️  Price Timestamp: {}", result.price_timestamp);
+
+    assert_eq!(result.error_code, ErrorCode::Success, "Borrow should be valid (safe LTV)");
+    assert_eq!(result.oracle_key_hash, LocalOracle::from_env().key_hash(), "Oracle key hash should be public");
+    assert_eq!(
+        result.collateral_assets,
+        [COLLATERAL_TOKEN, [0u8; 20]],
+        "Collateral markets should be public"
+    );
+    assert_eq!(result.ltv_weights_bps, [7500, 0], "LTV weights should be public");
+    assert_eq!(
+        (result.collateral_decimals, result.debt_decimals),
+        ([COLLATERAL_DECIMALS, 0], DEBT_DECIMALS),
+        "Market decimals should be public"
+    );
 
     // Generate and verify proof
     let (pk, vk) = client.setup(ELF);
@@ -210,20 +592,31 @@ fn test_unsafe_borrow() {
     println!("   LTV: 80% (computed in ZK)");
     println!("   Max LTV: 75%");
 
+    let old_commitment = crypto::hash_commitment(&secret_key, &single_asset(COLLATERAL_TOKEN, collateral_amount), 0, math::INDEX_SCALE, &old_salt);
+    let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
     let borrow_input = BorrowInput {
         user_secret_key: secret_key,
-        collateral_amount,
-        collateral_price_usd: collateral_price,
+        collateral: single_asset(COLLATERAL_TOKEN, collateral_amount),
+        prices: signed_prices(collateral_price),
         existing_debt: 0,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
         new_borrow_amount: borrow_amount,
-        max_ltv_bps: 7500, // 75%
+        ltv_weights_bps: [7500, 0], // 75%
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
         old_note_salt: old_salt,
         new_note_salt: new_salt,
         recipient_address: recipient,
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&1u8); // Operation type: 1 = borrow
+    stdin.write(&OP_BORROW);
     stdin.write(&borrow_input);
 
     let client = ProverClient::from_env();
@@ -233,11 +626,11 @@ fn test_unsafe_borrow() {
     println!("   Execution: {} cycles", report.total_instruction_count());
 
     // Read output
-    let result: BorrowOutput = output.read();
-    println!("   ❌ Valid: {} (expected 0)", result.is_valid);
+    let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
+    println!("   ❌ Rejected: {}", result.error_code);
     println!("   Result: Borrow rejected due to unsafe LTV ratio");
 
-    assert_eq!(result.is_valid, 0, "Borrow should be invalid (unsafe LTV)");
+    assert_eq!(result.error_code, ErrorCode::LtvExceeded, "Borrow should be invalid (unsafe LTV)");
     println!("   ✅ Test passed - Unsafe borrow correctly rejected!");
 }
 
@@ -252,91 +645,703 @@ fn run_fast_validation() {
     let client = ProverClient::from_env();
 
     // Test 1: Deposit
-    println!("[1/3] Validating DEPOSIT operation");
+    println!("[1/10] Validating DEPOSIT operation");
     println!("-----------------------------------");
     let start = Instant::now();
     
     let deposit_input = DepositInput {
         user_secret_key: [1u8; 32],
-        collateral_amount: 10_000_000_000_000_000_000u128,
+        collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
         note_salt: [42u8; 32],
+        depositor_address: [0u8; 20],
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&0u8);
+    stdin.write(&OP_DEPOSIT);
     stdin.write(&deposit_input);
 
     let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
-    let result: DepositOutput = output.read();
+    let result: DepositOutput = read_output(&mut output, OP_DEPOSIT).expect("proof of another operation");
     
     println!("  ✅ Execution: {:?}", start.elapsed());
     println!("  📊 Cycles: {}", report.total_instruction_count());
-    println!("  ✅ Valid: {}", result.is_valid);
-    println!("  📝 Commitment: {:?}...\n", &result.commitment_hash[..8]);
+    println!("  ✅ Result: {}", result.error_code);
+    println!("  📝 Commitment: {:?}...", &result.commitment_hash[..8]);
     
-    assert_eq!(result.is_valid, 1);
+    assert_eq!(result.error_code, ErrorCode::Success);
+
+    // Screened deposits prove the depositor is not on the sanctions blocklist
+    let blocklist = Blocklist::new(&[[0xBAu8; 20], [0x5Au8; 20]]).expect("blocklist fits the tree");
+    let mut screened_input = DepositInput {
+        depositor_address: [0x12u8; 20],
+        blocklist: blocklist.prove_exclusion(&[0x12u8; 20]),
+        ..deposit_input
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_DEPOSIT);
+    stdin.write(&screened_input);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: DepositOutput = read_output(&mut output, OP_DEPOSIT).expect("proof of another operation");
+
+    println!("  ✅ Screened depositor: {}", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::Success);
+    assert_eq!(result.blocklist_root, blocklist.root());
+
+    // A blocked depositor can't reuse someone else's exclusion proof
+    screened_input.depositor_address = [0xBAu8; 20];
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_DEPOSIT);
+    stdin.write(&screened_input);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: DepositOutput = read_output(&mut output, OP_DEPOSIT).expect("proof of another operation");
+
+    println!("  ❌ Blocked depositor rejected: {}\n", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::Blocklisted);
 
     // Test 2: Safe Borrow
-    println!("[2/3] Validating SAFE BORROW (LTV 20%)");
+    println!("[2/10] Validating SAFE BORROW (LTV 20%)");
     println!("-----------------------------------");
     let start = Instant::now();
     
-    let borrow_input = BorrowInput {
+    let old_commitment = crypto::hash_commitment(&[1u8; 32], &single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128), 0, math::INDEX_SCALE, &[42u8; 32]);
+    let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
+    let mut borrow_input = BorrowInput {
         user_secret_key: [1u8; 32],
-        collateral_amount: 10_000_000_000_000_000_000u128,
-        collateral_price_usd: 2500_000_000u128,
+        collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
+        prices: signed_prices(2500_000_000u128),
         existing_debt: 0,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
         new_borrow_amount: 5000_000_000u128,
-        max_ltv_bps: 7500,
+        ltv_weights_bps: [7500, 0],
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
         old_note_salt: [42u8; 32],
         new_note_salt: [43u8; 32],
         recipient_address: [0x12u8; 20],
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&1u8);
+    stdin.write(&OP_BORROW);
     stdin.write(&borrow_input);
 
     let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
-    let result: BorrowOutput = output.read();
+    let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
     
     println!("  ✅ Execution: {:?}", start.elapsed());
     println!("  📊 Cycles: {}", report.total_instruction_count());
-    println!("  ✅ Valid: {}", result.is_valid);
-    println!("  💸 Borrow: {} USDC\n", result.borrow_amount / 1_000_000);
+    println!("  ✅ Result: {}", result.error_code);
+    println!("  💸 Borrow: {} USDC", result.borrow_amount / 1_000_000);
     
-    assert_eq!(result.is_valid, 1);
+    assert_eq!(result.error_code, ErrorCode::Success);
+
+    // Inflating the signed price must invalidate the signature
+    borrow_input.prices[0].price_usd *= 10;
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_BORROW);
+    stdin.write(&borrow_input);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
+
+    println!("  ❌ Forged price rejected: {}", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::InvalidPriceSignature);
+
+    // 1 ETH @ $2500 at 75% ($1875) plus 2000 WMNT @ $0.80 at 50% ($800) backs one loan
+    let mut collateral = single_asset(COLLATERAL_TOKEN, 1_000_000_000_000_000_000u128);
+    collateral[1] = CollateralPosition {
+        asset: SECOND_COLLATERAL_TOKEN,
+        amount: 2_000_000_000_000_000_000_000u128,
+    };
+    let old_commitment = crypto::hash_commitment(&[1u8; 32], &collateral, 0, math::INDEX_SCALE, &[42u8; 32]);
+    let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
+    let mut prices = signed_prices(2500_000_000u128);
+    prices[1] = LocalOracle::from_env().sign_price(800_000u128, SECOND_COLLATERAL_TOKEN);
+
+    let mut borrow_input = BorrowInput {
+        user_secret_key: [1u8; 32],
+        collateral,
+        prices,
+        existing_debt: 0,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
+        new_borrow_amount: 2600_000_000u128, // Neither asset alone covers this
+        ltv_weights_bps: [7500, 5000],
+        collateral_decimals: [COLLATERAL_DECIMALS, 18],
+        debt_decimals: DEBT_DECIMALS,
+        old_note_salt: [42u8; 32],
+        new_note_salt: [43u8; 32],
+        recipient_address: [0x12u8; 20],
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+        blocklist: None,
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_BORROW);
+    stdin.write(&borrow_input);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
+
+    println!("  ✅ mETH + WMNT: {}", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::Success);
+
+    // Without a WMNT market the same note can't support the loan
+    borrow_input.prices[1] = SignedPrice::default();
+    borrow_input.ltv_weights_bps[1] = 0;
+    borrow_input.collateral_decimals[1] = 0;
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_BORROW);
+    stdin.write(&borrow_input);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
+
+    println!("  ❌ Unlisted WMNT rejected: {}\n", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::UnlistedCollateral);
 
     // Test 3: Unsafe Borrow
-    println!("[3/3] Validating UNSAFE BORROW (LTV 80%) - Should Reject");
+    println!("[3/10] Validating UNSAFE BORROW (LTV 80%) - Should Reject");
     println!("-----------------------------------");
     let start = Instant::now();
     
+    let old_commitment = crypto::hash_commitment(&[1u8; 32], &single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128), 0, math::INDEX_SCALE, &[42u8; 32]);
+    let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
     let borrow_input = BorrowInput {
         user_secret_key: [1u8; 32],
-        collateral_amount: 10_000_000_000_000_000_000u128,
-        collateral_price_usd: 2500_000_000u128,
+        collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
+        prices: signed_prices(2500_000_000u128),
         existing_debt: 0,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
         new_borrow_amount: 20000_000_000u128, // 80% LTV
-        max_ltv_bps: 7500,
+        ltv_weights_bps: [7500, 0],
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
         old_note_salt: [42u8; 32],
         new_note_salt: [44u8; 32],
         recipient_address: [0x12u8; 20],
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&1u8);
+    stdin.write(&OP_BORROW);
     stdin.write(&borrow_input);
 
     let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
-    let result: BorrowOutput = output.read();
+    let result: BorrowOutput = read_output(&mut output, OP_BORROW).expect("proof of another operation");
     
     println!("  ✅ Execution: {:?}", start.elapsed());
     println!("  📊 Cycles: {}", report.total_instruction_count());
-    println!("  ❌ Valid: {} (expected 0)", result.is_valid);
+    println!("  ❌ Rejected: {}", result.error_code);
     println!("  ✅ Correctly rejected unsafe LTV\n");
     
-    assert_eq!(result.is_valid, 0);
+    assert_eq!(result.error_code, ErrorCode::LtvExceeded);
+
+    // Test 4: Repay
+    println!("[4/10] Validating REPAY (2,000 of 5,000 USDC debt)");
+    println!("-----------------------------------");
+    let start = Instant::now();
+
+    // The note left behind by the safe borrow: 10 ETH collateral, 5,000 USDC debt
+    let old_commitment = crypto::hash_commitment(&[1u8; 32], &single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128), 5_000_000_000u128, math::INDEX_SCALE, &[43u8; 32]);
+    let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
+    let mut repay_input = RepayInput {
+        user_secret_key: [1u8; 32],
+        collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
+        existing_debt: 5_000_000_000u128,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
+        repay_amount: 2_000_000_000u128,
+        old_note_salt: [43u8; 32],
+        new_note_salt: [45u8; 32],
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_REPAY);
+    stdin.write(&repay_input);
+
+    let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: RepayOutput = read_output(&mut output, OP_REPAY).expect("proof of another operation");
+
+    println!("  ✅ Execution: {:?}", start.elapsed());
+    println!("  📊 Cycles: {}", report.total_instruction_count());
+    println!("  ✅ Result: {}", result.error_code);
+    println!("  💸 Repaid: {} USDC", result.repay_amount / 1_000_000);
+
+    assert_eq!(result.error_code, ErrorCode::Success);
+
+    // After 10% interest the same note owes 5,500 USDC: it can repay that much but no more
+    repay_input.borrow_index = math::INDEX_SCALE + math::INDEX_SCALE / 10;
+    for (repay_amount, expected) in [(5_500_000_000u128, ErrorCode::Success), (5_500_000_001u128, ErrorCode::InsufficientBalance)] {
+        repay_input.repay_amount = repay_amount;
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&OP_REPAY);
+        stdin.write(&repay_input);
+
+        let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+        let result: RepayOutput =
+            read_output(&mut output, OP_REPAY).expect("proof of another operation");
+
+        assert_eq!(result.error_code, expected);
+        assert_eq!(result.borrow_index, repay_input.borrow_index);
+    }
+    println!("  📈 Interest: 5,500 USDC owed at index 1.10\n");
+
+    // Test 5: Withdraw
+    println!("[5/10] Validating WITHDRAW (2 ETH, 3,000 USDC debt remains)");
+    println!("-----------------------------------");
+    let start = Instant::now();
+
+    // The note left behind by the repay: 10 ETH collateral, 3,000 USDC debt
+    let old_commitment = crypto::hash_commitment(&[1u8; 32], &single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128), 3_000_000_000u128, math::INDEX_SCALE, &[45u8; 32]);
+    let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
+    let mut withdraw_input = WithdrawInput {
+        user_secret_key: [1u8; 32],
+        collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
+        existing_debt: 3_000_000_000u128,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
+        withdraw_asset: COLLATERAL_TOKEN,
+        withdraw_amount: 2_000_000_000_000_000_000u128,
+        prices: signed_prices(2_500_000_000u128),
+        ltv_weights_bps: [7500, 0],
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
+        old_note_salt: [45u8; 32],
+        new_note_salt: [46u8; 32],
+        recipient_address: [0x12u8; 20],
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_WITHDRAW);
+    stdin.write(&withdraw_input);
+
+    let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: WithdrawOutput = read_output(&mut output, OP_WITHDRAW).expect("proof of another operation");
+
+    println!("  ✅ Execution: {:?}", start.elapsed());
+    println!("  📊 Cycles: {}", report.total_instruction_count());
+    println!("  ✅ Result: {}", result.error_code);
+    println!("  💸 Withdrawn: {} ETH", result.withdraw_amount / 1_000_000_000_000_000_000);
+
+    assert_eq!(result.error_code, ErrorCode::Success);
+    assert_eq!(result.oracle_key_hash, LocalOracle::from_env().key_hash());
+
+    // An inflated price the oracle never signed can't cover a larger withdrawal
+    withdraw_input.withdraw_amount = 9_000_000_000_000_000_000u128;
+    withdraw_input.prices[0].price_usd *= 100;
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_WITHDRAW);
+    stdin.write(&withdraw_input);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: WithdrawOutput = read_output(&mut output, OP_WITHDRAW).expect("proof of another operation");
+
+    println!("  ❌ Unsigned price rejected: {}\n", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::InvalidPriceSignature);
+
+    // Test 6: Liquidation
+    println!("[6/10] Validating LIQUIDATION (ETH drops to $2000, LTV 90% > 80%)");
+    println!("-----------------------------------");
+    let start = Instant::now();
+
+    // An unhealthy note: 10 ETH collateral, 18,000 USDC debt
+    let note_commitment = crypto::hash_commitment(&[2u8; 32], &single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128), 18_000_000_000u128, math::INDEX_SCALE, &[47u8; 32]);
+    let (merkle_root, merkle_path) = single_note_tree(&note_commitment);
+
+    let liquidation_input = LiquidationInput {
+        user_secret_key: [2u8; 32],
+        collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
+        debt_amount: 18_000_000_000u128,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
+        note_salt: [47u8; 32],
+        prices: signed_prices(2_000_000_000u128),
+        liquidation_thresholds_bps: [8000, 0],
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
+        recipient_address: [0x34u8; 20],
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_LIQUIDATE);
+    stdin.write(&liquidation_input);
+
+    let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: LiquidationOutput =
+        read_output(&mut output, OP_LIQUIDATE).expect("proof of another operation");
+
+    println!("  ✅ Execution: {:?}", start.elapsed());
+    println!("  📊 Cycles: {}", report.total_instruction_count());
+    println!("  ✅ Result: {}", result.error_code);
+    println!("  🔨 Seized: {} ETH", result.seized_collateral[0].amount / 1_000_000_000_000_000_000);
+    println!("  💸 Debt to repay: {} USDC\n", result.debt_to_repay / 1_000_000);
+
+    assert_eq!(result.error_code, ErrorCode::Success);
+    assert_eq!(result.oracle_key_hash, LocalOracle::from_env().key_hash());
+
+    // Test 7: Join-split
+    println!("[7/10] Validating JOIN-SPLIT (merge 4 + 6 ETH, split off 3 ETH)");
+    println!("-----------------------------------");
+    let start = Instant::now();
+
+    // Two dust deposits: 4 ETH debt-free and 6 ETH with 3,000 USDC debt
+    let dust = [
+        (single_asset(COLLATERAL_TOKEN, 4_000_000_000_000_000_000u128), 0u128, [48u8; 32]),
+        (single_asset(COLLATERAL_TOKEN, 6_000_000_000_000_000_000u128), 3_000_000_000u128, [49u8; 32]),
+    ];
+    let mut tree = merkle::IncrementalMerkleTree::new();
+    for (collateral, debt, salt) in &dust {
+        let commitment = crypto::hash_commitment(&[1u8; 32], collateral, *debt, math::INDEX_SCALE, salt);
+        tree.insert(commitment).expect("tree is full");
+    }
+    let spent_notes = [0u64, 1].map(|leaf_index| {
+        let (collateral, debt_amount, salt) = dust[leaf_index as usize];
+        SpentNote {
+            collateral,
+            debt_amount,
+            note_borrow_index: math::INDEX_SCALE,
+            salt,
+            merkle_path: tree.path(leaf_index).expect("leaf was inserted"),
+            leaf_index,
+        }
+    });
+
+    // Merge into 7 ETH carrying all the debt, and split off 3 ETH debt-free
+    let mut join_split_input = JoinSplitInput {
+        user_secret_key: [1u8; 32],
+        spent_count: 2,
+        spent_notes,
+        created_count: 2,
+        created_notes: [
+            CreatedNote {
+                collateral: single_asset(COLLATERAL_TOKEN, 7_000_000_000_000_000_000u128),
+                debt_amount: 3_000_000_000u128,
+                salt: [50u8; 32],
+            },
+            CreatedNote {
+                collateral: single_asset(COLLATERAL_TOKEN, 3_000_000_000_000_000_000u128),
+                debt_amount: 0,
+                salt: [51u8; 32],
+            },
+        ],
+        borrow_index: math::INDEX_SCALE,
+        prices: signed_prices(2_500_000_000u128),
+        ltv_weights_bps: [7500, 0],
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
+        merkle_root: tree.root(),
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_JOIN_SPLIT);
+    stdin.write(&join_split_input);
+
+    let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: JoinSplitOutput =
+        read_output(&mut output, OP_JOIN_SPLIT).expect("proof of another operation");
+
+    println!("  ✅ Execution: {:?}", start.elapsed());
+    println!("  📊 Cycles: {}", report.total_instruction_count());
+    println!("  ✅ Result: {}", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::Success);
+    assert_ne!(result.nullifier_hashes[0], result.nullifier_hashes[1]);
+    assert_eq!(result.oracle_key_hash, LocalOracle::from_env().key_hash());
+
+    // Creating collateral out of thin air is rejected
+    join_split_input.created_notes[1].collateral[0].amount += 1;
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_JOIN_SPLIT);
+    stdin.write(&join_split_input);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: JoinSplitOutput =
+        read_output(&mut output, OP_JOIN_SPLIT).expect("proof of another operation");
+
+    println!("  ❌ Inflated collateral rejected: {}", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::NotConserved);
+
+    // So is dropping debt, or parking it on a note that can't cover it
+    join_split_input.created_notes[1].collateral[0].amount -= 1;
+    for (debts, label, expected) in [
+        ([2_999_999_999u128, 0], "Dropped debt", ErrorCode::NotConserved),
+        ([0, 3_000_000_000u128], "Undercollateralised note", ErrorCode::LtvExceeded),
+    ] {
+        join_split_input.created_notes[0].debt_amount = debts[0];
+        join_split_input.created_notes[1].debt_amount = debts[1];
+        join_split_input.created_notes[1].collateral[0].amount = 1_000_000_000_000_000u128;
+        join_split_input.created_notes[0].collateral[0].amount = 9_999_000_000_000_000_000u128;
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&OP_JOIN_SPLIT);
+        stdin.write(&join_split_input);
+
+        let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+        let result: JoinSplitOutput =
+            read_output(&mut output, OP_JOIN_SPLIT).expect("proof of another operation");
+
+        println!("  ❌ {} rejected: {}", label, result.error_code);
+
+        assert_eq!(result.error_code, expected);
+    }
+
+    // Spending the same note twice would double its collateral
+    join_split_input.spent_notes[1] = join_split_input.spent_notes[0].clone();
+    join_split_input.created_count = 1;
+    join_split_input.created_notes[0] = CreatedNote {
+        collateral: single_asset(COLLATERAL_TOKEN, 8_000_000_000_000_000_000u128),
+        debt_amount: 0,
+        salt: [50u8; 32],
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_JOIN_SPLIT);
+    stdin.write(&join_split_input);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let result: JoinSplitOutput =
+        read_output(&mut output, OP_JOIN_SPLIT).expect("proof of another operation");
+
+    println!("  ❌ Double spend rejected: {}\n", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::DuplicateNote);
+
+    // Test 8: Disclosure
+    println!("[8/10] Validating DISCLOSURE (10 ETH note with 5,000 USDC debt)");
+    println!("-----------------------------------");
+    let start = Instant::now();
+
+    let note_commitment = crypto::hash_commitment(&[1u8; 32], &single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128), 5_000_000_000u128, math::INDEX_SCALE, &[52u8; 32]);
+    let (merkle_root, merkle_path) = single_note_tree(&note_commitment);
+    let disclose = |statement: DisclosureStatement| {
+        let disclosure_input = DisclosureInput {
+            user_secret_key: [1u8; 32],
+            collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
+            debt_amount: 5_000_000_000u128,
+            note_borrow_index: math::INDEX_SCALE,
+            note_salt: [52u8; 32],
+            statement,
+            challenge: [0xA0u8; 32],
+            merkle_root,
+            merkle_path,
+            leaf_index: 0,
+        };
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&OP_DISCLOSURE);
+        stdin.write(&disclosure_input);
+
+        let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
+        let result: DisclosureOutput =
+            read_output(&mut output, OP_DISCLOSURE).expect("proof of another operation");
+        (result, report.total_instruction_count())
+    };
+
+    // Collateral >= 5 ETH and LTV <= 25% at $2500 hold (the note is at 20%)
+    let (result, cycles) = disclose(DisclosureStatement::MinCollateral {
+        asset: COLLATERAL_TOKEN,
+        min_amount: 5_000_000_000_000_000_000u128,
+    });
+    let max_ltv = |max_ltv_bps| DisclosureStatement::MaxLtv {
+        markets: local_markets(2_500_000_000u128, 0),
+        max_ltv_bps,
+        debt_decimals: DEBT_DECIMALS,
+        borrow_index: math::INDEX_SCALE,
+    };
+    let (ltv_result, _) = disclose(max_ltv(2500));
+
+    println!("  ✅ Execution: {:?}", start.elapsed());
+    println!("  📊 Cycles: {}", cycles);
+    println!("  ✅ Collateral >= 5 ETH: {}", result.error_code);
+    println!("  ✅ LTV <= 25%: {}", ltv_result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::Success);
+    assert_eq!(ltv_result.error_code, ErrorCode::Success);
+    assert_eq!(result.owner_key, crypto::derive_owner_key(&[1u8; 32]));
+
+    // False statements are rejected
+    let (result, _) = disclose(DisclosureStatement::MinCollateral {
+        asset: COLLATERAL_TOKEN,
+        min_amount: 11_000_000_000_000_000_000u128,
+    });
+    let (ltv_result, _) = disclose(max_ltv(1500));
+
+    println!("  ❌ Collateral >= 11 ETH rejected: {}", result.error_code);
+    println!("  ❌ LTV <= 15% rejected: {}\n", ltv_result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::StatementFalse);
+    assert_eq!(ltv_result.error_code, ErrorCode::StatementFalse);
+
+    // Test 9: Migration
+    println!("[9/10] Validating MIGRATION (version 0 note with 5,000 USDC debt)");
+    println!("-----------------------------------");
+    let start = Instant::now();
+
+    let collateral = single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128);
+    let legacy_commitment = crypto::hash_commitment_v0(&[1u8; 32], &collateral, 5_000_000_000u128, math::INDEX_SCALE, &[53u8; 32]);
+    let migrate = |commitment: &[u8; 32]| {
+        let (merkle_root, merkle_path) = single_note_tree(commitment);
+        let migration_input = MigrationInput {
+            user_secret_key: [1u8; 32],
+            note: LegacyNote::Position {
+                collateral,
+                debt_amount: 5_000_000_000u128,
+                borrow_index: math::INDEX_SCALE,
+            },
+            note_salt: [53u8; 32],
+            new_note_salt: [54u8; 32],
+            merkle_root,
+            merkle_path,
+            leaf_index: 0,
+        };
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&OP_MIGRATION);
+        stdin.write(&migration_input);
+
+        let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
+        let result: MigrationOutput =
+            read_output(&mut output, OP_MIGRATION).expect("proof of another operation");
+        (result, report.total_instruction_count())
+    };
+
+    let (result, cycles) = migrate(&legacy_commitment);
+
+    println!("  ✅ Execution: {:?}", start.elapsed());
+    println!("  📊 Cycles: {}", cycles);
+    println!("  ✅ Result: {}", result.error_code);
+
+    let nullifier_key = crypto::derive_nullifier_key(&[1u8; 32]);
+    assert_eq!(result.error_code, ErrorCode::Success);
+    assert_eq!(result.nullifier_hash, crypto::hash_nullifier_v0(&nullifier_key, &legacy_commitment, 0));
+    assert_eq!(
+        result.new_commitment_hash,
+        crypto::hash_commitment(&[1u8; 32], &collateral, 5_000_000_000u128, math::INDEX_SCALE, &[54u8; 32])
+    );
+
+    // A note already committed with the current layout is not a legacy note
+    let current_commitment = crypto::hash_commitment(&[1u8; 32], &collateral, 5_000_000_000u128, math::INDEX_SCALE, &[53u8; 32]);
+    let (result, _) = migrate(&current_commitment);
+
+    println!("  ❌ Current-version note rejected: {}\n", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::MembershipFailed);
+
+    // Test 10: Batch Deposit
+    println!("[10/10] Validating BATCH DEPOSIT (1, 2 and 3 ETH from one funder)");
+    println!("-----------------------------------");
+    let start = Instant::now();
+
+    let batch_deposit = |deposits: Vec<DepositInput>| {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&OP_BATCH_DEPOSIT);
+        stdin.write(&BatchDepositInput { deposits });
+
+        let (mut output, report) = client.execute(ELF, &stdin).run().expect("execution failed");
+        let result: BatchDepositOutput =
+            read_output(&mut output, OP_BATCH_DEPOSIT).expect("proof of another operation");
+        (result, report.total_instruction_count())
+    };
+    let deposits = |funders: &[[u8; 20]]| -> Vec<DepositInput> {
+        funders
+            .iter()
+            .enumerate()
+            .map(|(i, funder)| DepositInput {
+                user_secret_key: [60 + i as u8; 32],
+                collateral: single_asset(COLLATERAL_TOKEN, (i as u128 + 1) * 1_000_000_000_000_000_000u128),
+                note_salt: [70 + i as u8; 32],
+                depositor_address: *funder,
+                blocklist: None,
+            })
+            .collect()
+    };
+
+    let (result, cycles) = batch_deposit(deposits(&[[0x12u8; 20]; 3]));
+
+    println!("  ✅ Execution: {:?}", start.elapsed());
+    println!("  📊 Cycles: {}", cycles);
+    println!("  ✅ Result: {}", result.error_code);
+    println!("  📝 Notes created: {}", result.deposit_count);
+
+    assert_eq!(result.error_code, ErrorCode::Success);
+    assert_eq!(result.deposit_count, 3);
+    assert_eq!(result.total_collateral, single_asset(COLLATERAL_TOKEN, 6_000_000_000_000_000_000u128));
+    for (i, commitment_hash) in result.commitment_hashes.iter().enumerate() {
+        let expected = if i < 3 {
+            let amount = (i as u128 + 1) * 1_000_000_000_000_000_000u128;
+            crypto::hash_commitment(&[60 + i as u8; 32], &single_asset(COLLATERAL_TOKEN, amount), 0, math::INDEX_SCALE, &[70 + i as u8; 32])
+        } else {
+            [0u8; 32]
+        };
+        assert_eq!(*commitment_hash, expected);
+    }
+
+    // The vault pulls the total from one address, so every deposit must name it
+    let (result, _) = batch_deposit(deposits(&[[0x12u8; 20], [0x34u8; 20]]));
+
+    println!("  ❌ Mixed funders rejected: {}", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::BatchMismatch);
+    assert_eq!(result.deposit_count, 0);
+
+    let (result, _) = batch_deposit(Vec::new());
+
+    println!("  ❌ Empty batch rejected: {}\n", result.error_code);
+
+    assert_eq!(result.error_code, ErrorCode::InvalidBatchSize);
+
+    // Unknown operations commit only the reason
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&0xFFu8);
+
+    let (mut output, _) = client.execute(ELF, &stdin).run().expect("execution failed");
+    let error_code: ErrorCode = read_output(&mut output, 0xFF).expect("proof of another operation");
+
+    println!("❌ Unknown operation rejected: {}\n", error_code);
+
+    assert_eq!(error_code, ErrorCode::UnknownOperation);
 
     println!("========================================");
     println!("✅ All validations passed!");
@@ -370,12 +1375,14 @@ fn test_groth16_proofs() {
 
     let deposit_input = DepositInput {
         user_secret_key: secret_key,
-        collateral_amount,
+        collateral: single_asset(COLLATERAL_TOKEN, collateral_amount),
         note_salt: salt,
+        depositor_address: [0u8; 20],
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&0u8);
+    stdin.write(&OP_DEPOSIT);
     stdin.write(&deposit_input);
 
     // Generate Groth16 proof
@@ -402,20 +1409,31 @@ fn test_groth16_proofs() {
     println!("-------------------------------------------");
     let borrow_start = Instant::now();
     
+    let old_commitment = crypto::hash_commitment(&secret_key, &single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128), 0, math::INDEX_SCALE, &[42u8; 32]);
+    let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
     let borrow_input = BorrowInput {
         user_secret_key: secret_key,
-        collateral_amount: 10_000_000_000_000_000_000u128,
-        collateral_price_usd: 2500_000_000u128,
+        collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
+        prices: signed_prices(2500_000_000u128),
         existing_debt: 0,
+        note_borrow_index: math::INDEX_SCALE,
+        borrow_index: math::INDEX_SCALE,
         new_borrow_amount: 5000_000_000u128,
-        max_ltv_bps: 7500,
+        ltv_weights_bps: [7500, 0],
+        collateral_decimals: [COLLATERAL_DECIMALS, 0],
+        debt_decimals: DEBT_DECIMALS,
         old_note_salt: [42u8; 32],
         new_note_salt: [43u8; 32],
         recipient_address: [0x12u8; 20],
+        merkle_root,
+        merkle_path,
+        leaf_index: 0,
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&1u8);
+    stdin.write(&OP_BORROW);
     stdin.write(&borrow_input);
 
     println!("🔨 Generating proof...");
@@ -450,6 +1468,11 @@ fn run_benchmarks() {
     let client = ProverClient::from_env();
     let (pk, _vk) = client.setup(ELF);
 
+    println!("Hash backend: {}\n", hashing::HASH_BACKEND);
+
+    // Cycles per scenario, compared against the previous run and the other backend's last run
+    let mut cycles = Vec::new();
+
     // Test various collateral amounts
     let test_amounts = vec![
         (1_000_000_000_000_000_000u128, "1 ETH"),
@@ -469,12 +1492,14 @@ fn run_benchmarks() {
         
         let deposit_input = DepositInput {
             user_secret_key: [1u8; 32],
-            collateral_amount: *amount,
+            collateral: single_asset(COLLATERAL_TOKEN, *amount),
             note_salt: [42u8; 32],
+            depositor_address: [0u8; 20],
+            blocklist: None,
         };
 
         let mut stdin = SP1Stdin::new();
-        stdin.write(&0u8);
+        stdin.write(&OP_DEPOSIT);
         stdin.write(&deposit_input);
 
         // Execute only (no proof) for cycle count
@@ -482,6 +1507,7 @@ fn run_benchmarks() {
         let exec_time = start.elapsed();
         
         deposit_times.push(exec_time);
+        cycles.push((format!("Deposit {}", label), report.total_instruction_count()));
         
         println!("  {} - {} cycles ({:?})", 
             label, 
@@ -509,26 +1535,39 @@ fn run_benchmarks() {
     for (eth_amount, price, borrow_usd, label) in &borrow_scenarios {
         let start = Instant::now();
         
+        let collateral_amount = eth_amount * 1_000_000_000_000_000_000u128;
+        let old_commitment = crypto::hash_commitment(&[1u8; 32], &single_asset(COLLATERAL_TOKEN, collateral_amount), 0, math::INDEX_SCALE, &[42u8; 32]);
+        let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
+
         let borrow_input = BorrowInput {
             user_secret_key: [1u8; 32],
-            collateral_amount: eth_amount * 1_000_000_000_000_000_000u128,
-            collateral_price_usd: price * 1_000_000u128,
+            collateral: single_asset(COLLATERAL_TOKEN, collateral_amount),
+            prices: signed_prices(price * 1_000_000u128),
             existing_debt: 0,
+            note_borrow_index: math::INDEX_SCALE,
+            borrow_index: math::INDEX_SCALE,
             new_borrow_amount: borrow_usd * 1_000_000u128,
-            max_ltv_bps: 7500,
+            ltv_weights_bps: [7500, 0],
+            collateral_decimals: [COLLATERAL_DECIMALS, 0],
+            debt_decimals: DEBT_DECIMALS,
             old_note_salt: [42u8; 32],
             new_note_salt: [43u8; 32],
             recipient_address: [0x12u8; 20],
+            merkle_root,
+            merkle_path,
+            leaf_index: 0,
+            blocklist: None,
         };
 
         let mut stdin = SP1Stdin::new();
-        stdin.write(&1u8);
+        stdin.write(&OP_BORROW);
         stdin.write(&borrow_input);
 
         let (_, report) = client.execute(ELF, &stdin).run().expect("execution failed");
         let exec_time = start.elapsed();
         
         borrow_times.push(exec_time);
+        cycles.push((format!("Borrow {}", label), report.total_instruction_count()));
         
         println!("  {} - {} cycles ({:?})", 
             label,
@@ -540,18 +1579,77 @@ fn run_benchmarks() {
     let avg_borrow = borrow_times.iter().sum::<std::time::Duration>() / borrow_times.len() as u32;
     println!("\n  Average: {:?}\n", avg_borrow);
 
+    // Test various transfer amounts out of a 1,000 USDC balance note
+    let transfer_scenarios = vec![
+        (100_000_000u128, "Partial (10%)"),
+        (1_000_000_000u128, "Full balance"),
+    ];
+
+    println!("📊 TRANSFER Operation Benchmarks");
+    println!("-----------------------------------");
+
+    let mut transfer_times = Vec::new();
+
+    for (amount, label) in &transfer_scenarios {
+        let start = Instant::now();
+
+        let balance = 1_000_000_000u128;
+        let owner_key = crypto::derive_stealth_owner_key(&crypto::derive_owner_key(&[1u8; 32]), &[6u8; 32]);
+        let commitment = crypto::hash_balance_commitment(&owner_key, &COLLATERAL_TOKEN, balance, &[42u8; 32]);
+        let (merkle_root, merkle_path) = single_note_tree(&commitment);
+
+        let transfer_input = TransferInput {
+            sender_secret_key: [1u8; 32],
+            token_address: COLLATERAL_TOKEN,
+            balance,
+            note_salt: [42u8; 32],
+            note_shared_secret: [6u8; 32],
+            transfer_amount: *amount,
+            recipient_owner_key: crypto::derive_stealth_owner_key(&crypto::derive_owner_key(&[2u8; 32]), &[7u8; 32]),
+            recipient_note_salt: [43u8; 32],
+            change_shared_secret: [8u8; 32],
+            change_note_salt: [44u8; 32],
+            merkle_root,
+            merkle_path,
+            leaf_index: 0,
+        };
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&OP_TRANSFER);
+        stdin.write(&transfer_input);
+
+        let (_, report) = client.execute(ELF, &stdin).run().expect("execution failed");
+        let exec_time = start.elapsed();
+
+        transfer_times.push(exec_time);
+        cycles.push((format!("Transfer {}", label), report.total_instruction_count()));
+
+        println!("  {} - {} cycles ({:?})",
+            label,
+            report.total_instruction_count(),
+            exec_time
+        );
+    }
+
+    let avg_transfer = transfer_times.iter().sum::<std::time::Duration>() / transfer_times.len() as u32;
+    println!("\n  Average: {:?}\n", avg_transfer);
+
+    report_cycle_deltas(&cycles);
+
     // Groth16 proof generation benchmark
     println!("📊 Groth16 Proof Generation");
     println!("-----------------------------------");
     
     let deposit_input = DepositInput {
         user_secret_key: [1u8; 32],
-        collateral_amount: 10_000_000_000_000_000_000u128,
+        collateral: single_asset(COLLATERAL_TOKEN, 10_000_000_000_000_000_000u128),
         note_salt: [42u8; 32],
+        depositor_address: [0u8; 20],
+        blocklist: None,
     };
 
     let mut stdin = SP1Stdin::new();
-    stdin.write(&0u8);
+    stdin.write(&OP_DEPOSIT);
     stdin.write(&deposit_input);
 
     let start = Instant::now();
@@ -569,6 +1667,77 @@ fn run_benchmarks() {
     println!("========================================\n");
 }
 
+/// Hash backends the ZK program can be built with (`--features poseidon` or `keccak` selects them)
+const HASH_BACKENDS: [&str; 3] = ["sha256", "poseidon", "keccak256"];
+
+/// File holding the cycle counts of the last benchmark run with a hash backend
+fn benchmark_cycles_path(backend: &str) -> String {
+    format!("benchmark-cycles-{}.txt", backend)
+}
+
+/// Cycle counts saved by an earlier benchmark run, if there was one
+fn read_benchmark_cycles(path: &str) -> Option<Vec<(String, u64)>> {
+    let saved = std::fs::read_to_string(path).ok()?;
+    Some(
+        saved
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter_map(|(label, count)| Some((label.to_string(), count.parse().ok()?)))
+            .collect(),
+    )
+}
+
+/// Print each scenario's change in cycles from the `before` run
+fn print_cycle_deltas(cycles: &[(String, u64)], before: &[(String, u64)], before_name: &str) {
+    for (label, count) in cycles {
+        match before.iter().find(|(before_label, _)| before_label == label) {
+            Some((_, before_count)) => {
+                let delta = *count as i64 - *before_count as i64;
+                println!(
+                    "  {} - {} -> {} cycles ({:+}, {:+.1}%)",
+                    label,
+                    before_count,
+                    count,
+                    delta,
+                    delta as f64 * 100.0 / *before_count as f64
+                );
+            }
+            None => println!("  {} - {} cycles (not in the {} run)", label, count, before_name),
+        }
+    }
+    println!();
+}
+
+/// Save this run's cycle counts, then report the change since the last run with the same
+/// hash backend (before/after a change such as the SHA-256 precompile) and against the
+/// other backend's last run
+fn report_cycle_deltas(cycles: &[(String, u64)]) {
+    let path = benchmark_cycles_path(hashing::HASH_BACKEND);
+    let previous = read_benchmark_cycles(&path);
+
+    let saved: String = cycles.iter().map(|(label, count)| format!("{}\t{}\n", label, count)).collect();
+    if let Err(err) = std::fs::write(&path, saved) {
+        println!("  ⚠️  Could not save cycle counts to {}: {}\n", path, err);
+    }
+
+    println!("📊 Cycle Regression vs Previous {} Run", hashing::HASH_BACKEND);
+    println!("-----------------------------------");
+    match previous {
+        Some(previous) => print_cycle_deltas(cycles, &previous, "previous"),
+        None => println!("  No previous run (saved this one to {} as the baseline)\n", path),
+    }
+
+    for backend in HASH_BACKENDS.iter().filter(|backend| **backend != hashing::HASH_BACKEND) {
+        println!("📊 Cycle Delta vs {}", backend);
+        println!("-----------------------------------");
+
+        match read_benchmark_cycles(&benchmark_cycles_path(backend)) {
+            Some(other) => print_cycle_deltas(cycles, &other, backend),
+            None => println!("  No {} run yet (run `benchmark` with that backend to compare)\n", backend),
+        }
+    }
+}
+
 // ============================================
 // Stress Test - Multiple Users
 // ============================================
@@ -583,6 +1752,9 @@ fn run_stress_test() {
     let num_users = 10;
     let mut total_time = std::time::Duration::ZERO;
     let mut successful_proofs = 0;
+    // Same deposits again, funded by one address and proven together
+    let mut deposit_time = std::time::Duration::ZERO;
+    let mut batch_deposits = Vec::new();
 
     println!("🏦 Simulating {} concurrent users...\n", num_users);
 
@@ -602,12 +1774,14 @@ fn run_stress_test() {
         let start = Instant::now();
         let deposit_input = DepositInput {
             user_secret_key: secret_key,
-            collateral_amount: collateral,
+            collateral: single_asset(COLLATERAL_TOKEN, collateral),
             note_salt: secret_key, // Use secret as salt for uniqueness
+            depositor_address: [0u8; 20],
+            blocklist: None,
         };
 
         let mut stdin = SP1Stdin::new();
-        stdin.write(&0u8);
+        stdin.write(&OP_DEPOSIT);
         stdin.write(&deposit_input);
 
         match client.prove(&pk, &stdin).groth16().run() {
@@ -617,6 +1791,7 @@ fn run_stress_test() {
                         successful_proofs += 1;
                         let elapsed = start.elapsed();
                         total_time += elapsed;
+                        deposit_time += elapsed;
                         println!("  ✅ Deposit proof: {:?}", elapsed);
                     }
                     Err(e) => println!("  ❌ Verification failed: {}", e),
@@ -624,23 +1799,34 @@ fn run_stress_test() {
             }
             Err(e) => println!("  ❌ Proving failed: {}", e),
         }
+        batch_deposits.push(deposit_input);
 
         // Borrow
         let start = Instant::now();
+        let old_commitment = crypto::hash_commitment(&secret_key, &single_asset(COLLATERAL_TOKEN, collateral), 0, math::INDEX_SCALE, &secret_key);
+        let (merkle_root, merkle_path) = single_note_tree(&old_commitment);
         let borrow_input = BorrowInput {
             user_secret_key: secret_key,
-            collateral_amount: collateral,
-            collateral_price_usd: 2500_000_000u128,
+            collateral: single_asset(COLLATERAL_TOKEN, collateral),
+            prices: signed_prices(2500_000_000u128),
             existing_debt: 0,
+            note_borrow_index: math::INDEX_SCALE,
+            borrow_index: math::INDEX_SCALE,
             new_borrow_amount: borrow,
-            max_ltv_bps: 7500,
+            ltv_weights_bps: [7500, 0],
+            collateral_decimals: [COLLATERAL_DECIMALS, 0],
+            debt_decimals: DEBT_DECIMALS,
             old_note_salt: secret_key,
             new_note_salt: [secret_key[0], secret_key[1], 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
             recipient_address: [secret_key[0]; 20],
+            merkle_root,
+            merkle_path,
+            leaf_index: 0,
+            blocklist: None,
         };
 
         let mut stdin = SP1Stdin::new();
-        stdin.write(&1u8);
+        stdin.write(&OP_BORROW);
         stdin.write(&borrow_input);
 
         match client.prove(&pk, &stdin).groth16().run() {
@@ -661,6 +1847,32 @@ fn run_stress_test() {
         println!();
     }
 
+    // Batch deposit: every user's deposit in one proof
+    println!("[Batch] Proving {} deposits together...", batch_deposits.len());
+    let start = Instant::now();
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&OP_BATCH_DEPOSIT);
+    stdin.write(&BatchDepositInput { deposits: batch_deposits });
+
+    let batch_time = match client.prove(&pk, &stdin).groth16().run() {
+        Ok(proof) => match client.verify(&proof, &vk) {
+            Ok(_) => {
+                let elapsed = start.elapsed();
+                println!("  ✅ Batch deposit proof: {:?}\n", elapsed);
+                Some(elapsed)
+            }
+            Err(e) => {
+                println!("  ❌ Verification failed: {}\n", e);
+                None
+            }
+        },
+        Err(e) => {
+            println!("  ❌ Proving failed: {}\n", e);
+            None
+        }
+    };
+
     let avg_time = total_time / (successful_proofs as u32);
     
     println!("========================================");
@@ -672,5 +1884,9 @@ fn run_stress_test() {
     println!("  Total Time: {:?}", total_time);
     println!("  Average per Proof: {:?}", avg_time);
     println!("  Success Rate: {:.1}%", (successful_proofs as f64 / (num_users * 2) as f64) * 100.0);
+    println!("  Separate Deposit Proofs: {:?}", deposit_time);
+    if let Some(batch_time) = batch_time {
+        println!("  Batch Deposit Proof: {:?} ({:.1}x faster)", batch_time, deposit_time.as_secs_f64() / batch_time.as_secs_f64());
+    }
     println!("========================================\n");
 }
@@ -0,0 +1,305 @@
+// Note payloads encrypted to the owner's viewing key, published next to each commitment so
+// wallets can be restored from keys alone
+// ECDH is shared with the ZK program via `crypto`: the sender runs it with an ephemeral key,
+// the owner with their viewing key and the published ephemeral public key
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use k256::NonZeroScalar;
+use sha2::{Digest, Sha256};
+
+use super::collateral::{CollateralPosition, MAX_COLLATERAL_ASSETS};
+use super::crypto;
+
+/// Plaintext size: a kind byte and the larger (position) note body; balance notes are
+/// zero-padded so both kinds look the same on-chain
+pub const NOTE_PLAINTEXT_LEN: usize = 1 + MAX_COLLATERAL_ASSETS * 36 + 16 + 16 + 32;
+
+/// Size of an encrypted note: ephemeral public key, 12-byte nonce, ciphertext and the 16-byte AEAD tag
+pub const ENCRYPTED_NOTE_LEN: usize = 33 + 12 + NOTE_PLAINTEXT_LEN + 16;
+
+/// Everything besides the owner's keys needed to open and spend a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotePlaintext {
+    /// Lending position (deposit, borrow, ...) committed under the owner's spending key
+    Position {
+        collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+        debt_amount: u128,
+        borrow_index: u128,
+        salt: [u8; 32],
+    },
+    /// Token balance (transfer) committed under a one-time stealth owner key
+    Balance {
+        token: [u8; 20],
+        amount: u128,
+        salt: [u8; 32],
+    },
+}
+
+impl NotePlaintext {
+    /// The note's salt (unique per note)
+    pub fn salt(&self) -> &[u8; 32] {
+        match self {
+            NotePlaintext::Position { salt, .. } | NotePlaintext::Balance { salt, .. } => salt,
+        }
+    }
+
+    /// Fixed-size encoding: kind (0 = position, 1 = balance) followed by the fields, little-endian
+    pub fn to_bytes(self) -> [u8; NOTE_PLAINTEXT_LEN] {
+        let mut bytes = Vec::with_capacity(NOTE_PLAINTEXT_LEN);
+        match self {
+            NotePlaintext::Position { collateral, debt_amount, borrow_index, salt } => {
+                bytes.push(0);
+                for position in collateral {
+                    bytes.extend_from_slice(&position.asset);
+                    bytes.extend_from_slice(&position.amount.to_le_bytes());
+                }
+                bytes.extend_from_slice(&debt_amount.to_le_bytes());
+                bytes.extend_from_slice(&borrow_index.to_le_bytes());
+                bytes.extend_from_slice(&salt);
+            }
+            NotePlaintext::Balance { token, amount, salt } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&token);
+                bytes.extend_from_slice(&amount.to_le_bytes());
+                bytes.extend_from_slice(&salt);
+            }
+        }
+        bytes.resize(NOTE_PLAINTEXT_LEN, 0);
+        bytes.try_into().expect("note fits the plaintext size")
+    }
+
+    /// Decode `to_bytes` output; None for an unknown kind or non-zero padding
+    pub fn from_bytes(bytes: &[u8; NOTE_PLAINTEXT_LEN]) -> Option<Self> {
+        let mut reader = &bytes[1..];
+        let mut take = |len: usize| {
+            let (field, rest) = reader.split_at(len);
+            reader = rest;
+            field
+        };
+        let u128_le = |field: &[u8]| u128::from_le_bytes(field.try_into().unwrap());
+
+        let note = match bytes[0] {
+            0 => {
+                let mut collateral = [CollateralPosition::default(); MAX_COLLATERAL_ASSETS];
+                for position in &mut collateral {
+                    position.asset = take(20).try_into().unwrap();
+                    position.amount = u128_le(take(16));
+                }
+                NotePlaintext::Position {
+                    collateral,
+                    debt_amount: u128_le(take(16)),
+                    borrow_index: u128_le(take(16)),
+                    salt: take(32).try_into().unwrap(),
+                }
+            }
+            1 => NotePlaintext::Balance {
+                token: take(20).try_into().unwrap(),
+                amount: u128_le(take(16)),
+                salt: take(32).try_into().unwrap(),
+            },
+            _ => return None,
+        };
+
+        reader.iter().all(|&byte| byte == 0).then_some(note)
+    }
+}
+
+/// Note payload as published on-chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedNote {
+    /// Sender's ephemeral public key (SEC1 compressed)
+    pub ephemeral_public_key: [u8; 33],
+    /// Random ChaCha20-Poly1305 nonce
+    pub nonce: [u8; 12],
+    /// ChaCha20-Poly1305 ciphertext of the plaintext, authenticated with the commitment
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedNote {
+    /// ephemeral_public_key || nonce || ciphertext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.ephemeral_public_key.as_slice(), &self.nonce, &self.ciphertext].concat()
+    }
+
+    /// Split published bytes; None unless they are exactly ENCRYPTED_NOTE_LEN long
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != ENCRYPTED_NOTE_LEN {
+            return None;
+        }
+        Some(Self {
+            ephemeral_public_key: bytes[..33].try_into().unwrap(),
+            nonce: bytes[33..45].try_into().unwrap(),
+            ciphertext: bytes[45..].to_vec(),
+        })
+    }
+}
+
+/// Fresh random ephemeral secret; never reuse one across notes
+/// Balance notes use it for their stealth owner key too, so one ephemeral key serves both
+pub fn random_ephemeral_secret() -> [u8; 32] {
+    NonZeroScalar::random(&mut OsRng).to_bytes().into()
+}
+
+/// Symmetric key for a note, bound to its commitment
+/// key = hash(shared_secret || commitment || "NOTE_ENCRYPTION_KEY")
+fn note_cipher(shared_secret: &[u8; 32], commitment: &[u8; 32]) -> ChaCha20Poly1305 {
+    let mut hasher = Sha256::new();
+
+    hasher.update(shared_secret);
+    hasher.update(commitment);
+    hasher.update(b"NOTE_ENCRYPTION_KEY");
+
+    ChaCha20Poly1305::new(&hasher.finalize())
+}
+
+/// Encrypt a note to the owner's viewing public key with a fresh ephemeral key, bound to its commitment
+pub fn encrypt_note(
+    viewing_public_key: &[u8; 33],
+    commitment: &[u8; 32],
+    note: &NotePlaintext,
+) -> EncryptedNote {
+    encrypt_note_with_ephemeral_secret(&random_ephemeral_secret(), viewing_public_key, commitment, note)
+}
+
+/// Encrypt a note with the ephemeral secret its stealth owner key was derived from,
+/// so the owner recovers both from the one published ephemeral key
+pub fn encrypt_note_with_ephemeral_secret(
+    ephemeral_secret: &[u8; 32],
+    viewing_public_key: &[u8; 33],
+    commitment: &[u8; 32],
+    note: &NotePlaintext,
+) -> EncryptedNote {
+    let ephemeral_public_key =
+        crypto::derive_ecdh_public_key(ephemeral_secret).expect("invalid ephemeral secret");
+    let shared_secret = crypto::derive_stealth_shared_secret(ephemeral_secret, viewing_public_key)
+        .expect("invalid viewing public key");
+
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let payload = Payload { msg: &note.to_bytes(), aad: commitment };
+    let ciphertext = note_cipher(&shared_secret, commitment)
+        .encrypt(&nonce, payload)
+        .expect("note encryption failed");
+
+    EncryptedNote { ephemeral_public_key, nonce: nonce.into(), ciphertext }
+}
+
+/// Trial-decrypt a published note with a viewing key
+/// Returns the note and its ECDH shared secret, or None if it belongs to someone else
+pub fn decrypt_note(
+    viewing_key: &[u8; 32],
+    commitment: &[u8; 32],
+    encrypted: &EncryptedNote,
+) -> Option<(NotePlaintext, [u8; 32])> {
+    let shared_secret =
+        crypto::derive_stealth_shared_secret(viewing_key, &encrypted.ephemeral_public_key)?;
+
+    let payload = Payload { msg: &encrypted.ciphertext, aad: commitment };
+    let plaintext = note_cipher(&shared_secret, commitment)
+        .decrypt(&Nonce::from(encrypted.nonce), payload)
+        .ok()?;
+    let note = NotePlaintext::from_bytes(plaintext.as_slice().try_into().ok()?)?;
+
+    Some((note, shared_secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWING_KEY: [u8; 32] = [3u8; 32];
+    const COMMITMENT: [u8; 32] = [0xC0u8; 32];
+
+    fn viewing_public_key(viewing_key: &[u8; 32]) -> [u8; 33] {
+        crypto::derive_ecdh_public_key(viewing_key).unwrap()
+    }
+
+    fn position() -> NotePlaintext {
+        let mut collateral = [CollateralPosition::default(); MAX_COLLATERAL_ASSETS];
+        collateral[0] = CollateralPosition { asset: [0xEEu8; 20], amount: 10u128.pow(19) };
+        NotePlaintext::Position {
+            collateral,
+            debt_amount: 5_000_000_000,
+            borrow_index: 10u128.pow(18),
+            salt: [2u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let balance = NotePlaintext::Balance { token: [1u8; 20], amount: 100, salt: [4u8; 32] };
+        for note in [position(), balance] {
+            let encrypted = encrypt_note(&viewing_public_key(&VIEWING_KEY), &COMMITMENT, &note);
+            let published = encrypted.to_bytes();
+            assert_eq!(published.len(), ENCRYPTED_NOTE_LEN);
+
+            let (decrypted, _) =
+                decrypt_note(&VIEWING_KEY, &COMMITMENT, &EncryptedNote::from_bytes(&published).unwrap()).unwrap();
+            assert_eq!(decrypted, note);
+        }
+    }
+
+    #[test]
+    fn test_shared_secret_matches_ephemeral_key() {
+        // The owner recovers the shared secret the sender derived the stealth owner key from
+        let ephemeral_secret = random_ephemeral_secret();
+        let viewing_public_key = viewing_public_key(&VIEWING_KEY);
+        let encrypted =
+            encrypt_note_with_ephemeral_secret(&ephemeral_secret, &viewing_public_key, &COMMITMENT, &position());
+
+        let (_, shared_secret) = decrypt_note(&VIEWING_KEY, &COMMITMENT, &encrypted).unwrap();
+        assert_eq!(
+            shared_secret,
+            crypto::derive_stealth_shared_secret(&ephemeral_secret, &viewing_public_key).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fresh_keystream_per_encryption() {
+        // The same note encrypted twice shares no ephemeral key, nonce or ciphertext
+        let first = encrypt_note(&viewing_public_key(&VIEWING_KEY), &COMMITMENT, &position());
+        let second = encrypt_note(&viewing_public_key(&VIEWING_KEY), &COMMITMENT, &position());
+        assert_ne!(first.ephemeral_public_key, second.ephemeral_public_key);
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.ciphertext, second.ciphertext);
+
+        // Even under a reused ephemeral secret, different commitments get different keys
+        let ephemeral_secret = random_ephemeral_secret();
+        let viewing_public_key = viewing_public_key(&VIEWING_KEY);
+        let encrypt = |commitment: &[u8; 32]| {
+            encrypt_note_with_ephemeral_secret(&ephemeral_secret, &viewing_public_key, commitment, &position())
+        };
+        let mut other = encrypt(&[0xC1u8; 32]);
+        other.nonce = encrypt(&COMMITMENT).nonce;
+        assert!(decrypt_note(&VIEWING_KEY, &COMMITMENT, &other).is_none());
+    }
+
+    #[test]
+    fn test_wrong_key_or_commitment() {
+        let encrypted = encrypt_note(&viewing_public_key(&VIEWING_KEY), &COMMITMENT, &position());
+        assert!(decrypt_note(&[4u8; 32], &COMMITMENT, &encrypted).is_none());
+        assert!(decrypt_note(&VIEWING_KEY, &[0xC1u8; 32], &encrypted).is_none());
+    }
+
+    #[test]
+    fn test_tampered_note() {
+        let encrypted = encrypt_note(&viewing_public_key(&VIEWING_KEY), &COMMITMENT, &position());
+
+        let mut tampered = encrypted.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(decrypt_note(&VIEWING_KEY, &COMMITMENT, &tampered).is_none());
+
+        let mut tampered = encrypted.clone();
+        tampered.nonce[0] ^= 1;
+        assert!(decrypt_note(&VIEWING_KEY, &COMMITMENT, &tampered).is_none());
+
+        let mut tampered = encrypted.clone();
+        tampered.ephemeral_public_key = viewing_public_key(&[5u8; 32]);
+        assert!(decrypt_note(&VIEWING_KEY, &COMMITMENT, &tampered).is_none());
+
+        let mut tampered = encrypted.to_bytes();
+        tampered.pop();
+        assert!(EncryptedNote::from_bytes(&tampered).is_none());
+    }
+}
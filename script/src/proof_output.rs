@@ -0,0 +1,9 @@
+// Public values of a proof: the operation code the ZK program committed, then the operation's output
+use serde::de::DeserializeOwned;
+use sp1_sdk::SP1PublicValues;
+
+/// Read an operation's output, or None if the proof is of another operation
+pub fn read_output<T: DeserializeOwned>(public_values: &mut SP1PublicValues, operation: u8) -> Option<T> {
+    let committed: u8 = public_values.read();
+    (committed == operation).then(|| public_values.read())
+}
@@ -0,0 +1,65 @@
+// Rebuild the vault's commitment tree from its events, so notes are proven against a root it knows
+use ethers::{
+    contract::abigen,
+    core::types::{Address, U256},
+    providers::Middleware,
+};
+use std::{error::Error, sync::Arc};
+
+use super::merkle::IncrementalMerkleTree;
+
+// Events of every leaf the vault inserts, and the root they must add up to
+abigen!(
+    AegisVaultTree,
+    r#"[
+        function merkleRoot() external view returns (bytes32)
+        event Deposit(bytes32 indexed commitment, uint256 leafIndex, uint256 timestamp)
+        event Borrow(bytes32 indexed nullifierHash, bytes32 indexed newCommitment, address indexed recipient, uint256 borrowAmount, uint256 leafIndex, uint256 timestamp)
+        event BorrowViaRelayer(bytes32 indexed nullifierHash, bytes32 indexed newCommitment, address indexed actualUser, address relayer, uint256 borrowAmount, uint256 leafIndex, uint256 timestamp)
+        event Shield(bytes32 indexed commitment, address indexed token, uint256 amount, uint256 leafIndex, uint256 timestamp)
+        event ShieldedTransfer(bytes32 indexed nullifierHash, bytes32 recipientCommitment, uint256 recipientLeafIndex, bytes32 changeCommitment, uint256 changeLeafIndex, uint256 timestamp)
+    ]"#
+);
+
+/// Rebuild the vault's commitment tree from its Deposit/Borrow/Shield/ShieldedTransfer events
+pub async fn sync_tree<M: Middleware + 'static>(
+    client: Arc<M>,
+    vault_address: Address,
+) -> Result<IncrementalMerkleTree, Box<dyn Error>> {
+    let vault = AegisVaultTree::new(vault_address, client);
+
+    let mut leaves: Vec<(U256, [u8; 32])> = Vec::new();
+    for event in vault.deposit_filter().from_block(0u64).query().await? {
+        leaves.push((event.leaf_index, event.commitment));
+    }
+    for event in vault.borrow_filter().from_block(0u64).query().await? {
+        leaves.push((event.leaf_index, event.new_commitment));
+    }
+    for event in vault.borrow_via_relayer_filter().from_block(0u64).query().await? {
+        leaves.push((event.leaf_index, event.new_commitment));
+    }
+    for event in vault.shield_filter().from_block(0u64).query().await? {
+        leaves.push((event.leaf_index, event.commitment));
+    }
+    for event in vault.shielded_transfer_filter().from_block(0u64).query().await? {
+        leaves.push((event.recipient_leaf_index, event.recipient_commitment));
+        leaves.push((event.change_leaf_index, event.change_commitment));
+    }
+    leaves.sort_by_key(|(index, _)| *index);
+
+    let mut tree = IncrementalMerkleTree::new();
+    for (index, commitment) in leaves {
+        let inserted = tree.insert(commitment).ok_or("commitment tree is full")?;
+        if U256::from(inserted) != index {
+            return Err(format!("missing commitment before leaf {}", index).into());
+        }
+    }
+
+    // Our local tree must agree with the vault byte-for-byte
+    let onchain_root: [u8; 32] = vault.merkle_root().call().await?;
+    if tree.root() != onchain_root {
+        return Err("local Merkle root does not match vault root".into());
+    }
+
+    Ok(tree)
+}
@@ -0,0 +1,122 @@
+// Local note keys: public address derivation, one-time owner keys for recipients and scanning
+// Key derivation is shared with the ZK program via `crypto`
+use super::crypto::{self, PublicAddress};
+use super::note_encryption::{self, EncryptedNote, NotePlaintext};
+
+/// Published with every note sent to a public address so its owner can find it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StealthAnnouncement {
+    /// Sender's one-time ephemeral public key (SEC1 compressed)
+    pub ephemeral_public_key: [u8; 33],
+    /// One-time owner key the note is committed to
+    pub owner_key: [u8; 32],
+}
+
+/// One-time owner key a sender derived for a recipient
+#[derive(Debug, Clone, Copy)]
+pub struct StealthNoteKey {
+    /// What the sender publishes next to the note
+    pub announcement: StealthAnnouncement,
+    /// Shared secret the recipient recovers by scanning (an input to the spend proof)
+    pub shared_secret: [u8; 32],
+}
+
+/// Derive a one-time owner key for `address` from a fresh, never reused ephemeral secret
+pub fn stealth_note_key(address: &PublicAddress, ephemeral_secret: &[u8; 32]) -> StealthNoteKey {
+    let ephemeral_public_key =
+        crypto::derive_ecdh_public_key(ephemeral_secret).expect("invalid ephemeral secret");
+    let shared_secret =
+        crypto::derive_stealth_shared_secret(ephemeral_secret, &address.viewing_public_key)
+            .expect("invalid viewing public key");
+
+    StealthNoteKey {
+        announcement: StealthAnnouncement {
+            ephemeral_public_key,
+            owner_key: crypto::derive_stealth_owner_key(&address.owner_key, &shared_secret),
+        },
+        shared_secret,
+    }
+}
+
+/// A published note this wallet can open and spend
+#[derive(Debug, Clone, Copy)]
+pub struct OwnedNote {
+    /// Position of the note in the scanned list
+    pub position: usize,
+    /// The note's commitment
+    pub commitment: [u8; 32],
+    /// Decrypted note contents
+    pub note: NotePlaintext,
+    /// ECDH shared secret (a balance note's stealth shared secret for the spend proof)
+    pub shared_secret: [u8; 32],
+}
+
+/// Holds a spending key and the keys derived from it
+pub struct Wallet {
+    spending_key: [u8; 32],
+    viewing_key: [u8; 32],
+    address: PublicAddress,
+}
+
+impl Wallet {
+    /// Create a wallet from a raw spending key (the note secret key)
+    pub fn new(spending_key: [u8; 32]) -> Self {
+        Self {
+            spending_key,
+            viewing_key: crypto::derive_viewing_key(&spending_key),
+            address: crypto::derive_public_address(&spending_key),
+        }
+    }
+
+    /// Secret key proofs spending this wallet's notes take
+    pub fn spending_key(&self) -> [u8; 32] {
+        self.spending_key
+    }
+
+    /// Address to share with senders
+    pub fn address(&self) -> PublicAddress {
+        self.address
+    }
+
+    /// Shared secret of an announced note if it was sent to this wallet
+    pub fn scan(&self, announcement: &StealthAnnouncement) -> Option<[u8; 32]> {
+        let shared_secret = crypto::derive_stealth_shared_secret(
+            &self.viewing_key,
+            &announcement.ephemeral_public_key,
+        )?;
+        let owner_key = crypto::derive_stealth_owner_key(&self.address.owner_key, &shared_secret);
+
+        (owner_key == announcement.owner_key).then_some(shared_secret)
+    }
+
+    /// Trial-decrypt published (commitment, encrypted note) pairs and keep the ones this wallet
+    /// can spend: the decrypted contents must recompute the commitment under this wallet's keys
+    pub fn scan_notes(&self, notes: &[([u8; 32], EncryptedNote)]) -> Vec<OwnedNote> {
+        notes
+            .iter()
+            .enumerate()
+            .filter_map(|(position, (commitment, encrypted))| {
+                let (note, shared_secret) =
+                    note_encryption::decrypt_note(&self.viewing_key, commitment, encrypted)?;
+                let opened = match note {
+                    NotePlaintext::Position { collateral, debt_amount, borrow_index, salt } => {
+                        crypto::hash_commitment(&self.spending_key, &collateral, debt_amount, borrow_index, &salt)
+                    }
+                    NotePlaintext::Balance { token, amount, salt } => crypto::hash_balance_commitment(
+                        &crypto::derive_stealth_owner_key(&self.address.owner_key, &shared_secret),
+                        &token,
+                        amount,
+                        &salt,
+                    ),
+                };
+
+                (opened == *commitment).then_some(OwnedNote {
+                    position,
+                    commitment: *commitment,
+                    note,
+                    shared_secret,
+                })
+            })
+            .collect()
+    }
+}
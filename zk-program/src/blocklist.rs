@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::merkle::{verify_merkle_path, IncrementalMerkleTree, MERKLE_DEPTH};
+
+/// First and last leaves of every blocklist, so any address hash sorts between two leaves
+pub const LOW_SENTINEL: [u8; 32] = [0x00u8; 32];
+pub const HIGH_SENTINEL: [u8; 32] = [0xFFu8; 32];
+
+/// Hash an address into a blocklist leaf
+/// leaf = hash(address || "BLOCKLIST_ADDRESS")
+pub fn hash_blocklist_address(address: &[u8; 20]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(address);
+    hasher.update(b"BLOCKLIST_ADDRESS");
+
+    let result = hasher.finalize();
+    result.into()
+}
+
+/// Proof that an address is not on a blocklist: its leaf sorts strictly between two adjacent
+/// leaves of the sorted tree, so it can't be anywhere in it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlocklistProof {
+    /// Root of the sorted blocklist tree (public, the vault checks it is the current list)
+    pub root: [u8; 32],
+    /// Largest leaf below the address's leaf
+    pub low_leaf: [u8; 32],
+    /// Position of the low leaf (the high leaf is the next one)
+    pub low_index: u64,
+    /// Authentication path for the low leaf
+    pub low_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Smallest leaf above the address's leaf
+    pub high_leaf: [u8; 32],
+    /// Authentication path for the high leaf
+    pub high_path: [[u8; 32]; MERKLE_DEPTH],
+}
+
+/// Whether `address` is provably absent from the blocklist with root `proof.root`
+pub fn verify_exclusion(address: &[u8; 20], proof: &BlocklistProof) -> bool {
+    let leaf = hash_blocklist_address(address);
+    // The last possible index has no next leaf (and must not wrap to the first)
+    let Some(high_index) = proof.low_index.checked_add(1) else {
+        return false;
+    };
+
+    // Byte arrays compare lexicographically, i.e. as big-endian numbers
+    proof.low_leaf < leaf
+        && leaf < proof.high_leaf
+        && verify_merkle_path(&proof.low_leaf, proof.low_index, &proof.low_path, &proof.root)
+        && verify_merkle_path(&proof.high_leaf, high_index, &proof.high_path, &proof.root)
+}
+
+/// Blocklist root an operation was screened against, and whether screening passed
+/// Screening is optional: without a proof the root is zero and the vault decides
+/// whether it accepts unscreened operations
+pub fn screen_address(address: &[u8; 20], proof: &Option<BlocklistProof>) -> ([u8; 32], bool) {
+    match proof {
+        Some(proof) => (proof.root, verify_exclusion(address, proof)),
+        None => ([0u8; 32], true),
+    }
+}
+
+/// Sorted Merkle tree of blocked address hashes, bracketed by the sentinels
+/// Whoever maintains the list publishes its root; users build exclusion proofs from it
+#[allow(dead_code)] // The guest only needs the verifier half
+pub struct Blocklist {
+    /// Sorted, deduplicated leaves
+    leaves: Vec<[u8; 32]>,
+    tree: IncrementalMerkleTree,
+}
+
+#[allow(dead_code)]
+impl Blocklist {
+    /// Build the tree for `addresses`, or None if they don't fit in it
+    pub fn new(addresses: &[[u8; 20]]) -> Option<Self> {
+        let mut leaves: Vec<[u8; 32]> = addresses.iter().map(hash_blocklist_address).collect();
+        leaves.push(LOW_SENTINEL);
+        leaves.push(HIGH_SENTINEL);
+        leaves.sort_unstable();
+        leaves.dedup();
+
+        let mut tree = IncrementalMerkleTree::new();
+        for leaf in &leaves {
+            tree.insert(*leaf)?;
+        }
+
+        Some(Self { leaves, tree })
+    }
+
+    /// Root to publish (and to set on the vault)
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    /// Exclusion proof for `address`, or None if it is blocked
+    pub fn prove_exclusion(&self, address: &[u8; 20]) -> Option<BlocklistProof> {
+        let leaf = hash_blocklist_address(address);
+        let high_index = self.leaves.partition_point(|other| *other < leaf);
+        if self.leaves.get(high_index) == Some(&leaf) {
+            return None;
+        }
+
+        // The sentinels guarantee both neighbours exist
+        let low_index = high_index as u64 - 1;
+        Some(BlocklistProof {
+            root: self.root(),
+            low_leaf: self.leaves[high_index - 1],
+            low_index,
+            low_path: self.tree.path(low_index)?,
+            high_leaf: self.leaves[high_index],
+            high_path: self.tree.path(low_index + 1)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCKED: [u8; 20] = [0xBAu8; 20];
+    const CLEAN: [u8; 20] = [0xC1u8; 20];
+
+    #[test]
+    fn test_clean_address_excluded() {
+        let blocklist = Blocklist::new(&[BLOCKED, [0x01u8; 20], [0xFEu8; 20]]).unwrap();
+        let proof = blocklist.prove_exclusion(&CLEAN).unwrap();
+
+        assert!(verify_exclusion(&CLEAN, &proof));
+        assert_eq!(screen_address(&CLEAN, &Some(proof)), (blocklist.root(), true));
+    }
+
+    #[test]
+    fn test_blocked_address_cannot_prove_exclusion() {
+        let blocklist = Blocklist::new(&[BLOCKED, [0x01u8; 20]]).unwrap();
+        assert!(blocklist.prove_exclusion(&BLOCKED).is_none());
+
+        // Nor can it borrow a clean address's proof
+        let proof = blocklist.prove_exclusion(&CLEAN).unwrap();
+        assert!(!verify_exclusion(&BLOCKED, &proof));
+    }
+
+    #[test]
+    fn test_neighbours_must_be_adjacent() {
+        // Skipping over the blocked leaf: the sentinels bracket it but aren't neighbours
+        let blocklist = Blocklist::new(&[BLOCKED]).unwrap();
+        let tree_path = |index| blocklist.tree.path(index).unwrap();
+        let proof = BlocklistProof {
+            root: blocklist.root(),
+            low_leaf: LOW_SENTINEL,
+            low_index: 0,
+            low_path: tree_path(0),
+            high_leaf: HIGH_SENTINEL,
+            high_path: tree_path(2),
+        };
+
+        assert!(!verify_exclusion(&BLOCKED, &proof));
+    }
+
+    #[test]
+    fn test_last_index_has_no_neighbour() {
+        let blocklist = Blocklist::new(&[BLOCKED]).unwrap();
+        let mut proof = blocklist.prove_exclusion(&CLEAN).unwrap();
+        proof.low_index = u64::MAX;
+
+        assert!(!verify_exclusion(&CLEAN, &proof));
+    }
+
+    #[test]
+    fn test_unscreened_operation() {
+        assert_eq!(screen_address(&BLOCKED, &None), ([0u8; 32], true));
+    }
+
+    #[test]
+    fn test_proof_bound_to_root() {
+        let blocklist = Blocklist::new(&[BLOCKED]).unwrap();
+        let mut proof = blocklist.prove_exclusion(&CLEAN).unwrap();
+        proof.root = Blocklist::new(&[]).unwrap().root();
+
+        assert!(!verify_exclusion(&CLEAN, &proof));
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+/// Number of collateral slots in a note (e.g. mETH and WMNT backing one loan)
+/// Must match MAX_COLLATERAL_ASSETS in contracts/src/AegisVault.sol
+pub const MAX_COLLATERAL_ASSETS: usize = 2;
+
+/// One collateral asset held by a note; unused slots have a zero asset and amount
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CollateralPosition {
+    /// Collateral token address
+    pub asset: [u8; 20],
+    /// Amount of that token
+    pub amount: u128,
+}
+
+/// Valuation parameters of one collateral asset; unused slots have a zero asset
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CollateralMarket {
+    /// Collateral token address
+    pub asset: [u8; 20],
+    /// Price of one whole token in USD
+    pub price_usd: u128,
+    /// Share of the value counted against debt in basis points
+    /// (max LTV when borrowing, liquidation threshold when liquidating)
+    pub weight_bps: u16,
+    /// Decimals of the token amount
+    pub decimals: u8,
+}
+
+/// Note collateral holding a single asset in the first slot
+#[allow(dead_code)] // Used by the host and tests to build single-asset notes
+pub fn single_asset(asset: [u8; 20], amount: u128) -> [CollateralPosition; MAX_COLLATERAL_ASSETS] {
+    let mut collateral = [CollateralPosition::default(); MAX_COLLATERAL_ASSETS];
+    collateral[0] = CollateralPosition { asset, amount };
+    collateral
+}
+
+/// Whether a note holds any collateral at all
+pub fn has_collateral(collateral: &[CollateralPosition; MAX_COLLATERAL_ASSETS]) -> bool {
+    collateral.iter().any(|position| position.amount > 0)
+}
+
+/// Whether every slot is either empty (zero asset and amount) or a non-zero amount of a
+/// non-zero asset, with no asset held in two slots
+pub fn is_well_formed(collateral: &[CollateralPosition; MAX_COLLATERAL_ASSETS]) -> bool {
+    collateral.iter().enumerate().all(|(i, position)| {
+        if *position == CollateralPosition::default() {
+            return true;
+        }
+        position.asset != [0u8; 20]
+            && position.amount > 0
+            && collateral[..i].iter().all(|earlier| earlier.asset != position.asset)
+    })
+}
+
+/// Market entry for `asset`, if it is listed
+pub fn find_market<'a>(
+    markets: &'a [CollateralMarket; MAX_COLLATERAL_ASSETS],
+    asset: &[u8; 20],
+) -> Option<&'a CollateralMarket> {
+    markets
+        .iter()
+        .find(|market| market.asset != [0u8; 20] && market.asset == *asset)
+}
+
+/// Whether every asset the note holds has a market entry
+pub fn is_collateral_listed(
+    collateral: &[CollateralPosition; MAX_COLLATERAL_ASSETS],
+    markets: &[CollateralMarket; MAX_COLLATERAL_ASSETS],
+) -> bool {
+    collateral
+        .iter()
+        .filter(|position| position.amount > 0)
+        .all(|position| find_market(markets, &position.asset).is_some())
+}
+
+/// Total amount of `asset` held across `notes`, or None on overflow
+fn total_of(notes: &[[CollateralPosition; MAX_COLLATERAL_ASSETS]], asset: &[u8; 20]) -> Option<u128> {
+    notes
+        .iter()
+        .flatten()
+        .filter(|position| position.asset == *asset)
+        .try_fold(0u128, |total, position| total.checked_add(position.amount))
+}
+
+/// Whether `outputs` hold exactly the same total of every asset as `inputs`
+pub fn is_collateral_conserved(
+    inputs: &[[CollateralPosition; MAX_COLLATERAL_ASSETS]],
+    outputs: &[[CollateralPosition; MAX_COLLATERAL_ASSETS]],
+) -> bool {
+    inputs.iter().chain(outputs).flatten().all(|position| {
+        let total_in = total_of(inputs, &position.asset);
+        total_in.is_some() && total_in == total_of(outputs, &position.asset)
+    })
+}
+
+/// Total of every asset across `notes`, one slot per asset in order of first appearance
+/// Returns None if the notes hold more assets than a note has slots or a total overflows
+pub fn total_collateral(
+    notes: &[[CollateralPosition; MAX_COLLATERAL_ASSETS]],
+) -> Option<[CollateralPosition; MAX_COLLATERAL_ASSETS]> {
+    let mut totals = [CollateralPosition::default(); MAX_COLLATERAL_ASSETS];
+
+    for position in notes.iter().flatten().filter(|position| position.amount > 0) {
+        let slot = totals
+            .iter()
+            .position(|total| total.asset == position.asset || *total == CollateralPosition::default())?;
+        totals[slot].asset = position.asset;
+        totals[slot].amount = totals[slot].amount.checked_add(position.amount)?;
+    }
+
+    Some(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METH: [u8; 20] = [0xEE; 20];
+    const WMNT: [u8; 20] = [0xDD; 20];
+
+    fn market(asset: [u8; 20]) -> CollateralMarket {
+        CollateralMarket { asset, price_usd: 1, weight_bps: 7500, decimals: 18 }
+    }
+
+    #[test]
+    fn test_well_formed_collateral() {
+        let mut collateral = single_asset(METH, 1000);
+        assert!(is_well_formed(&collateral));
+
+        collateral[1] = CollateralPosition { asset: WMNT, amount: 500 };
+        assert!(is_well_formed(&collateral), "Two different assets are allowed");
+
+        collateral[1].asset = METH;
+        assert!(!is_well_formed(&collateral), "Same asset in two slots");
+
+        collateral[1] = CollateralPosition { asset: WMNT, amount: 0 };
+        assert!(!is_well_formed(&collateral), "Empty slots must be fully zero");
+
+        collateral[1] = CollateralPosition { asset: [0u8; 20], amount: 500 };
+        assert!(!is_well_formed(&collateral), "Amounts need an asset");
+    }
+
+    #[test]
+    fn test_collateral_must_be_listed() {
+        let markets = [market(METH), CollateralMarket::default()];
+        let mut collateral = single_asset(METH, 1000);
+        assert!(is_collateral_listed(&collateral, &markets));
+
+        collateral[1] = CollateralPosition { asset: WMNT, amount: 500 };
+        assert!(!is_collateral_listed(&collateral, &markets));
+        assert!(is_collateral_listed(&collateral, &[market(WMNT), market(METH)]));
+
+        // The zero asset is never a market
+        assert!(find_market(&markets, &[0u8; 20]).is_none());
+    }
+
+    #[test]
+    fn test_collateral_conservation() {
+        let mut mixed = single_asset(METH, 600);
+        mixed[1] = CollateralPosition { asset: WMNT, amount: 50 };
+        let mut merged = single_asset(WMNT, 50);
+        merged[1] = CollateralPosition { asset: METH, amount: 1000 };
+
+        // Merging two notes, in any slot order
+        let inputs = [single_asset(METH, 400), mixed];
+        assert!(is_collateral_conserved(&inputs, &[merged]));
+        assert!(!is_collateral_conserved(&inputs, &[single_asset(METH, 1000)]), "WMNT dropped");
+
+        // Splitting one note
+        let inputs = [single_asset(METH, 1000)];
+        assert!(is_collateral_conserved(&inputs, &[single_asset(METH, 300), single_asset(METH, 700)]));
+        assert!(!is_collateral_conserved(&inputs, &[single_asset(METH, 300), single_asset(METH, 701)]));
+        assert!(!is_collateral_conserved(&inputs, &[single_asset(METH, 300), single_asset(WMNT, 700)]));
+
+        // Overflowing totals never balance
+        let inputs = [single_asset(METH, u128::MAX), single_asset(METH, 1)];
+        assert!(!is_collateral_conserved(&inputs, &[single_asset(METH, 0)]));
+    }
+
+    #[test]
+    fn test_total_collateral() {
+        let mut mixed = single_asset(WMNT, 50);
+        mixed[1] = CollateralPosition { asset: METH, amount: 600 };
+
+        let mut expected = single_asset(METH, 1000);
+        expected[1] = CollateralPosition { asset: WMNT, amount: 50 };
+        assert_eq!(total_collateral(&[single_asset(METH, 400), mixed]), Some(expected));
+        assert_eq!(total_collateral(&[]), Some([CollateralPosition::default(); MAX_COLLATERAL_ASSETS]));
+
+        // A third asset has no slot, and totals can't overflow
+        let third = single_asset([0xCC; 20], 1);
+        assert_eq!(total_collateral(&[single_asset(METH, 400), mixed, third]), None);
+        assert_eq!(total_collateral(&[single_asset(METH, u128::MAX), single_asset(METH, 1)]), None);
+    }
+}
@@ -1,53 +1,267 @@
+use k256::elliptic_curve::{ops::Reduce, sec1::ToEncodedPoint};
+use k256::{NonZeroScalar, PublicKey, Scalar, U256};
 use sha2::{Digest, Sha256};
 
+use super::collateral::{CollateralPosition, MAX_COLLATERAL_ASSETS};
+use super::hashing::{domain_hasher, Domain};
+
 /// Hash a note to create a commitment
-/// commitment = hash(secret_key || collateral_amount || debt_amount || salt)
+/// commitment = hash(position domain || secret_key || (asset || amount) per collateral slot || debt_amount || borrow_index || salt)
+/// `debt_amount` is the debt as of `borrow_index`, the global index when the note was created
 pub fn hash_commitment(
     secret_key: &[u8; 32],
-    collateral_amount: u128,
+    collateral: &[CollateralPosition; MAX_COLLATERAL_ASSETS],
     debt_amount: u128,
+    borrow_index: u128,
+    salt: &[u8; 32],
+) -> [u8; 32] {
+    let mut hasher = domain_hasher(Domain::PositionCommitment);
+
+    hasher.update(secret_key);
+    for position in collateral {
+        hasher.update(position.asset);
+        hasher.update(position.amount.to_le_bytes());
+    }
+    hasher.update(debt_amount.to_le_bytes());
+    hasher.update(borrow_index.to_le_bytes());
+    hasher.update(salt);
+
+    hasher.finalize()
+}
+
+/// Version 0 note commitment, without a domain prefix (notes created before versioning)
+/// Only used to migrate legacy notes
+pub fn hash_commitment_v0(
+    secret_key: &[u8; 32],
+    collateral: &[CollateralPosition; MAX_COLLATERAL_ASSETS],
+    debt_amount: u128,
+    borrow_index: u128,
     salt: &[u8; 32],
 ) -> [u8; 32] {
     let mut hasher = Sha256::new();
     
     hasher.update(secret_key);
-    hasher.update(collateral_amount.to_le_bytes());
+    for position in collateral {
+        hasher.update(position.asset);
+        hasher.update(position.amount.to_le_bytes());
+    }
     hasher.update(debt_amount.to_le_bytes());
+    hasher.update(borrow_index.to_le_bytes());
     hasher.update(salt);
     
     let result = hasher.finalize();
     result.into()
 }
 
-/// Hash to create a nullifier (marks note as spent)
-/// nullifier = hash(secret_key || "NULLIFIER" || salt)
-pub fn hash_nullifier(secret_key: &[u8; 32], salt: &[u8; 32]) -> [u8; 32] {
+/// Derive the nullifier key from the spending key
+/// nullifier_key = hash(secret_key || "NULLIFIER_KEY")
+/// The nullifier key can recognise spends but can't open or spend notes
+pub fn derive_nullifier_key(secret_key: &[u8; 32]) -> [u8; 32] {
     let mut hasher = Sha256::new();
-    
+
     hasher.update(secret_key);
-    hasher.update(b"NULLIFIER");
+    hasher.update(b"NULLIFIER_KEY");
+
+    let result = hasher.finalize();
+    result.into()
+}
+
+/// Derive the owner key that balance notes are addressed to
+/// owner_key = hash(secret_key || "OWNER_KEY")
+/// Notes are never committed to it directly but to a one-time stealth owner key derived from it
+pub fn derive_owner_key(secret_key: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(secret_key);
+    hasher.update(b"OWNER_KEY");
+
+    let result = hasher.finalize();
+    result.into()
+}
+
+/// Derive the viewing key from the spending key
+/// viewing_key = hash(secret_key || "VIEWING_KEY") reduced to a secp256k1 scalar
+/// The viewing key can find notes sent to the owner but can't spend them
+#[allow(dead_code)] // Only the host scans for notes
+pub fn derive_viewing_key(secret_key: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(secret_key);
+    hasher.update(b"VIEWING_KEY");
+
+    let result = hasher.finalize();
+    <Scalar as Reduce<U256>>::reduce_bytes(&result).to_bytes().into()
+}
+
+/// SEC1-compressed secp256k1 public key of a private scalar (viewing or ephemeral key)
+/// Returns None for the zero scalar or a value outside the curve order
+#[allow(dead_code)] // Only the host derives addresses and ephemeral keys
+pub fn derive_ecdh_public_key(private_key: &[u8; 32]) -> Option<[u8; 33]> {
+    let scalar = Option::<NonZeroScalar>::from(NonZeroScalar::from_repr((*private_key).into()))?;
+    let public_key = PublicKey::from_secret_scalar(&scalar);
+
+    public_key.to_encoded_point(true).as_bytes().try_into().ok()
+}
+
+/// Public address senders need to create notes for an owner
+#[allow(dead_code)] // Only the host derives addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicAddress {
+    /// Owner key the one-time stealth owner keys are derived from
+    pub owner_key: [u8; 32],
+    /// Public viewing key senders run ECDH against (SEC1 compressed)
+    pub viewing_public_key: [u8; 33],
+}
+
+/// Derive the public address of a spending key
+#[allow(dead_code)] // Only the host derives addresses
+pub fn derive_public_address(secret_key: &[u8; 32]) -> PublicAddress {
+    PublicAddress {
+        owner_key: derive_owner_key(secret_key),
+        viewing_public_key: derive_ecdh_public_key(&derive_viewing_key(secret_key))
+            .expect("viewing key is a valid scalar"),
+    }
+}
+
+/// ECDH secret shared by a note's sender (ephemeral key, viewing public key) and
+/// its owner (viewing key, ephemeral public key)
+/// shared_secret = hash(sec1(private_key * public_key) || "STEALTH_SHARED_SECRET")
+/// Returns None for malformed keys
+#[allow(dead_code)] // Only the host runs ECDH
+pub fn derive_stealth_shared_secret(private_key: &[u8; 32], public_key: &[u8]) -> Option<[u8; 32]> {
+    let scalar = Option::<NonZeroScalar>::from(NonZeroScalar::from_repr((*private_key).into()))?;
+    let public_key = PublicKey::from_sec1_bytes(public_key).ok()?;
+    let shared_point = (public_key.to_projective() * *scalar).to_affine();
+
+    let mut hasher = Sha256::new();
+
+    hasher.update(shared_point.to_encoded_point(true).as_bytes());
+    hasher.update(b"STEALTH_SHARED_SECRET");
+
+    let result = hasher.finalize();
+    Some(result.into())
+}
+
+/// One-time owner key a note for `owner_key` is committed to
+/// stealth_owner_key = hash(owner_key || "STEALTH_OWNER_KEY" || shared_secret)
+/// Notes to the same owner get unlinkable keys; spending still needs the owner's secret key
+pub fn derive_stealth_owner_key(owner_key: &[u8; 32], shared_secret: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(owner_key);
+    hasher.update(b"STEALTH_OWNER_KEY");
+    hasher.update(shared_secret);
+
+    let result = hasher.finalize();
+    result.into()
+}
+
+/// Hash a token balance note to create a commitment
+/// commitment = hash(balance domain || owner_key || token || amount || salt)
+pub fn hash_balance_commitment(
+    owner_key: &[u8; 32],
+    token: &[u8; 20],
+    amount: u128,
+    salt: &[u8; 32],
+) -> [u8; 32] {
+    let mut hasher = domain_hasher(Domain::BalanceCommitment);
+
+    hasher.update(owner_key);
+    hasher.update(token);
+    hasher.update(amount.to_le_bytes());
     hasher.update(salt);
-    
+
+    hasher.finalize()
+}
+
+/// Version 0 balance commitment: hash(owner_key || "BALANCE_NOTE" || token || amount || salt)
+/// Only used to migrate legacy notes
+pub fn hash_balance_commitment_v0(
+    owner_key: &[u8; 32],
+    token: &[u8; 20],
+    amount: u128,
+    salt: &[u8; 32],
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(owner_key);
+    hasher.update(b"BALANCE_NOTE");
+    hasher.update(token);
+    hasher.update(amount.to_le_bytes());
+    hasher.update(salt);
+
+    let result = hasher.finalize();
+    result.into()
+}
+
+/// Hash to create a nullifier (marks note as spent)
+/// nullifier = hash(nullifier domain || nullifier_key || commitment || leaf_index)
+/// Binding to the commitment and its position makes every note's nullifier unique,
+/// even when two notes share a salt or an identical commitment is inserted twice
+pub fn hash_nullifier(
+    nullifier_key: &[u8; 32],
+    commitment: &[u8; 32],
+    leaf_index: u64,
+) -> [u8; 32] {
+    let mut hasher = domain_hasher(Domain::Nullifier);
+
+    hasher.update(nullifier_key);
+    hasher.update(commitment);
+    hasher.update(leaf_index.to_le_bytes());
+
+    hasher.finalize()
+}
+
+/// Version 0 nullifier: hash(nullifier_key || "NULLIFIER" || commitment || leaf_index)
+/// Revealed when a legacy note is migrated, so it can't be migrated twice
+pub fn hash_nullifier_v0(
+    nullifier_key: &[u8; 32],
+    commitment: &[u8; 32],
+    leaf_index: u64,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(nullifier_key);
+    hasher.update(b"NULLIFIER");
+    hasher.update(commitment);
+    hasher.update(leaf_index.to_le_bytes());
+
     let result = hasher.finalize();
     result.into()
 }
 
 /// Verify ownership of a note
 /// Returns true if the provided secret can generate the commitment
-pub fn verify_note_ownership(
+#[cfg(test)]
+fn verify_note_ownership(
     secret_key: &[u8; 32],
-    collateral_amount: u128,
+    collateral: &[CollateralPosition; MAX_COLLATERAL_ASSETS],
     debt_amount: u128,
+    borrow_index: u128,
     salt: &[u8; 32],
     expected_commitment: &[u8; 32],
 ) -> bool {
-    let computed = hash_commitment(secret_key, collateral_amount, debt_amount, salt);
+    let computed = hash_commitment(secret_key, collateral, debt_amount, borrow_index, salt);
     computed == *expected_commitment
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::collateral::single_asset;
+
+    const ASSET: [u8; 20] = [0xEE; 20];
+
+    /// Borrow index of a note with no accrued interest (1.0 in 18-decimal fixed point)
+    const INDEX: u128 = 1_000_000_000_000_000_000;
+
+    fn from_hex(hex: &str) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        out
+    }
 
     #[test]
     fn test_commitment_deterministic() {
@@ -56,23 +270,235 @@ mod tests {
         let amount = 1000u128;
         let debt = 500u128;
 
-        let hash1 = hash_commitment(&secret, amount, debt, &salt);
-        let hash2 = hash_commitment(&secret, amount, debt, &salt);
+        let hash1 = hash_commitment(&secret, &single_asset(ASSET, amount), debt, INDEX, &salt);
+        let hash2 = hash_commitment(&secret, &single_asset(ASSET, amount), debt, INDEX, &salt);
 
         assert_eq!(hash1, hash2, "Hash should be deterministic");
     }
 
     #[test]
-    fn test_nullifier_deterministic() {
+    fn test_commitment_binds_borrow_index() {
+        let secret = [1u8; 32];
+        let salt = [2u8; 32];
+
+        assert_ne!(
+            hash_commitment(&secret, &single_asset(ASSET, 1000), 500, INDEX, &salt),
+            hash_commitment(&secret, &single_asset(ASSET, 1000), 500, INDEX + 1, &salt),
+            "Notes recorded at different borrow indices must differ"
+        );
+    }
+
+    #[test]
+    fn test_commitment_binds_collateral_assets() {
         let secret = [1u8; 32];
         let salt = [2u8; 32];
 
-        let null1 = hash_nullifier(&secret, &salt);
-        let null2 = hash_nullifier(&secret, &salt);
+        let mut two_assets = single_asset(ASSET, 1000);
+        two_assets[1] = CollateralPosition { asset: [0xDD; 20], amount: 500 };
+        let mut swapped = two_assets;
+        swapped.swap(0, 1);
+
+        let one_asset = hash_commitment(&secret, &single_asset(ASSET, 1000), 0, INDEX, &salt);
+        assert_ne!(
+            one_asset,
+            hash_commitment(&secret, &single_asset([0xDD; 20], 1000), 0, INDEX, &salt),
+            "Same amount of a different asset must differ"
+        );
+        assert_ne!(one_asset, hash_commitment(&secret, &two_assets, 0, INDEX, &salt));
+        assert_ne!(
+            hash_commitment(&secret, &two_assets, 0, INDEX, &salt),
+            hash_commitment(&secret, &swapped, 0, INDEX, &salt),
+            "Collateral slots are ordered"
+        );
+    }
+
+    #[test]
+    fn test_nullifier_deterministic() {
+        let nullifier_key = derive_nullifier_key(&[1u8; 32]);
+        let commitment = hash_commitment(&[1u8; 32], &single_asset(ASSET, 1000), 0, INDEX, &[2u8; 32]);
+
+        let null1 = hash_nullifier(&nullifier_key, &commitment, 0);
+        let null2 = hash_nullifier(&nullifier_key, &commitment, 0);
 
         assert_eq!(null1, null2, "Nullifier should be deterministic");
     }
 
+    #[test]
+    fn test_nullifier_key_separate_from_secret() {
+        let secret = [1u8; 32];
+        let nullifier_key = derive_nullifier_key(&secret);
+
+        assert_ne!(nullifier_key, secret, "Nullifier key must not be the spending key");
+        assert_ne!(
+            nullifier_key,
+            derive_nullifier_key(&[2u8; 32]),
+            "Different secrets should produce different nullifier keys"
+        );
+    }
+
+    #[test]
+    fn test_nullifier_unique_for_notes_sharing_salt() {
+        let secret = [1u8; 32];
+        let salt = [2u8; 32];
+        let nullifier_key = derive_nullifier_key(&secret);
+
+        // Same owner and salt, different amounts
+        let note1 = hash_commitment(&secret, &single_asset(ASSET, 1000), 0, INDEX, &salt);
+        let note2 = hash_commitment(&secret, &single_asset(ASSET, 1000), 500, INDEX, &salt);
+        let note3 = hash_commitment(&secret, &single_asset(ASSET, 2000), 0, INDEX, &salt);
+
+        let null1 = hash_nullifier(&nullifier_key, &note1, 0);
+        let null2 = hash_nullifier(&nullifier_key, &note2, 1);
+        let null3 = hash_nullifier(&nullifier_key, &note3, 2);
+
+        assert_ne!(null1, null2, "Notes sharing a salt must not collide");
+        assert_ne!(null1, null3, "Notes sharing a salt must not collide");
+        assert_ne!(null2, null3, "Notes sharing a salt must not collide");
+    }
+
+    #[test]
+    fn test_nullifier_unique_per_leaf_index() {
+        let nullifier_key = derive_nullifier_key(&[1u8; 32]);
+        let commitment = hash_commitment(&[1u8; 32], &single_asset(ASSET, 1000), 0, INDEX, &[2u8; 32]);
+
+        assert_ne!(
+            hash_nullifier(&nullifier_key, &commitment, 0),
+            hash_nullifier(&nullifier_key, &commitment, 1),
+            "Same commitment at different leaves should have different nullifiers"
+        );
+    }
+
+    #[test]
+    fn test_balance_commitment_keyed_to_owner() {
+        let owner_key = derive_owner_key(&[1u8; 32]);
+        let commitment = hash_balance_commitment(&owner_key, &ASSET, 1000, &[2u8; 32]);
+
+        assert_ne!(owner_key, [1u8; 32], "Owner key must not be the spending key");
+        assert_ne!(owner_key, derive_nullifier_key(&[1u8; 32]), "Owner and nullifier keys differ");
+        assert_ne!(
+            commitment,
+            hash_balance_commitment(&derive_owner_key(&[3u8; 32]), &ASSET, 1000, &[2u8; 32]),
+            "Notes to different owners must differ"
+        );
+        assert_ne!(commitment, hash_balance_commitment(&owner_key, &[0xDD; 20], 1000, &[2u8; 32]));
+        assert_ne!(commitment, hash_balance_commitment(&owner_key, &ASSET, 1001, &[2u8; 32]));
+    }
+
+    #[test]
+    fn test_stealth_keys_agree_between_sender_and_owner() {
+        let secret = [1u8; 32];
+        let address = derive_public_address(&secret);
+        let ephemeral_secret = [5u8; 32];
+        let ephemeral_public_key = derive_ecdh_public_key(&ephemeral_secret).unwrap();
+
+        // Sender: ephemeral key with the address; owner: viewing key with the announcement
+        let sender_secret =
+            derive_stealth_shared_secret(&ephemeral_secret, &address.viewing_public_key).unwrap();
+        let owner_secret =
+            derive_stealth_shared_secret(&derive_viewing_key(&secret), &ephemeral_public_key).unwrap();
+        assert_eq!(sender_secret, owner_secret);
+
+        // Someone else's viewing key derives a different secret
+        assert_ne!(
+            derive_stealth_shared_secret(&derive_viewing_key(&[2u8; 32]), &ephemeral_public_key),
+            Some(sender_secret)
+        );
+
+        // Every ephemeral key gives the same owner a fresh one-time key
+        let other_secret = derive_stealth_shared_secret(&[6u8; 32], &address.viewing_public_key).unwrap();
+        assert_ne!(
+            derive_stealth_owner_key(&address.owner_key, &sender_secret),
+            derive_stealth_owner_key(&address.owner_key, &other_secret)
+        );
+    }
+
+    #[test]
+    fn test_malformed_stealth_keys_rejected() {
+        let viewing_public_key = derive_public_address(&[1u8; 32]).viewing_public_key;
+
+        assert!(derive_ecdh_public_key(&[0u8; 32]).is_none(), "Zero is not a private key");
+        assert!(derive_ecdh_public_key(&[0xFFu8; 32]).is_none(), "Above the curve order");
+        assert!(derive_stealth_shared_secret(&[0u8; 32], &viewing_public_key).is_none());
+
+        // x-coordinate above the field prime, and a truncated key
+        let mut off_curve = [0xFFu8; 33];
+        off_curve[0] = 0x02;
+        assert!(derive_stealth_shared_secret(&[5u8; 32], &off_curve).is_none());
+        assert!(derive_stealth_shared_secret(&[5u8; 32], &viewing_public_key[..20]).is_none());
+    }
+
+    #[test]
+    fn test_current_hashes_differ_from_legacy() {
+        let secret = [1u8; 32];
+        let salt = [2u8; 32];
+        let nullifier_key = derive_nullifier_key(&secret);
+        let owner_key = derive_owner_key(&secret);
+
+        let commitment = hash_commitment(&secret, &single_asset(ASSET, 1000), 500, INDEX, &salt);
+        let legacy_commitment = hash_commitment_v0(&secret, &single_asset(ASSET, 1000), 500, INDEX, &salt);
+        assert_ne!(commitment, legacy_commitment, "A migrated note gets a fresh commitment");
+        assert_ne!(
+            hash_balance_commitment(&owner_key, &ASSET, 1000, &salt),
+            hash_balance_commitment_v0(&owner_key, &ASSET, 1000, &salt)
+        );
+
+        // Spending the migrated note can't reveal the nullifier its migration revealed
+        assert_ne!(
+            hash_nullifier(&nullifier_key, &commitment, 0),
+            hash_nullifier_v0(&nullifier_key, &legacy_commitment, 0)
+        );
+        assert_ne!(
+            hash_nullifier(&nullifier_key, &legacy_commitment, 0),
+            hash_nullifier_v0(&nullifier_key, &legacy_commitment, 0)
+        );
+    }
+
+    #[test]
+    fn test_legacy_nullifier_golden_vector() {
+        // Pins version 0 under every hash backend: a change here lets legacy notes migrate twice
+        let preimage = [&[1u8; 32][..], b"NULLIFIER", &[2u8; 32], &5u64.to_le_bytes()].concat();
+        let expected: [u8; 32] = Sha256::digest(preimage).into();
+
+        assert_eq!(hash_nullifier_v0(&[1u8; 32], &[2u8; 32], 5), expected);
+        assert_eq!(expected, from_hex("6d71283a71790b12158ad97cdbee5a46b7b81659f6b51be76fabb13e3d4a99f0"));
+    }
+
+    #[cfg(not(any(feature = "poseidon", feature = "keccak")))]
+    #[test]
+    fn test_current_hashes_golden_vectors() {
+        // Pins the version 1 layout: a change here orphans every note in the tree
+        let secret = [1u8; 32];
+        let salt = [2u8; 32];
+        let commitment = hash_commitment(&secret, &single_asset(ASSET, 1000), 500, INDEX, &salt);
+
+        assert_eq!(commitment, from_hex("b7784061f34c1f1a2417630c1f421e7190ab8df2c2206550ee186b9f7cf7dde0"));
+        assert_eq!(
+            hash_balance_commitment(&derive_owner_key(&secret), &ASSET, 1000, &salt),
+            from_hex("8b70e7d9c3cbfb3f7a2abd63fcb0fb7787fd1e6a601372899da8f013c3177cce")
+        );
+        assert_eq!(
+            hash_nullifier(&derive_nullifier_key(&secret), &commitment, 3),
+            from_hex("aba95afba997c262d630753dd5355344d659900ebffabacd1e0bb2f8f25846ec")
+        );
+    }
+
+    #[cfg(feature = "keccak")]
+    #[test]
+    fn test_keccak_hashes_golden_vectors() {
+        // Shared with the Foundry tests, which recompute them with keccak256
+        let secret = [1u8; 32];
+        let salt = [2u8; 32];
+
+        assert_eq!(
+            hash_balance_commitment(&derive_owner_key(&secret), &ASSET, 1000, &salt),
+            from_hex("225772b9f7b3d8207f6709234e75718ac264e72c488e422f35de5d02bf9f989e")
+        );
+        assert_eq!(
+            hash_nullifier(&[3u8; 32], &[4u8; 32], 3),
+            from_hex("a29f13e71af16d12903fd423eb3a263bce967c9c2dcdfa4998f666999c1c3660")
+        );
+    }
+
     #[test]
     fn test_different_secrets_different_commitments() {
         let secret1 = [1u8; 32];
@@ -81,8 +507,8 @@ mod tests {
         let amount = 1000u128;
         let debt = 0u128;
 
-        let hash1 = hash_commitment(&secret1, amount, debt, &salt);
-        let hash2 = hash_commitment(&secret2, amount, debt, &salt);
+        let hash1 = hash_commitment(&secret1, &single_asset(ASSET, amount), debt, INDEX, &salt);
+        let hash2 = hash_commitment(&secret2, &single_asset(ASSET, amount), debt, INDEX, &salt);
 
         assert_ne!(hash1, hash2, "Different secrets should produce different hashes");
     }
@@ -94,16 +520,16 @@ mod tests {
         let amount = 1000u128;
         let debt = 500u128;
 
-        let commitment = hash_commitment(&secret, amount, debt, &salt);
+        let commitment = hash_commitment(&secret, &single_asset(ASSET, amount), debt, INDEX, &salt);
 
         assert!(
-            verify_note_ownership(&secret, amount, debt, &salt, &commitment),
+            verify_note_ownership(&secret, &single_asset(ASSET, amount), debt, INDEX, &salt, &commitment),
             "Should verify correct ownership"
         );
 
         let wrong_secret = [99u8; 32];
         assert!(
-            !verify_note_ownership(&wrong_secret, amount, debt, &salt, &commitment),
+            !verify_note_ownership(&wrong_secret, &single_asset(ASSET, amount), debt, INDEX, &salt, &commitment),
             "Should reject wrong secret"
         );
     }
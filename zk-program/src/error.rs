@@ -0,0 +1,210 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::math::MathError;
+
+/// Why an operation was rejected, committed in its public values (one byte, 0 = success)
+/// Codes are append-only: the vault and host tooling decode them by value
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// Every check passed
+    Success = 0,
+    /// Operation type is not one the program handles
+    UnknownOperation = 1,
+    /// Amount (or collateral) is zero
+    ZeroAmount = 2,
+    /// Amount exceeds the note's balance, collateral or debt
+    InsufficientBalance = 3,
+    /// Collateral slots hold a zero asset, a zero amount or the same asset twice
+    MalformedCollateral = 4,
+    /// Note's commitment is not in the tree under the given root
+    MembershipFailed = 5,
+    /// A price is missing, not signed by the oracle or signed by different oracles
+    InvalidPriceSignature = 6,
+    /// The note holds an asset without a market
+    UnlistedCollateral = 7,
+    /// Debt exceeds the LTV-weighted collateral value
+    LtvExceeded = 8,
+    /// Arithmetic overflowed
+    Overflow = 9,
+    /// Token or price decimals are out of range
+    InvalidDecimals = 10,
+    /// Arithmetic divided by zero
+    DivisionByZero = 11,
+    /// Borrow index is zero or lower than the note's index
+    InvalidBorrowIndex = 12,
+    /// Address is on the blocklist (its exclusion proof doesn't verify)
+    Blocklisted = 13,
+    /// Recipient owner key is zero
+    InvalidRecipient = 14,
+    /// A market has a zero price or a weight outside (0, 100%]
+    InvalidMarket = 15,
+    /// Note is healthy (or has no debt), so it can't be liquidated
+    PositionHealthy = 16,
+    /// Join-split spends or creates a number of notes other than 1 or 2
+    InvalidNoteCount = 17,
+    /// The same note is spent twice
+    DuplicateNote = 18,
+    /// Created notes don't hold exactly the spent collateral and debt
+    NotConserved = 19,
+    /// Disclosed statement doesn't hold for the note
+    StatementFalse = 20,
+    /// Batch deposit holds no deposits or more than fit one proof
+    InvalidBatchSize = 21,
+    /// Batch deposits have different funders or blocklist roots, or their total collateral
+    /// doesn't fit a note's slots
+    BatchMismatch = 22,
+}
+
+/// Every code, indexed by value
+const ERROR_CODES: [ErrorCode; 23] = [
+    ErrorCode::Success,
+    ErrorCode::UnknownOperation,
+    ErrorCode::ZeroAmount,
+    ErrorCode::InsufficientBalance,
+    ErrorCode::MalformedCollateral,
+    ErrorCode::MembershipFailed,
+    ErrorCode::InvalidPriceSignature,
+    ErrorCode::UnlistedCollateral,
+    ErrorCode::LtvExceeded,
+    ErrorCode::Overflow,
+    ErrorCode::InvalidDecimals,
+    ErrorCode::DivisionByZero,
+    ErrorCode::InvalidBorrowIndex,
+    ErrorCode::Blocklisted,
+    ErrorCode::InvalidRecipient,
+    ErrorCode::InvalidMarket,
+    ErrorCode::PositionHealthy,
+    ErrorCode::InvalidNoteCount,
+    ErrorCode::DuplicateNote,
+    ErrorCode::NotConserved,
+    ErrorCode::StatementFalse,
+    ErrorCode::InvalidBatchSize,
+    ErrorCode::BatchMismatch,
+];
+
+impl ErrorCode {
+    /// Code of an operation from its checks, run in order until the first failure
+    pub fn from_checks(checks: impl FnOnce() -> Result<(), ErrorCode>) -> Self {
+        match checks() {
+            Ok(()) => ErrorCode::Success,
+            Err(code) => code,
+        }
+    }
+
+    /// Decode a committed code, or None if it is not one this build knows
+    pub fn from_u8(code: u8) -> Option<Self> {
+        ERROR_CODES.get(code as usize).copied()
+    }
+
+    /// Whether every check passed
+    pub fn is_success(self) -> bool {
+        self == ErrorCode::Success
+    }
+
+    /// Reason shown to users when an operation is rejected
+    pub fn description(self) -> &'static str {
+        match self {
+            ErrorCode::Success => "success",
+            ErrorCode::UnknownOperation => "unknown operation type",
+            ErrorCode::ZeroAmount => "amount is zero",
+            ErrorCode::InsufficientBalance => "amount exceeds the note's balance",
+            ErrorCode::MalformedCollateral => "collateral slots are malformed",
+            ErrorCode::MembershipFailed => "note is not in the commitment tree",
+            ErrorCode::InvalidPriceSignature => "price is not signed by the oracle",
+            ErrorCode::UnlistedCollateral => "note holds an asset without a market",
+            ErrorCode::LtvExceeded => "debt exceeds the maximum LTV",
+            ErrorCode::Overflow => "arithmetic overflow",
+            ErrorCode::InvalidDecimals => "decimals out of range",
+            ErrorCode::DivisionByZero => "division by zero",
+            ErrorCode::InvalidBorrowIndex => "borrow index is below the note's index",
+            ErrorCode::Blocklisted => "address is on the blocklist",
+            ErrorCode::InvalidRecipient => "recipient key is zero",
+            ErrorCode::InvalidMarket => "market price or weight is invalid",
+            ErrorCode::PositionHealthy => "position is healthy",
+            ErrorCode::InvalidNoteCount => "join-split must spend and create 1 or 2 notes",
+            ErrorCode::DuplicateNote => "the same note is spent twice",
+            ErrorCode::NotConserved => "collateral or debt is not conserved",
+            ErrorCode::StatementFalse => "statement does not hold for the note",
+            ErrorCode::InvalidBatchSize => "batch must hold 1 to 16 deposits",
+            ErrorCode::BatchMismatch => "batch deposits don't share a funder, root or collateral slots",
+        }
+    }
+}
+
+impl core::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} (code {})", self.description(), *self as u8)
+    }
+}
+
+/// Err(`code`) unless `condition` holds, so checks chain with `?`
+pub fn ensure(condition: bool, code: ErrorCode) -> Result<(), ErrorCode> {
+    if condition {
+        Ok(())
+    } else {
+        Err(code)
+    }
+}
+
+impl From<MathError> for ErrorCode {
+    fn from(error: MathError) -> Self {
+        match error {
+            MathError::Overflow => ErrorCode::Overflow,
+            MathError::InvalidDecimals => ErrorCode::InvalidDecimals,
+            MathError::DivisionByZero => ErrorCode::DivisionByZero,
+            MathError::InvalidIndex => ErrorCode::InvalidBorrowIndex,
+        }
+    }
+}
+
+// Serialized as its value so it takes the single byte the vault reads
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = u8::deserialize(deserializer)?;
+        ErrorCode::from_u8(code).ok_or_else(|| D::Error::custom("unknown error code"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_indexed_by_value() {
+        for (value, code) in ERROR_CODES.iter().enumerate() {
+            assert_eq!(*code as usize, value);
+            assert_eq!(ErrorCode::from_u8(value as u8), Some(*code));
+        }
+        assert_eq!(ErrorCode::from_u8(ERROR_CODES.len() as u8), None);
+    }
+
+    #[test]
+    fn test_first_failure_wins() {
+        let code = ErrorCode::from_checks(|| {
+            ensure(true, ErrorCode::MembershipFailed)?;
+            ensure(false, ErrorCode::ZeroAmount)?;
+            ensure(false, ErrorCode::LtvExceeded)
+        });
+
+        assert_eq!(code, ErrorCode::ZeroAmount);
+        assert!(ErrorCode::from_checks(|| Ok(())).is_success());
+    }
+
+    #[test]
+    fn test_math_errors_keep_their_cause() {
+        let code = ErrorCode::from_checks(|| {
+            Err(MathError::InvalidIndex)?;
+            Ok(())
+        });
+
+        assert_eq!(code, ErrorCode::InvalidBorrowIndex);
+    }
+}
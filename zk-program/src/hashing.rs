@@ -0,0 +1,215 @@
+#[cfg(feature = "poseidon")]
+use ark_bn254::Fr;
+#[cfg(feature = "poseidon")]
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
+#[cfg(not(any(feature = "poseidon", feature = "keccak")))]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "keccak")]
+use tiny_keccak::{Hasher, Keccak};
+
+#[cfg(all(feature = "poseidon", feature = "keccak"))]
+compile_error!("the poseidon and keccak hash backends are mutually exclusive");
+
+/// Version of the commitment and nullifier hash layout, the first byte of every
+/// domain-separated hash
+/// Version 0 is the legacy layout (raw fields, tags in the middle of the message): legacy
+/// notes can only be spent by migrating them to the current version
+pub const HASH_VERSION: u8 = 1;
+
+/// Purpose of a hash; each has its own tag so different structures never hash alike
+/// Key derivations keep their suffix-tagged layout, since changing them would change
+/// every user's address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Domain {
+    /// Lending position note (collateral and debt)
+    PositionCommitment,
+    /// Token balance note (shielded transfers)
+    BalanceCommitment,
+    /// Nullifier revealed when a note is spent
+    Nullifier,
+}
+
+impl Domain {
+    /// Tag hashed after the version, unique per purpose
+    pub fn tag(self) -> &'static [u8] {
+        match self {
+            Domain::PositionCommitment => b"aegis.position_commitment",
+            Domain::BalanceCommitment => b"aegis.balance_commitment",
+            Domain::Nullifier => b"aegis.nullifier",
+        }
+    }
+}
+
+/// Hash function behind commitments and nullifiers (`--features poseidon` selects Poseidon,
+/// `--features keccak` keccak256, which the vault can recompute cheaply)
+/// The ZK program and the host must use the same one, and a deployment can't switch
+/// without migrating every note
+#[allow(dead_code)] // Only the host reports the backend
+pub const HASH_BACKEND: &str = if cfg!(feature = "poseidon") {
+    "poseidon"
+} else if cfg!(feature = "keccak") {
+    "keccak256"
+} else {
+    "sha256"
+};
+
+/// Bytes packed into each Poseidon input (31 bytes always fit below the BN254 modulus)
+#[cfg(feature = "poseidon")]
+const POSEIDON_CHUNK_BYTES: usize = 31;
+
+/// Message chunks absorbed per Poseidon call, next to the chaining value
+/// (the circom parameters take at most 12 inputs)
+#[cfg(feature = "poseidon")]
+const POSEIDON_CHUNKS_PER_CALL: usize = 11;
+
+/// Hasher for one domain, backed by SHA-256, keccak256 or Poseidon
+pub struct DomainHasher {
+    #[cfg(not(any(feature = "poseidon", feature = "keccak")))]
+    sha256: Sha256,
+    #[cfg(feature = "keccak")]
+    keccak: Keccak,
+    /// Poseidon works on whole field elements, so the message is packed at the end
+    #[cfg(feature = "poseidon")]
+    message: Vec<u8>,
+}
+
+impl DomainHasher {
+    #[cfg(not(any(feature = "poseidon", feature = "keccak")))]
+    fn new() -> Self {
+        DomainHasher { sha256: Sha256::new() }
+    }
+
+    #[cfg(feature = "keccak")]
+    fn new() -> Self {
+        DomainHasher { keccak: Keccak::v256() }
+    }
+
+    #[cfg(feature = "poseidon")]
+    fn new() -> Self {
+        DomainHasher { message: Vec::new() }
+    }
+
+    /// Absorb the next field
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        #[cfg(not(any(feature = "poseidon", feature = "keccak")))]
+        self.sha256.update(data);
+        #[cfg(feature = "keccak")]
+        self.keccak.update(data.as_ref());
+        #[cfg(feature = "poseidon")]
+        self.message.extend_from_slice(data.as_ref());
+    }
+
+    #[cfg(not(any(feature = "poseidon", feature = "keccak")))]
+    pub fn finalize(self) -> [u8; 32] {
+        self.sha256.finalize().into()
+    }
+
+    #[cfg(feature = "keccak")]
+    pub fn finalize(self) -> [u8; 32] {
+        let mut output = [0u8; 32];
+        self.keccak.finalize(&mut output);
+        output
+    }
+
+    /// Poseidon over BN254 (circom parameters), chained over 31-byte chunks:
+    /// state = length, then state = poseidon(state || next 11 chunks) until the message ends
+    /// Starting from the length keeps messages that differ only in zero padding apart
+    #[cfg(feature = "poseidon")]
+    pub fn finalize(self) -> [u8; 32] {
+        let mut state = [0u8; 32];
+        state[24..].copy_from_slice(&(self.message.len() as u64).to_be_bytes());
+
+        for block in self.message.chunks(POSEIDON_CHUNK_BYTES * POSEIDON_CHUNKS_PER_CALL) {
+            let mut inputs: Vec<&[u8]> = vec![&state];
+            inputs.extend(block.chunks(POSEIDON_CHUNK_BYTES));
+
+            let mut poseidon = Poseidon::<Fr>::new_circom(inputs.len()).expect("at most 12 inputs");
+            state = poseidon.hash_bytes_be(&inputs).expect("inputs are below the modulus");
+        }
+
+        state
+    }
+}
+
+/// Hasher with the domain prefix already absorbed
+/// hash = H(version || tag_length || tag || fields)
+/// The length prefix keeps a tag from running into the fields that follow it
+pub fn domain_hasher(domain: Domain) -> DomainHasher {
+    let tag = domain.tag();
+    let mut hasher = DomainHasher::new();
+
+    hasher.update([HASH_VERSION, tag.len() as u8]);
+    hasher.update(tag);
+
+    hasher
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOMAINS: [Domain; 3] = [Domain::PositionCommitment, Domain::BalanceCommitment, Domain::Nullifier];
+
+    fn hash(domain: Domain, fields: &[u8]) -> [u8; 32] {
+        let mut hasher = domain_hasher(domain);
+        hasher.update(fields);
+        hasher.finalize()
+    }
+
+    #[test]
+    fn test_domains_separate_identical_fields() {
+        let fields = [7u8; 116];
+        for (i, first) in DOMAINS.iter().enumerate() {
+            for second in &DOMAINS[i + 1..] {
+                assert_ne!(first.tag(), second.tag());
+                assert_ne!(hash(*first, &fields), hash(*second, &fields), "{:?} vs {:?}", first, second);
+            }
+        }
+    }
+
+    #[cfg(not(any(feature = "poseidon", feature = "keccak")))]
+    #[test]
+    fn test_prefix_is_versioned_and_length_prefixed() {
+        let tag = Domain::Nullifier.tag();
+        let mut expected = Sha256::new();
+        expected.update([HASH_VERSION, tag.len() as u8]);
+        expected.update(tag);
+        expected.update([1u8; 32]);
+
+        assert_eq!(hash(Domain::Nullifier, &[1u8; 32]), <[u8; 32]>::from(expected.finalize()));
+
+        // Without a version and length, a tag and the start of the fields could be re-split
+        let mut unprefixed = Sha256::new();
+        unprefixed.update(tag);
+        unprefixed.update([1u8; 32]);
+        assert_ne!(hash(Domain::Nullifier, &[1u8; 32]), <[u8; 32]>::from(unprefixed.finalize()));
+    }
+
+    #[test]
+    fn test_split_updates_match_single_update() {
+        let mut split = domain_hasher(Domain::PositionCommitment);
+        split.update([1u8; 32]);
+        split.update([2u8; 400]);
+
+        let whole = [[1u8; 32].as_slice(), &[2u8; 400]].concat();
+        assert_eq!(split.finalize(), hash(Domain::PositionCommitment, &whole));
+    }
+
+    #[cfg(feature = "poseidon")]
+    #[test]
+    fn test_poseidon_packing_unambiguous() {
+        // Fill the first chunk, so the last one is 0x01 or 0x0001: the same integer,
+        // told apart only by the message length
+        let fill = vec![7u8; POSEIDON_CHUNK_BYTES - 2 - Domain::Nullifier.tag().len()];
+        assert_ne!(
+            hash(Domain::Nullifier, &[fill.as_slice(), &[1]].concat()),
+            hash(Domain::Nullifier, &[fill.as_slice(), &[0, 1]].concat())
+        );
+
+        // Messages spanning several Poseidon calls still bind every byte
+        let mut long = [3u8; 700];
+        let reference = hash(Domain::Nullifier, &long);
+        long[699] = 4;
+        assert_ne!(hash(Domain::Nullifier, &long), reference);
+    }
+}
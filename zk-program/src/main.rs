@@ -1,151 +1,1476 @@
-#![no_main]
+#![cfg_attr(not(test), no_main)]
+// Tests run the handlers' verify functions on the host, under the test harness's main
+#[cfg(not(test))]
 sp1_zkvm::entrypoint!(main);
 
 mod types;
+mod blocklist;
+mod collateral;
 mod crypto;
+mod error;
+mod hashing;
 mod transfer;
+mod merkle;
+mod oracle;
+mod math;
+mod operation;
 
 use types::*;
+use blocklist::*;
+use collateral::*;
 use crypto::*;
+use error::*;
 use transfer::*;
+use merkle::*;
+use oracle::*;
+use math::*;
+use operation::*;
 
-const BPS_DENOMINATOR: u128 = 10_000;
+/// Decimals of oracle USD prices
+const PRICE_DECIMALS: u8 = 6;
 
 /// Main entry point for the ZK program
 /// Reads operation type and dispatches to appropriate handler
 pub fn main() {
-    // Read operation type: 0 = deposit, 1 = borrow, 2 = transfer
+    // Read operation type (codes in operation.rs)
     let operation_type: u8 = sp1_zkvm::io::read::<u8>();
 
     match operation_type {
-        0 => handle_deposit(),
-        1 => handle_borrow(),
-        2 => handle_transfer(),
+        OP_DEPOSIT => handle_deposit(),
+        OP_BORROW => handle_borrow(),
+        OP_TRANSFER => handle_transfer(),
+        OP_REPAY => handle_repay(),
+        OP_WITHDRAW => handle_withdraw(),
+        OP_LIQUIDATE => handle_liquidation(),
+        OP_JOIN_SPLIT => handle_join_split(),
+        OP_DISCLOSURE => handle_disclosure(),
+        OP_MIGRATION => handle_migration(),
+        OP_BATCH_DEPOSIT => handle_batch_deposit(),
+        OP_SHIELD => handle_shield(),
         _ => {
-            // Invalid operation, output failure
-            let output = DepositOutput {
-                commitment_hash: [0u8; 32],
-                is_valid: 0,
-            };
-            sp1_zkvm::io::commit(&output);
+            // Invalid operation: there is no output to fill, so commit only the reason
+            commit_output(operation_type, &ErrorCode::UnknownOperation, ErrorCode::UnknownOperation);
         }
     }
 }
 
-/// Handle transfer operation - verify balance and create transfer proof
+/// Commit an operation's public values: its operation code, then its output
+/// The code tells the vault which operation a proof is of, since all of them share one ELF.
+/// With the `strict` feature a rejected operation panics instead, so no proof of it can be
+/// generated and no proving time is spent on it
+fn commit_output<T: serde::Serialize>(operation: u8, output: &T, error_code: ErrorCode) {
+    if cfg!(feature = "strict") && !error_code.is_success() {
+        panic!("operation rejected: {}", error_code);
+    }
+    sp1_zkvm::io::commit(&operation);
+    sp1_zkvm::io::commit(output);
+}
+
+/// Handle shield operation - lock tokens in a balance note for shielded transfers
+fn handle_shield() {
+    let input = sp1_zkvm::io::read::<ShieldInput>();
+    let output = verify_shield(&input);
+    commit_output(OP_SHIELD, &output, output.error_code);
+}
+
+/// Handle transfer operation - spend a balance note into a recipient note and a change note
 fn handle_transfer() {
     let input = sp1_zkvm::io::read::<TransferInput>();
     let output = verify_transfer(&input);
-    sp1_zkvm::io::commit(&output);
+    commit_output(OP_TRANSFER, &output, output.error_code);
 }
 
 /// Handle deposit operation - create initial commitment
 fn handle_deposit() {
     let input = sp1_zkvm::io::read::<DepositInput>();
+    let output = verify_deposit(&input);
+    commit_output(OP_DEPOSIT, &output, output.error_code);
+}
+
+/// Check a deposit and generate its commitment
+fn verify_deposit(input: &DepositInput) -> DepositOutput {
+    // Screen the depositor against the blocklist, if a proof was given
+    let (blocklist_root, is_screened) = screen_address(&input.depositor_address, &input.blocklist);
 
     // Validate inputs
-    if input.collateral_amount == 0 {
-        let output = DepositOutput {
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(has_collateral(&input.collateral), ErrorCode::ZeroAmount)?;
+        ensure(is_well_formed(&input.collateral), ErrorCode::MalformedCollateral)?;
+        ensure(is_screened, ErrorCode::Blocklisted)
+    });
+    if !error_code.is_success() {
+        return DepositOutput {
             commitment_hash: [0u8; 32],
-            is_valid: 0,
+            collateral: input.collateral,
+            depositor_address: input.depositor_address,
+            blocklist_root,
+            error_code,
         };
-        sp1_zkvm::io::commit(&output);
-        return;
     }
 
     // Generate commitment hash for the deposit
-    // Commitment = hash(secret_key, collateral_amount, debt=0, salt)
+    // Commitment = hash(secret_key, collateral, debt=0, index=1.0, salt)
     let commitment_hash = hash_commitment(
         &input.user_secret_key,
-        input.collateral_amount,
-        0, // Initial deposit has zero debt
+        &input.collateral,
+        0,           // Initial deposit has zero debt
+        INDEX_SCALE, // so any starting index accrues nothing
         &input.note_salt,
     );
 
-    let output = DepositOutput {
+    // Expose the committed assets so the vault can pull exactly these amounts
+    // and the screened depositor so it can check that is the caller
+    DepositOutput {
         commitment_hash,
-        is_valid: 1,
-    };
+        collateral: input.collateral,
+        depositor_address: input.depositor_address,
+        blocklist_root,
+        error_code,
+    }
+}
+
+/// Handle batch deposit operation - create many commitments with one proof, so a relayer
+/// or institution onboards many notes for a single on-chain verification
+fn handle_batch_deposit() {
+    let input = sp1_zkvm::io::read::<BatchDepositInput>();
+    let output = verify_batch_deposit(&input);
+    commit_output(OP_BATCH_DEPOSIT, &output, output.error_code);
+}
+
+/// Check every deposit of a batch and generate their commitments
+fn verify_batch_deposit(input: &BatchDepositInput) -> BatchDepositOutput {
+    // Step 1: Screen every depositor and sum the collateral the vault pulls for the batch
+    let screenings: Vec<_> = input
+        .deposits
+        .iter()
+        .map(|deposit| screen_address(&deposit.depositor_address, &deposit.blocklist))
+        .collect();
+    let notes: Vec<_> = input.deposits.iter().map(|deposit| deposit.collateral).collect();
+    let total = total_collateral(&notes);
+
+    // One address funds the whole batch, so every deposit must name the first one's funder and root
+    let (depositor_address, blocklist_root) = input
+        .deposits
+        .first()
+        .zip(screenings.first())
+        .map_or(([0u8; 20], [0u8; 32]), |(deposit, (root, _))| (deposit.depositor_address, *root));
+
+    // Step 2: Validate every deposit as a single deposit would be
+    let error_code = ErrorCode::from_checks(|| {
+        ensure((1..=MAX_BATCH_DEPOSITS).contains(&input.deposits.len()), ErrorCode::InvalidBatchSize)?;
+        for (deposit, (root, is_screened)) in input.deposits.iter().zip(&screenings) {
+            ensure(has_collateral(&deposit.collateral), ErrorCode::ZeroAmount)?;
+            ensure(is_well_formed(&deposit.collateral), ErrorCode::MalformedCollateral)?;
+            ensure(*is_screened, ErrorCode::Blocklisted)?;
+            ensure(
+                deposit.depositor_address == depositor_address && *root == blocklist_root,
+                ErrorCode::BatchMismatch,
+            )?;
+        }
+        ensure(total.is_some(), ErrorCode::BatchMismatch)
+    });
+
+    // Step 3: Generate a commitment per deposit (none if any deposit is invalid)
+    let mut commitment_hashes = [[0u8; 32]; MAX_BATCH_DEPOSITS];
+    if error_code.is_success() {
+        for (commitment_hash, deposit) in commitment_hashes.iter_mut().zip(&input.deposits) {
+            *commitment_hash = hash_commitment(
+                &deposit.user_secret_key,
+                &deposit.collateral,
+                0,           // Like a single deposit: no debt
+                INDEX_SCALE, // at the starting index
+                &deposit.note_salt,
+            );
+        }
+    }
 
-    sp1_zkvm::io::commit(&output);
+    // Step 4: Create output
+    BatchDepositOutput {
+        commitment_hashes,
+        deposit_count: if error_code.is_success() { input.deposits.len() as u8 } else { 0 },
+        total_collateral: total.unwrap_or_default(),
+        depositor_address,
+        blocklist_root,
+        error_code,
+    }
 }
 
-/// Handle borrow operation - prove LTV is safe and generate new commitment
+/// Handle borrow operation - prove the old note exists, LTV is safe and generate new commitment
 fn handle_borrow() {
     let input = sp1_zkvm::io::read::<BorrowInput>();
+    let output = verify_borrow(&input);
+    commit_output(OP_BORROW, &output, output.error_code);
+}
+
+/// Check a borrow against the old note and generate the new note's commitment
+fn verify_borrow(input: &BorrowInput) -> BorrowOutput {
+    // Step 1: Recompute the old note's commitment and prove it is in the tree
+    let old_commitment_hash = hash_commitment(
+        &input.user_secret_key,
+        &input.collateral,
+        input.existing_debt,
+        input.note_borrow_index,
+        &input.old_note_salt,
+    );
+    let is_member = verify_merkle_path(
+        &old_commitment_hash,
+        input.leaf_index,
+        &input.merkle_path,
+        &input.merkle_root,
+    );
+
+    // Step 2: Generate nullifier for old note (marks it as spent)
+    let nullifier_key = derive_nullifier_key(&input.user_secret_key);
+    let nullifier_hash = hash_nullifier(&nullifier_key, &old_commitment_hash, input.leaf_index);
+
+    // Step 3: Verify every listed market's price was signed by the same oracle
+    let priced = verify_signed_markets(&input.prices, &input.ltv_weights_bps, &input.collateral_decimals);
+    let markets = priced.markets;
 
-    // Step 1: Generate nullifier for old note (marks it as spent)
-    let nullifier_hash = hash_nullifier(&input.user_secret_key, &input.old_note_salt);
+    // Step 4: Accrue interest on the old debt up to the current index, then add the new borrow
+    let new_total_debt =
+        accrue_debt(input.existing_debt, input.note_borrow_index, input.borrow_index)
+            .and_then(|debt| debt.checked_add(input.new_borrow_amount).ok_or(MathError::Overflow));
 
-    // Step 2: Calculate new total debt
-    let new_total_debt = input.existing_debt.saturating_add(input.new_borrow_amount);
+    // Step 5: Screen the recipient against the blocklist, if a proof was given
+    let (blocklist_root, is_screened) = screen_address(&input.recipient_address, &input.blocklist);
 
-    // Step 3: Verify LTV ratio is safe
-    let is_ltv_safe = check_ltv(
-        input.collateral_amount,
-        input.collateral_price_usd,
+    // Step 6: Old note exists, prices are signed and the LTV is safe across all listed
+    // collateral (overflow counts as unsafe); the first failed check is the error code
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(is_member, ErrorCode::MembershipFailed)?;
+        ensure(priced.are_signed, ErrorCode::InvalidPriceSignature)?;
+        ensure(is_collateral_listed(&input.collateral, &markets), ErrorCode::UnlistedCollateral)?;
+        let is_ltv_safe = check_ltv(&input.collateral, &markets, new_total_debt?, input.debt_decimals)?;
+        ensure(is_ltv_safe, ErrorCode::LtvExceeded)?;
+        ensure(is_screened, ErrorCode::Blocklisted)
+    });
+    let new_total_debt = new_total_debt.unwrap_or(0);
+
+    // Step 7: Generate new commitment with updated debt, recorded at the current index
+    let new_commitment_hash = hash_commitment(
+        &input.user_secret_key,
+        &input.collateral,
         new_total_debt,
-        input.max_ltv_bps,
+        input.borrow_index,
+        &input.new_note_salt,
+    );
+
+    // Step 8: Create output
+    BorrowOutput {
+        nullifier_hash,
+        new_commitment_hash,
+        recipient_address: input.recipient_address,
+        borrow_amount: input.new_borrow_amount,
+        merkle_root: input.merkle_root,
+        oracle_key_hash: priced.oracle_key_hash,
+        price_timestamp: priced.price_timestamp,
+        collateral_assets: markets.map(|market| market.asset),
+        ltv_weights_bps: input.ltv_weights_bps,
+        collateral_decimals: input.collateral_decimals,
+        debt_decimals: input.debt_decimals,
+        borrow_index: input.borrow_index,
+        blocklist_root,
+        error_code,
+    }
+}
+
+/// Handle repay operation - spend the old note and commit a new one with reduced debt
+fn handle_repay() {
+    let input = sp1_zkvm::io::read::<RepayInput>();
+    let output = verify_repay(&input);
+    commit_output(OP_REPAY, &output, output.error_code);
+}
+
+/// Check a repayment against the old note and generate the new note's commitment
+fn verify_repay(input: &RepayInput) -> RepayOutput {
+    // Step 1: Recompute the old note's commitment and prove it is in the tree
+    let old_commitment_hash = hash_commitment(
+        &input.user_secret_key,
+        &input.collateral,
+        input.existing_debt,
+        input.note_borrow_index,
+        &input.old_note_salt,
     );
+    let is_member = verify_merkle_path(
+        &old_commitment_hash,
+        input.leaf_index,
+        &input.merkle_path,
+        &input.merkle_root,
+    );
+
+    // Step 2: Generate nullifier for old note (marks it as spent)
+    let nullifier_key = derive_nullifier_key(&input.user_secret_key);
+    let nullifier_hash = hash_nullifier(&nullifier_key, &old_commitment_hash, input.leaf_index);
 
-    // Step 4: Generate new commitment with updated debt
+    // Step 3: Accrue interest, then repayment must be non-zero and can't exceed the debt
+    let current_debt = accrue_debt(input.existing_debt, input.note_borrow_index, input.borrow_index);
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(is_member, ErrorCode::MembershipFailed)?;
+        let debt = current_debt?;
+        ensure(input.repay_amount > 0, ErrorCode::ZeroAmount)?;
+        ensure(input.repay_amount <= debt, ErrorCode::InsufficientBalance)
+    });
+    let remaining_debt = current_debt.unwrap_or(0).saturating_sub(input.repay_amount);
+
+    // Step 4: Generate new commitment with reduced debt, recorded at the current index
     let new_commitment_hash = hash_commitment(
         &input.user_secret_key,
-        input.collateral_amount,
-        new_total_debt,
+        &input.collateral,
+        remaining_debt,
+        input.borrow_index,
         &input.new_note_salt,
     );
 
     // Step 5: Create output
-    let output = BorrowOutput {
+    RepayOutput {
+        nullifier_hash,
+        new_commitment_hash,
+        repay_amount: input.repay_amount,
+        merkle_root: input.merkle_root,
+        borrow_index: input.borrow_index,
+        error_code,
+    }
+}
+
+/// Handle withdraw operation - release collateral and prove the remaining position is safe
+fn handle_withdraw() {
+    let input = sp1_zkvm::io::read::<WithdrawInput>();
+    let output = verify_withdraw(&input);
+    commit_output(OP_WITHDRAW, &output, output.error_code);
+}
+
+/// Check a withdrawal against the old note and generate the new note's commitment
+fn verify_withdraw(input: &WithdrawInput) -> WithdrawOutput {
+    // Step 1: Recompute the old note's commitment and prove it is in the tree
+    let old_commitment_hash = hash_commitment(
+        &input.user_secret_key,
+        &input.collateral,
+        input.existing_debt,
+        input.note_borrow_index,
+        &input.old_note_salt,
+    );
+    let is_member = verify_merkle_path(
+        &old_commitment_hash,
+        input.leaf_index,
+        &input.merkle_path,
+        &input.merkle_root,
+    );
+
+    // Step 2: Generate nullifier for old note (marks it as spent)
+    let nullifier_key = derive_nullifier_key(&input.user_secret_key);
+    let nullifier_hash = hash_nullifier(&nullifier_key, &old_commitment_hash, input.leaf_index);
+
+    // Step 3: Take the withdrawal out of the note's balance of that asset
+    let slot = input
+        .collateral
+        .iter()
+        .position(|position| position.asset != [0u8; 20] && position.asset == input.withdraw_asset);
+    let balance = slot.map_or(0, |slot| input.collateral[slot].amount);
+    let mut remaining_collateral = input.collateral;
+    if let Some(slot) = slot {
+        let remaining = &mut remaining_collateral[slot];
+        remaining.amount = remaining.amount.saturating_sub(input.withdraw_amount);
+        if remaining.amount == 0 {
+            // Fully withdrawn assets free their slot
+            *remaining = CollateralPosition::default();
+        }
+    }
+
+    // Step 4: Value the remaining collateral at oracle-signed prices
+    // The debt is hidden, so the vault can't tell which withdrawals needed the prices:
+    // every withdrawal must carry signed ones
+    let priced = verify_signed_markets(&input.prices, &input.ltv_weights_bps, &input.collateral_decimals);
+
+    // Step 5: Withdrawal must be non-zero and can't exceed the balance, and the remaining
+    // collateral must still cover the debt with interest (debt-free notes can fully exit)
+    let current_debt = accrue_debt(input.existing_debt, input.note_borrow_index, input.borrow_index);
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(is_member, ErrorCode::MembershipFailed)?;
+        ensure(priced.are_signed, ErrorCode::InvalidPriceSignature)?;
+        ensure(input.withdraw_amount > 0, ErrorCode::ZeroAmount)?;
+        ensure(input.withdraw_amount <= balance, ErrorCode::InsufficientBalance)?;
+        let debt = current_debt?;
+        let is_ltv_safe = debt == 0
+            || check_ltv(&remaining_collateral, &priced.markets, debt, input.debt_decimals)?;
+        ensure(is_ltv_safe, ErrorCode::LtvExceeded)
+    });
+
+    // Step 6: Generate new commitment with remaining collateral, recorded at the current index
+    let new_commitment_hash = hash_commitment(
+        &input.user_secret_key,
+        &remaining_collateral,
+        current_debt.unwrap_or(0),
+        input.borrow_index,
+        &input.new_note_salt,
+    );
+
+    // Step 7: Create output
+    WithdrawOutput {
         nullifier_hash,
         new_commitment_hash,
         recipient_address: input.recipient_address,
-        borrow_amount: input.new_borrow_amount,
-        is_valid: if is_ltv_safe { 1 } else { 0 },
+        withdraw_asset: input.withdraw_asset,
+        withdraw_amount: input.withdraw_amount,
+        merkle_root: input.merkle_root,
+        oracle_key_hash: priced.oracle_key_hash,
+        price_timestamp: priced.price_timestamp,
+        markets: priced.markets,
+        debt_decimals: input.debt_decimals,
+        borrow_index: input.borrow_index,
+        error_code,
+    }
+}
+
+/// Handle liquidation operation - prove a note is under-collateralised and close it
+fn handle_liquidation() {
+    let input = sp1_zkvm::io::read::<LiquidationInput>();
+    let output = verify_liquidation(&input);
+    commit_output(OP_LIQUIDATE, &output, output.error_code);
+}
+
+/// Check a note is liquidatable and generate its nullifier
+fn verify_liquidation(input: &LiquidationInput) -> LiquidationOutput {
+    // Step 1: Recompute the note's commitment and prove it is in the tree
+    let commitment_hash = hash_commitment(
+        &input.user_secret_key,
+        &input.collateral,
+        input.debt_amount,
+        input.note_borrow_index,
+        &input.note_salt,
+    );
+    let is_member = verify_merkle_path(
+        &commitment_hash,
+        input.leaf_index,
+        &input.merkle_path,
+        &input.merkle_root,
+    );
+
+    // Step 2: Generate nullifier for the note (it can't be spent again)
+    let nullifier_key = derive_nullifier_key(&input.user_secret_key);
+    let nullifier_hash = hash_nullifier(&nullifier_key, &commitment_hash, input.leaf_index);
+
+    // Step 3: Accrue interest up to the current index
+    let current_debt = accrue_debt(input.debt_amount, input.note_borrow_index, input.borrow_index);
+    let debt_to_repay = current_debt.unwrap_or(0);
+
+    // Step 4: Value the note at oracle-signed prices, so a keeper can't make it look
+    // unhealthy with a price of their own
+    let priced = verify_signed_markets(&input.prices, &input.liquidation_thresholds_bps, &input.collateral_decimals);
+    let markets = priced.markets;
+
+    // Step 5: Reject inputs that would make any note look unhealthy
+    // (every held asset needs a priced market with a threshold in (0, 100%])
+    let are_markets_valid =
+        markets.iter().filter(|market| market.asset != [0u8; 20]).all(|market| {
+            market.price_usd > 0
+                && market.weight_bps > 0
+                && market.weight_bps as u128 <= BPS_DENOMINATOR
+        });
+
+    // Step 6: Note is liquidatable when its debt exceeds the threshold-weighted collateral
+    // (an arithmetic failure must not make a healthy note liquidatable)
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(is_member, ErrorCode::MembershipFailed)?;
+        ensure(priced.are_signed, ErrorCode::InvalidPriceSignature)?;
+        let debt = current_debt?;
+        ensure(debt > 0, ErrorCode::PositionHealthy)?;
+        ensure(is_collateral_listed(&input.collateral, &markets), ErrorCode::UnlistedCollateral)?;
+        ensure(are_markets_valid, ErrorCode::InvalidMarket)?;
+        let is_healthy = check_ltv(&input.collateral, &markets, debt, input.debt_decimals)?;
+        ensure(!is_healthy, ErrorCode::PositionHealthy)
+    });
+
+    // Step 7: Create output - the whole position is closed
+    LiquidationOutput {
+        nullifier_hash,
+        seized_collateral: input.collateral,
+        debt_to_repay,
+        markets,
+        debt_decimals: input.debt_decimals,
+        oracle_key_hash: priced.oracle_key_hash,
+        price_timestamp: priced.price_timestamp,
+        recipient_address: input.recipient_address,
+        merkle_root: input.merkle_root,
+        borrow_index: input.borrow_index,
+        error_code,
+    }
+}
+
+/// Handle join-split operation - spend up to two notes and create up to two with the same
+/// total collateral and debt (merges dust deposits, splits off part of a position)
+fn handle_join_split() {
+    let input = sp1_zkvm::io::read::<JoinSplitInput>();
+    let output = verify_join_split(&input);
+    commit_output(OP_JOIN_SPLIT, &output, output.error_code);
+}
+
+/// Check a join-split conserves its notes and generate the new commitments
+fn verify_join_split(input: &JoinSplitInput) -> JoinSplitOutput {
+    // Step 1: Both sides use one or two notes
+    let spent_count = input.spent_count as usize;
+    let created_count = input.created_count as usize;
+    let are_counts_valid = (1..=MAX_SPLIT_NOTES).contains(&spent_count)
+        && (1..=MAX_SPLIT_NOTES).contains(&created_count);
+    let spent_notes = &input.spent_notes[..spent_count.min(MAX_SPLIT_NOTES)];
+    let created_notes = &input.created_notes[..created_count.min(MAX_SPLIT_NOTES)];
+
+    // Step 2: Prove every spent note is in the tree, nullify it and accrue its debt
+    let nullifier_key = derive_nullifier_key(&input.user_secret_key);
+    let mut nullifier_hashes = [[0u8; 32]; MAX_SPLIT_NOTES];
+    let mut are_members = true;
+    let mut spent_debt = Ok(0u128);
+    for (i, note) in spent_notes.iter().enumerate() {
+        let commitment_hash = hash_commitment(
+            &input.user_secret_key,
+            &note.collateral,
+            note.debt_amount,
+            note.note_borrow_index,
+            &note.salt,
+        );
+        are_members &= verify_merkle_path(
+            &commitment_hash,
+            note.leaf_index,
+            &note.merkle_path,
+            &input.merkle_root,
+        );
+        nullifier_hashes[i] = hash_nullifier(&nullifier_key, &commitment_hash, note.leaf_index);
+        spent_debt = spent_debt.and_then(|total| {
+            accrue_debt(note.debt_amount, note.note_borrow_index, input.borrow_index)
+                .and_then(|debt| total.checked_add(debt).ok_or(MathError::Overflow))
+        });
+    }
+
+    // A note can't be spent twice in the same proof
+    let are_spent_distinct = spent_count < 2 || nullifier_hashes[0] != nullifier_hashes[1];
+
+    // Step 3: Value new notes at oracle-signed prices; which notes carry debt is hidden,
+    // so every join-split carries signed prices whether or not it needs them
+    let priced = verify_signed_markets(&input.prices, &input.ltv_weights_bps, &input.collateral_decimals);
+    let markets = priced.markets;
+
+    // Step 4: Every new note holds collateral, and any debt it carries is within LTV
+    let mut new_commitment_hashes = [[0u8; 32]; MAX_SPLIT_NOTES];
+    let mut created_checks = Ok(());
+    let mut created_debt = Some(0u128);
+    for (i, note) in created_notes.iter().enumerate() {
+        created_checks = created_checks.and_then(|()| {
+            ensure(has_collateral(&note.collateral), ErrorCode::ZeroAmount)?;
+            ensure(is_well_formed(&note.collateral), ErrorCode::MalformedCollateral)?;
+            let is_ltv_safe = note.debt_amount == 0
+                || check_ltv(&note.collateral, &markets, note.debt_amount, input.debt_decimals)?;
+            ensure(is_ltv_safe, ErrorCode::LtvExceeded)
+        });
+        created_debt = created_debt.and_then(|total| total.checked_add(note.debt_amount));
+        new_commitment_hashes[i] = hash_commitment(
+            &input.user_secret_key,
+            &note.collateral,
+            note.debt_amount,
+            input.borrow_index,
+            &note.salt,
+        );
+    }
+
+    // Step 5: Collateral of each asset and debt with interest are conserved exactly
+    let spent_collateral: Vec<_> = spent_notes.iter().map(|note| note.collateral).collect();
+    let created_collateral: Vec<_> = created_notes.iter().map(|note| note.collateral).collect();
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(are_counts_valid, ErrorCode::InvalidNoteCount)?;
+        ensure(are_members, ErrorCode::MembershipFailed)?;
+        ensure(are_spent_distinct, ErrorCode::DuplicateNote)?;
+        ensure(priced.are_signed, ErrorCode::InvalidPriceSignature)?;
+        created_checks?;
+        let spent_debt = spent_debt?;
+        let created_debt = created_debt.ok_or(ErrorCode::Overflow)?;
+        ensure(
+            is_collateral_conserved(&spent_collateral, &created_collateral) && created_debt == spent_debt,
+            ErrorCode::NotConserved,
+        )
+    });
+
+    // Step 6: Create output
+    JoinSplitOutput {
+        nullifier_hashes,
+        new_commitment_hashes,
+        markets,
+        debt_decimals: input.debt_decimals,
+        oracle_key_hash: priced.oracle_key_hash,
+        price_timestamp: priced.price_timestamp,
+        merkle_root: input.merkle_root,
+        borrow_index: input.borrow_index,
+        error_code,
+    }
+}
+
+/// Handle disclosure operation - prove a statement about a note to an auditor
+/// The note is not spent and no nullifier is revealed, so the proof can't be linked to it
+fn handle_disclosure() {
+    let input = sp1_zkvm::io::read::<DisclosureInput>();
+    let output = verify_disclosure(&input);
+    commit_output(OP_DISCLOSURE, &output, output.error_code);
+}
+
+/// Check a statement about a note without spending it
+fn verify_disclosure(input: &DisclosureInput) -> DisclosureOutput {
+    // Step 1: Recompute the note's commitment and prove it is in the tree
+    let commitment_hash = hash_commitment(
+        &input.user_secret_key,
+        &input.collateral,
+        input.debt_amount,
+        input.note_borrow_index,
+        &input.note_salt,
+    );
+    let is_member = verify_merkle_path(
+        &commitment_hash,
+        input.leaf_index,
+        &input.merkle_path,
+        &input.merkle_root,
+    );
+
+    // Step 2: Check the statement against the hidden note
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(is_member, ErrorCode::MembershipFailed)?;
+        let is_statement_true = match input.statement {
+            DisclosureStatement::MinCollateral { asset, min_amount } => {
+                ensure(min_amount > 0, ErrorCode::ZeroAmount)?;
+                // A committed note holds each asset in at most one slot
+                input
+                    .collateral
+                    .iter()
+                    .any(|position| position.asset == asset && position.amount >= min_amount)
+            }
+            DisclosureStatement::MaxLtv { markets, max_ltv_bps, debt_decimals, borrow_index } => {
+                // Assets without a market count as zero, which only makes the LTV look worse
+                let markets = markets.map(|market| CollateralMarket { weight_bps: max_ltv_bps, ..market });
+                let debt = accrue_debt(input.debt_amount, input.note_borrow_index, borrow_index)?;
+                check_ltv(&input.collateral, &markets, debt, debt_decimals)?
+            }
+        };
+        ensure(is_statement_true, ErrorCode::StatementFalse)
+    });
+
+    // Step 3: Create output - only the statement, the holder and the root are public
+    DisclosureOutput {
+        statement: input.statement,
+        owner_key: derive_owner_key(&input.user_secret_key),
+        challenge: input.challenge,
+        merkle_root: input.merkle_root,
+        error_code,
+    }
+}
+
+/// Handle migration operation - spend a note committed with the version 0 hash layout into
+/// a note with the same contents under the current layout
+fn handle_migration() {
+    let input = sp1_zkvm::io::read::<MigrationInput>();
+    let output = verify_migration(&input);
+    commit_output(OP_MIGRATION, &output, output.error_code);
+}
+
+/// Check a legacy note exists and generate its migrated commitment
+fn verify_migration(input: &MigrationInput) -> MigrationOutput {
+    // Step 1: Recompute the legacy commitment and the migrated note's commitment
+    let (legacy_commitment_hash, new_commitment_hash) = match input.note {
+        LegacyNote::Position { collateral, debt_amount, borrow_index } => {
+            let secret_key = &input.user_secret_key;
+            (
+                hash_commitment_v0(secret_key, &collateral, debt_amount, borrow_index, &input.note_salt),
+                hash_commitment(secret_key, &collateral, debt_amount, borrow_index, &input.new_note_salt),
+            )
+        }
+        LegacyNote::Balance { token, amount, shared_secret } => {
+            let owner_key = derive_stealth_owner_key(&derive_owner_key(&input.user_secret_key), &shared_secret);
+            (
+                hash_balance_commitment_v0(&owner_key, &token, amount, &input.note_salt),
+                hash_balance_commitment(&owner_key, &token, amount, &input.new_note_salt),
+            )
+        }
     };
 
-    sp1_zkvm::io::commit(&output);
+    // Step 2: Prove the legacy note is in the tree
+    let is_member = verify_merkle_path(
+        &legacy_commitment_hash,
+        input.leaf_index,
+        &input.merkle_path,
+        &input.merkle_root,
+    );
+    let error_code = ErrorCode::from_checks(|| ensure(is_member, ErrorCode::MembershipFailed));
+
+    // Step 3: Generate the legacy nullifier, the one a version 0 spend of the note would reveal
+    let nullifier_key = derive_nullifier_key(&input.user_secret_key);
+    let nullifier_hash = hash_nullifier_v0(&nullifier_key, &legacy_commitment_hash, input.leaf_index);
+
+    // Step 4: Create output
+    MigrationOutput {
+        nullifier_hash,
+        new_commitment_hash,
+        merkle_root: input.merkle_root,
+        error_code,
+    }
+}
+
+/// Collateral markets valued at oracle-signed prices
+struct SignedMarkets {
+    /// Price, weight and decimals of each market (unused slots have a zero asset)
+    markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+    /// Whether at least one market is listed and every listed price was signed by the same oracle
+    are_signed: bool,
+    /// Hash of the oracle key that signed the prices (the vault checks it is trusted)
+    oracle_key_hash: [u8; 32],
+    /// Time the oldest price was signed (the vault checks freshness)
+    price_timestamp: u64,
 }
 
-/// Check if the LTV (Loan-to-Value) ratio is safe
-/// Returns true if debt is within acceptable limits
+/// Build each market from its signed price, weight and decimals, and verify the signatures
+fn verify_signed_markets(
+    prices: &[SignedPrice; MAX_COLLATERAL_ASSETS],
+    weights_bps: &[u16; MAX_COLLATERAL_ASSETS],
+    decimals: &[u8; MAX_COLLATERAL_ASSETS],
+) -> SignedMarkets {
+    let oracle_public_key = &prices[0].oracle_public_key;
+    let are_signed = prices[0].asset != [0u8; 20]
+        && prices.iter().filter(|price| price.asset != [0u8; 20]).all(|price| {
+            price.oracle_public_key == *oracle_public_key
+                && verify_price_signature(
+                    price.price_usd,
+                    price.timestamp,
+                    &price.asset,
+                    &price.oracle_public_key,
+                    &price.signature,
+                )
+        });
+
+    // The vault checks freshness of the oldest price
+    let price_timestamp = prices
+        .iter()
+        .filter(|price| price.asset != [0u8; 20])
+        .map(|price| price.timestamp)
+        .min()
+        .unwrap_or(0);
+
+    SignedMarkets {
+        markets: core::array::from_fn(|i| CollateralMarket {
+            asset: prices[i].asset,
+            price_usd: prices[i].price_usd,
+            weight_bps: weights_bps[i],
+            decimals: decimals[i],
+        }),
+        are_signed,
+        oracle_key_hash: hash_oracle_key(oracle_public_key),
+        price_timestamp,
+    }
+}
+
+/// Check if the LTV (Loan-to-Value) ratio is safe across all of a note's collateral
+/// Each held asset is valued at its market's price and weighted by its market's LTV;
+/// assets without a market count as zero
+/// Returns Ok(true) if debt is within acceptable limits, or an error if the math overflows
 fn check_ltv(
-    collateral_amount: u128,
-    collateral_price_usd: u128,
+    collateral: &[CollateralPosition; MAX_COLLATERAL_ASSETS],
+    markets: &[CollateralMarket; MAX_COLLATERAL_ASSETS],
     total_debt: u128,
-    max_ltv_bps: u16,
-) -> bool {
+    debt_decimals: u8,
+) -> Result<bool, MathError> {
     // Validate inputs
-    if collateral_amount == 0 || collateral_price_usd == 0 {
-        return false;
-    }
-
-    if max_ltv_bps as u128 > BPS_DENOMINATOR {
-        return false;
-    }
-
-    // To avoid overflow, we rearrange the formula:
-    // Instead of: debt <= (collateral * price * max_ltv) / 10000
-    // We use: debt * 10000 <= collateral * price * max_ltv
-    
-    // Note: collateral_amount is in wei (18 decimals)
-    // collateral_price_usd is in 6 decimals
-    // total_debt is in 6 decimals
-    // We need to normalize: divide collateral by 1e18 to get ETH, multiply by price
-    // Final collateral_value and debt should be in same units (USD with 6 decimals)
-    
-    // Normalize collateral to ETH (remove 18 decimals)
-    let collateral_eth = collateral_amount / 1_000_000_000_000_000_000;
-    
-    // Calculate collateral value in USD (both in 6 decimals now)
-    let collateral_value_usd = collateral_eth.saturating_mul(collateral_price_usd);
-    
-    // Calculate max allowed debt
-    let max_allowed_debt = collateral_value_usd
-        .saturating_mul(max_ltv_bps as u128)
-        .saturating_div(BPS_DENOMINATOR);
-
-    // Check if debt is within limits
-    total_debt <= max_allowed_debt
+    if !has_collateral(collateral) {
+        return Ok(false);
+    }
+
+    let mut weighted = Vec::with_capacity(MAX_COLLATERAL_ASSETS);
+    for position in collateral.iter().filter(|position| position.amount > 0) {
+        let Some(market) = find_market(markets, &position.asset) else {
+            continue;
+        };
+
+        if market.weight_bps as u128 > BPS_DENOMINATOR {
+            return Ok(false);
+        }
+
+        weighted.push(WeightedCollateral {
+            amount: position.amount,
+            price: market.price_usd,
+            weight_bps: market.weight_bps,
+            decimals: market.decimals,
+        });
+    }
+
+    // Exact 256-bit comparison: fractional collateral counts and nothing saturates
+    is_within_weighted_ltv(&weighted, total_debt, PRICE_DECIMALS, debt_decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+    const SECRET: [u8; 32] = [1u8; 32];
+    const METH: [u8; 20] = [0xEE; 20];
+    const WMNT: [u8; 20] = [0xDD; 20];
+    const DEPOSITOR: [u8; 20] = [0x12; 20];
+    const BLOCKED: [u8; 20] = [0xBA; 20];
+    const OLD_SALT: [u8; 32] = [2u8; 32];
+    const NEW_SALT: [u8; 32] = [3u8; 32];
+
+    /// One whole 18-decimal collateral token and one whole 6-decimal debt token
+    const ETH: u128 = 1_000_000_000_000_000_000;
+    const USDC: u128 = 1_000_000;
+
+    /// ETH at $2500 with 6 price decimals
+    const PRICE: u128 = 2500 * USDC;
+
+    fn oracle_key() -> SigningKey {
+        SigningKey::from_slice(&[7u8; 32]).unwrap()
+    }
+
+    fn sign_price(key: &SigningKey, asset: [u8; 20], price_usd: u128) -> SignedPrice {
+        let signature: Signature = key.sign(&price_message(price_usd, 1_700_000_000, &asset));
+        SignedPrice {
+            price_usd,
+            timestamp: 1_700_000_000,
+            asset,
+            oracle_public_key: key.verifying_key().to_sec1_bytes().to_vec(),
+            signature: signature.to_bytes().to_vec(),
+        }
+    }
+
+    /// Oracle-signed METH price; the second market is unused
+    fn signed_prices(price_usd: u128) -> [SignedPrice; MAX_COLLATERAL_ASSETS] {
+        [sign_price(&oracle_key(), METH, price_usd), SignedPrice::default()]
+    }
+
+    /// METH market valued at `price_usd` with weight `weight_bps`; the second market is unused
+    fn markets(price_usd: u128, weight_bps: u16) -> [CollateralMarket; MAX_COLLATERAL_ASSETS] {
+        [
+            CollateralMarket { asset: METH, price_usd, weight_bps, decimals: 18 },
+            CollateralMarket::default(),
+        ]
+    }
+
+    fn two_assets(meth: u128, wmnt: u128) -> [CollateralPosition; MAX_COLLATERAL_ASSETS] {
+        [
+            CollateralPosition { asset: METH, amount: meth },
+            CollateralPosition { asset: WMNT, amount: wmnt },
+        ]
+    }
+
+    /// Tree holding `commitments` in order
+    fn tree_of(commitments: &[[u8; 32]]) -> IncrementalMerkleTree {
+        let mut tree = IncrementalMerkleTree::new();
+        for commitment in commitments {
+            tree.insert(*commitment).unwrap();
+        }
+        tree
+    }
+
+    /// Commitment of SECRET's note under OLD_SALT, recorded at the starting index
+    fn old_note(collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS], debt: u128) -> [u8; 32] {
+        hash_commitment(&SECRET, &collateral, debt, INDEX_SCALE, &OLD_SALT)
+    }
+
+    fn nullifier(commitment: &[u8; 32], leaf_index: u64) -> [u8; 32] {
+        hash_nullifier(&derive_nullifier_key(&SECRET), commitment, leaf_index)
+    }
+
+    fn deposit(amount: u128) -> DepositInput {
+        DepositInput {
+            user_secret_key: SECRET,
+            collateral: single_asset(METH, amount),
+            note_salt: OLD_SALT,
+            depositor_address: DEPOSITOR,
+            blocklist: None,
+        }
+    }
+
+    fn borrow(collateral: u128, debt: u128, amount: u128) -> BorrowInput {
+        let collateral = single_asset(METH, collateral);
+        let tree = tree_of(&[old_note(collateral, debt)]);
+        BorrowInput {
+            user_secret_key: SECRET,
+            collateral,
+            prices: signed_prices(PRICE),
+            existing_debt: debt,
+            note_borrow_index: INDEX_SCALE,
+            borrow_index: INDEX_SCALE,
+            new_borrow_amount: amount,
+            ltv_weights_bps: [7500, 0],
+            collateral_decimals: [18, 0],
+            debt_decimals: 6,
+            old_note_salt: OLD_SALT,
+            new_note_salt: NEW_SALT,
+            recipient_address: DEPOSITOR,
+            merkle_root: tree.root(),
+            merkle_path: tree.path(0).unwrap(),
+            leaf_index: 0,
+            blocklist: None,
+        }
+    }
+
+    fn repay(debt: u128, amount: u128) -> RepayInput {
+        let collateral = single_asset(METH, 10 * ETH);
+        let tree = tree_of(&[old_note(collateral, debt)]);
+        RepayInput {
+            user_secret_key: SECRET,
+            collateral,
+            existing_debt: debt,
+            note_borrow_index: INDEX_SCALE,
+            borrow_index: INDEX_SCALE,
+            repay_amount: amount,
+            old_note_salt: OLD_SALT,
+            new_note_salt: NEW_SALT,
+            merkle_root: tree.root(),
+            merkle_path: tree.path(0).unwrap(),
+            leaf_index: 0,
+        }
+    }
+
+    fn withdraw(collateral: u128, debt: u128, amount: u128) -> WithdrawInput {
+        let collateral = single_asset(METH, collateral);
+        let tree = tree_of(&[old_note(collateral, debt)]);
+        WithdrawInput {
+            user_secret_key: SECRET,
+            collateral,
+            existing_debt: debt,
+            note_borrow_index: INDEX_SCALE,
+            borrow_index: INDEX_SCALE,
+            withdraw_asset: METH,
+            withdraw_amount: amount,
+            prices: signed_prices(PRICE),
+            ltv_weights_bps: [7500, 0],
+            collateral_decimals: [18, 0],
+            debt_decimals: 6,
+            old_note_salt: OLD_SALT,
+            new_note_salt: NEW_SALT,
+            recipient_address: DEPOSITOR,
+            merkle_root: tree.root(),
+            merkle_path: tree.path(0).unwrap(),
+            leaf_index: 0,
+        }
+    }
+
+    fn liquidation(debt: u128, price_usd: u128) -> LiquidationInput {
+        let collateral = single_asset(METH, 10 * ETH);
+        let tree = tree_of(&[old_note(collateral, debt)]);
+        LiquidationInput {
+            user_secret_key: SECRET,
+            collateral,
+            debt_amount: debt,
+            note_borrow_index: INDEX_SCALE,
+            borrow_index: INDEX_SCALE,
+            note_salt: OLD_SALT,
+            prices: signed_prices(price_usd),
+            liquidation_thresholds_bps: [8000, 0],
+            collateral_decimals: [18, 0],
+            debt_decimals: 6,
+            recipient_address: DEPOSITOR,
+            merkle_root: tree.root(),
+            merkle_path: tree.path(0).unwrap(),
+            leaf_index: 0,
+        }
+    }
+
+    /// Merge a 4 ETH note and a 6 ETH note with 5,000 USDC debt into 3 ETH and 7 ETH notes,
+    /// the second carrying the debt
+    fn join_split() -> JoinSplitInput {
+        let spent = [(single_asset(METH, 4 * ETH), 0), (single_asset(METH, 6 * ETH), 5000 * USDC)];
+        let commitments = spent.map(|(collateral, debt)| old_note(collateral, debt));
+        let tree = tree_of(&commitments);
+        let spent_notes = core::array::from_fn(|i| SpentNote {
+            collateral: spent[i].0,
+            debt_amount: spent[i].1,
+            note_borrow_index: INDEX_SCALE,
+            salt: OLD_SALT,
+            merkle_path: tree.path(i as u64).unwrap(),
+            leaf_index: i as u64,
+        });
+        let created_notes = [
+            CreatedNote { collateral: single_asset(METH, 3 * ETH), debt_amount: 0, salt: NEW_SALT },
+            CreatedNote { collateral: single_asset(METH, 7 * ETH), debt_amount: 5000 * USDC, salt: [4u8; 32] },
+        ];
+
+        JoinSplitInput {
+            user_secret_key: SECRET,
+            spent_count: 2,
+            spent_notes,
+            created_count: 2,
+            created_notes,
+            borrow_index: INDEX_SCALE,
+            prices: signed_prices(PRICE),
+            ltv_weights_bps: [7500, 0],
+            collateral_decimals: [18, 0],
+            debt_decimals: 6,
+            merkle_root: tree.root(),
+        }
+    }
+
+    fn disclosure(statement: DisclosureStatement) -> DisclosureInput {
+        let collateral = single_asset(METH, 10 * ETH);
+        let tree = tree_of(&[old_note(collateral, 5000 * USDC)]);
+        DisclosureInput {
+            user_secret_key: SECRET,
+            collateral,
+            debt_amount: 5000 * USDC,
+            note_borrow_index: INDEX_SCALE,
+            note_salt: OLD_SALT,
+            statement,
+            challenge: [9u8; 32],
+            merkle_root: tree.root(),
+            merkle_path: tree.path(0).unwrap(),
+            leaf_index: 0,
+        }
+    }
+
+    fn migration(note: LegacyNote, commitment: [u8; 32]) -> MigrationInput {
+        let tree = tree_of(&[[0xAAu8; 32], commitment]);
+        MigrationInput {
+            user_secret_key: SECRET,
+            note,
+            note_salt: OLD_SALT,
+            new_note_salt: NEW_SALT,
+            merkle_root: tree.root(),
+            merkle_path: tree.path(1).unwrap(),
+            leaf_index: 1,
+        }
+    }
+
+    #[test]
+    fn test_deposit_commits_note() {
+        let output = verify_deposit(&deposit(10 * ETH));
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(
+            output.commitment_hash,
+            hash_commitment(&SECRET, &single_asset(METH, 10 * ETH), 0, INDEX_SCALE, &OLD_SALT)
+        );
+        assert_eq!(output.collateral, single_asset(METH, 10 * ETH));
+        assert_eq!((output.depositor_address, output.blocklist_root), (DEPOSITOR, [0u8; 32]));
+    }
+
+    #[test]
+    fn test_deposit_rejections() {
+        let mut input = deposit(0);
+        assert_eq!(verify_deposit(&input).error_code, ErrorCode::ZeroAmount);
+
+        input.collateral = two_assets(ETH, ETH);
+        input.collateral[1].asset = METH;
+        assert_eq!(verify_deposit(&input).error_code, ErrorCode::MalformedCollateral);
+
+        // Screening passes for a clean depositor, and a blocked one can't reuse their proof
+        let blocklist = Blocklist::new(&[BLOCKED]).unwrap();
+        let mut input = deposit(ETH);
+        input.blocklist = blocklist.prove_exclusion(&DEPOSITOR);
+        let output = verify_deposit(&input);
+        assert_eq!((output.error_code, output.blocklist_root), (ErrorCode::Success, blocklist.root()));
+
+        input.depositor_address = BLOCKED;
+        let output = verify_deposit(&input);
+        assert_eq!(output.error_code, ErrorCode::Blocklisted);
+        assert_eq!(output.commitment_hash, [0u8; 32]);
+    }
+
+    #[test]
+    fn test_batch_deposit_commits_every_note() {
+        let deposits: Vec<_> = (1..=3)
+            .map(|i| DepositInput { note_salt: [i as u8; 32], ..deposit(i * ETH) })
+            .collect();
+        let output = verify_batch_deposit(&BatchDepositInput { deposits: deposits.clone() });
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.deposit_count, 3);
+        assert_eq!(output.total_collateral, single_asset(METH, 6 * ETH));
+        assert_eq!(output.depositor_address, DEPOSITOR);
+        for (i, deposit) in deposits.iter().enumerate() {
+            assert_eq!(output.commitment_hashes[i], verify_deposit(deposit).commitment_hash);
+        }
+        assert!(output.commitment_hashes[3..].iter().all(|hash| *hash == [0u8; 32]));
+    }
+
+    #[test]
+    fn test_batch_deposit_rejections() {
+        let batch = |deposits: Vec<DepositInput>| verify_batch_deposit(&BatchDepositInput { deposits });
+
+        assert_eq!(batch(Vec::new()).error_code, ErrorCode::InvalidBatchSize);
+        let oversized = vec![deposit(ETH); MAX_BATCH_DEPOSITS + 1];
+        assert_eq!(batch(oversized).error_code, ErrorCode::InvalidBatchSize);
+        assert_eq!(batch(vec![deposit(ETH), deposit(0)]).error_code, ErrorCode::ZeroAmount);
+
+        let mut malformed = deposit(ETH);
+        malformed.collateral[1] = CollateralPosition { asset: [0u8; 20], amount: 1 };
+        assert_eq!(batch(vec![deposit(ETH), malformed]).error_code, ErrorCode::MalformedCollateral);
+
+        let blocklist = Blocklist::new(&[BLOCKED]).unwrap();
+        let blocked = DepositInput {
+            depositor_address: BLOCKED,
+            blocklist: blocklist.prove_exclusion(&DEPOSITOR),
+            ..deposit(ETH)
+        };
+        assert_eq!(batch(vec![blocked]).error_code, ErrorCode::Blocklisted);
+
+        // One funder and one root for the whole batch
+        let other_funder = DepositInput { depositor_address: [0x34; 20], ..deposit(ETH) };
+        assert_eq!(batch(vec![deposit(ETH), other_funder]).error_code, ErrorCode::BatchMismatch);
+
+        let screened = DepositInput { blocklist: blocklist.prove_exclusion(&DEPOSITOR), ..deposit(ETH) };
+        assert_eq!(batch(vec![screened, deposit(ETH)]).error_code, ErrorCode::BatchMismatch);
+
+        // The total must fit a note's slots
+        let third_asset = DepositInput { collateral: single_asset([0xCC; 20], ETH), ..deposit(ETH) };
+        let mixed = DepositInput { collateral: two_assets(ETH, ETH), ..deposit(ETH) };
+        let output = batch(vec![mixed, third_asset]);
+        assert_eq!(output.error_code, ErrorCode::BatchMismatch);
+        assert_eq!(output.deposit_count, 0);
+        assert!(output.commitment_hashes.iter().all(|hash| *hash == [0u8; 32]));
+    }
+
+    #[test]
+    fn test_borrow_within_ltv() {
+        // 5,000 USDC against $25,000 at 75%
+        let input = borrow(10 * ETH, 0, 5000 * USDC);
+        let output = verify_borrow(&input);
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.nullifier_hash, nullifier(&old_note(input.collateral, 0), 0));
+        assert_eq!(
+            output.new_commitment_hash,
+            hash_commitment(&SECRET, &input.collateral, 5000 * USDC, INDEX_SCALE, &NEW_SALT)
+        );
+        assert_eq!(output.oracle_key_hash, hash_oracle_key(&oracle_key().verifying_key().to_sec1_bytes()));
+        assert_eq!(output.price_timestamp, 1_700_000_000);
+        assert_eq!(output.collateral_assets, [METH, [0u8; 20]]);
+    }
+
+    #[test]
+    fn test_borrow_rejections() {
+        let mut input = borrow(10 * ETH, 0, 5000 * USDC);
+        input.existing_debt = 1;
+        assert_eq!(verify_borrow(&input).error_code, ErrorCode::MembershipFailed);
+
+        let mut input = borrow(10 * ETH, 0, 5000 * USDC);
+        input.prices[0].price_usd = 100 * PRICE;
+        assert_eq!(verify_borrow(&input).error_code, ErrorCode::InvalidPriceSignature);
+
+        // Every price must come from the same oracle
+        let mut input = borrow(10 * ETH, 0, 5000 * USDC);
+        input.collateral = two_assets(10 * ETH, ETH);
+        input.prices[1] = sign_price(&SigningKey::from_slice(&[8u8; 32]).unwrap(), WMNT, USDC);
+        let tree = tree_of(&[old_note(input.collateral, 0)]);
+        input.merkle_root = tree.root();
+        assert_eq!(verify_borrow(&input).error_code, ErrorCode::InvalidPriceSignature);
+
+        input.prices[1] = SignedPrice::default();
+        assert_eq!(verify_borrow(&input).error_code, ErrorCode::UnlistedCollateral);
+
+        // 20,000 USDC is 80% of $25,000
+        let input = borrow(10 * ETH, 0, 20_000 * USDC);
+        assert_eq!(verify_borrow(&input).error_code, ErrorCode::LtvExceeded);
+
+        let mut input = borrow(10 * ETH, 0, 5000 * USDC);
+        input.borrow_index = INDEX_SCALE - 1;
+        assert_eq!(verify_borrow(&input).error_code, ErrorCode::InvalidBorrowIndex);
+
+        let mut input = borrow(10 * ETH, 0, 5000 * USDC);
+        input.recipient_address = BLOCKED;
+        input.blocklist = Blocklist::new(&[BLOCKED]).unwrap().prove_exclusion(&DEPOSITOR);
+        assert_eq!(verify_borrow(&input).error_code, ErrorCode::Blocklisted);
+    }
+
+    #[test]
+    fn test_repay_reduces_debt() {
+        let mut input = repay(5000 * USDC, 2000 * USDC);
+        let output = verify_repay(&input);
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.nullifier_hash, nullifier(&old_note(input.collateral, 5000 * USDC), 0));
+        assert_eq!(
+            output.new_commitment_hash,
+            hash_commitment(&SECRET, &input.collateral, 3000 * USDC, INDEX_SCALE, &NEW_SALT)
+        );
+
+        // Repaying in full is allowed, with interest accrued at the current index
+        input.borrow_index = INDEX_SCALE * 11 / 10;
+        input.repay_amount = 5500 * USDC;
+        assert_eq!(verify_repay(&input).error_code, ErrorCode::Success);
+    }
+
+    #[test]
+    fn test_repay_rejections() {
+        let mut input = repay(5000 * USDC, 2000 * USDC);
+        input.merkle_root = [0u8; 32];
+        assert_eq!(verify_repay(&input).error_code, ErrorCode::MembershipFailed);
+
+        assert_eq!(verify_repay(&repay(5000 * USDC, 0)).error_code, ErrorCode::ZeroAmount);
+        assert_eq!(verify_repay(&repay(5000 * USDC, 5001 * USDC)).error_code, ErrorCode::InsufficientBalance);
+
+        let mut input = repay(5000 * USDC, 2000 * USDC);
+        input.borrow_index = 0;
+        assert_eq!(verify_repay(&input).error_code, ErrorCode::InvalidBorrowIndex);
+    }
+
+    #[test]
+    fn test_withdraw_keeps_position_safe() {
+        // 2 of 10 ETH out with 3,000 USDC debt: $20,000 at 75% still covers it
+        let input = withdraw(10 * ETH, 3000 * USDC, 2 * ETH);
+        let output = verify_withdraw(&input);
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.nullifier_hash, nullifier(&old_note(input.collateral, 3000 * USDC), 0));
+        assert_eq!(
+            output.new_commitment_hash,
+            hash_commitment(&SECRET, &single_asset(METH, 8 * ETH), 3000 * USDC, INDEX_SCALE, &NEW_SALT)
+        );
+        assert_eq!((output.withdraw_asset, output.withdraw_amount), (METH, 2 * ETH));
+        assert_eq!(output.markets, markets(PRICE, 7500));
+        assert_eq!(output.debt_decimals, 6);
+        assert_eq!(output.oracle_key_hash, hash_oracle_key(&oracle_key().verifying_key().to_sec1_bytes()));
+        assert_eq!(output.price_timestamp, 1_700_000_000);
+
+        // A debt-free note can fully exit, freeing the slot
+        let output = verify_withdraw(&withdraw(10 * ETH, 0, 10 * ETH));
+        let empty = [CollateralPosition::default(); MAX_COLLATERAL_ASSETS];
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.new_commitment_hash, hash_commitment(&SECRET, &empty, 0, INDEX_SCALE, &NEW_SALT));
+    }
+
+    #[test]
+    fn test_withdraw_rejections() {
+        let mut input = withdraw(10 * ETH, 3000 * USDC, 2 * ETH);
+        input.existing_debt = 0;
+        assert_eq!(verify_withdraw(&input).error_code, ErrorCode::MembershipFailed);
+
+        assert_eq!(verify_withdraw(&withdraw(10 * ETH, 0, 0)).error_code, ErrorCode::ZeroAmount);
+        assert_eq!(verify_withdraw(&withdraw(10 * ETH, 0, 11 * ETH)).error_code, ErrorCode::InsufficientBalance);
+
+        // An asset the note doesn't hold has no balance
+        let mut input = withdraw(10 * ETH, 0, ETH);
+        input.withdraw_asset = WMNT;
+        assert_eq!(verify_withdraw(&input).error_code, ErrorCode::InsufficientBalance);
+
+        // 9 of 10 ETH out leaves $2,500 at 75% against 3,000 USDC
+        let input = withdraw(10 * ETH, 3000 * USDC, 9 * ETH);
+        assert_eq!(verify_withdraw(&input).error_code, ErrorCode::LtvExceeded);
+
+        // Nor can a prover raise the price or weight to make it look safe
+        let mut input = withdraw(10 * ETH, 3000 * USDC, 9 * ETH);
+        input.prices[0].price_usd = 100 * PRICE;
+        assert_eq!(verify_withdraw(&input).error_code, ErrorCode::InvalidPriceSignature);
+
+        let mut input = withdraw(10 * ETH, 3000 * USDC, 9 * ETH);
+        input.ltv_weights_bps[0] = 10_000;
+        let output = verify_withdraw(&input);
+        assert_eq!(output.error_code, ErrorCode::LtvExceeded);
+        assert_eq!(output.markets[0].weight_bps, 10_000, "The vault sees the weight used");
+
+        // Read as 18-decimal, the debt is dust
+        let mut input = withdraw(10 * ETH, 3000 * USDC, 9 * ETH);
+        input.debt_decimals = 18;
+        let output = verify_withdraw(&input);
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.debt_decimals, 18, "The vault sees the decimals used");
+
+        // Every withdrawal needs signed prices, even from a debt-free note
+        let mut input = withdraw(10 * ETH, 0, ETH);
+        input.prices[0] = SignedPrice::default();
+        assert_eq!(verify_withdraw(&input).error_code, ErrorCode::InvalidPriceSignature);
+    }
+
+    #[test]
+    fn test_liquidation_of_unhealthy_note() {
+        // At $2,000, 10 ETH with an 80% threshold covers $16,000 of the 18,000 USDC debt
+        let input = liquidation(18_000 * USDC, 2000 * USDC);
+        let output = verify_liquidation(&input);
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.nullifier_hash, nullifier(&old_note(input.collateral, 18_000 * USDC), 0));
+        assert_eq!(output.seized_collateral, input.collateral);
+        assert_eq!(output.debt_to_repay, 18_000 * USDC);
+        assert_eq!(output.markets, markets(2000 * USDC, 8000));
+        assert_eq!(output.debt_decimals, 6);
+        assert_eq!(output.oracle_key_hash, hash_oracle_key(&oracle_key().verifying_key().to_sec1_bytes()));
+        assert_eq!(output.price_timestamp, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_liquidation_rejections() {
+        let mut input = liquidation(18_000 * USDC, 2000 * USDC);
+        input.debt_amount = 1;
+        assert_eq!(verify_liquidation(&input).error_code, ErrorCode::MembershipFailed);
+
+        // Healthy at $2,500, and a debt-free note is never liquidatable
+        assert_eq!(verify_liquidation(&liquidation(18_000 * USDC, PRICE)).error_code, ErrorCode::PositionHealthy);
+        assert_eq!(verify_liquidation(&liquidation(0, 1)).error_code, ErrorCode::PositionHealthy);
+
+        // A keeper can't crash the price of a healthy note
+        let mut input = liquidation(18_000 * USDC, PRICE);
+        input.prices[0].price_usd = 2000 * USDC;
+        assert_eq!(verify_liquidation(&input).error_code, ErrorCode::InvalidPriceSignature);
+
+        let mut input = liquidation(18_000 * USDC, PRICE);
+        input.prices[0] = sign_price(&SigningKey::from_slice(&[8u8; 32]).unwrap(), METH, 2000 * USDC);
+        let output = verify_liquidation(&input);
+        assert_eq!(output.error_code, ErrorCode::Success, "Signatures verify, the vault checks the key");
+        assert_ne!(output.oracle_key_hash, hash_oracle_key(&oracle_key().verifying_key().to_sec1_bytes()));
+
+        let mut input = liquidation(18_000 * USDC, 2000 * USDC);
+        input.prices[0] = sign_price(&oracle_key(), WMNT, 2000 * USDC);
+        assert_eq!(verify_liquidation(&input).error_code, ErrorCode::UnlistedCollateral);
+
+        // A zero threshold would make any note with debt look unhealthy
+        let mut input = liquidation(18_000 * USDC, PRICE);
+        input.liquidation_thresholds_bps[0] = 0;
+        assert_eq!(verify_liquidation(&input).error_code, ErrorCode::InvalidMarket);
+
+        let mut input = liquidation(18_000 * USDC, 2000 * USDC);
+        input.debt_decimals = MAX_DECIMALS + 1;
+        assert_eq!(verify_liquidation(&input).error_code, ErrorCode::InvalidDecimals);
+    }
+
+    #[test]
+    fn test_join_split_conserves_notes() {
+        let input = join_split();
+        let output = verify_join_split(&input);
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.nullifier_hashes[0], nullifier(&old_note(single_asset(METH, 4 * ETH), 0), 0));
+        assert_eq!(
+            output.new_commitment_hashes[1],
+            hash_commitment(&SECRET, &single_asset(METH, 7 * ETH), 5000 * USDC, INDEX_SCALE, &[4u8; 32])
+        );
+        assert_eq!(output.markets, markets(PRICE, 7500));
+        assert_eq!(output.debt_decimals, 6);
+        assert_eq!(output.oracle_key_hash, hash_oracle_key(&oracle_key().verifying_key().to_sec1_bytes()));
+        assert_eq!(output.price_timestamp, 1_700_000_000);
+
+        // Merging both notes into one
+        let mut input = join_split();
+        input.created_count = 1;
+        input.created_notes[0].collateral = single_asset(METH, 10 * ETH);
+        input.created_notes[0].debt_amount = 5000 * USDC;
+        let output = verify_join_split(&input);
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.new_commitment_hashes[1], [0u8; 32]);
+    }
+
+    #[test]
+    fn test_join_split_rejections() {
+        let mut input = join_split();
+        input.spent_count = 0;
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::InvalidNoteCount);
+        input.spent_count = 3;
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::InvalidNoteCount);
+
+        let mut input = join_split();
+        input.merkle_root = [0u8; 32];
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::MembershipFailed);
+
+        let mut input = join_split();
+        input.spent_notes[0] = input.spent_notes[1].clone();
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::DuplicateNote);
+
+        let mut input = join_split();
+        input.created_notes[0].collateral = single_asset(METH, 0);
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::ZeroAmount);
+
+        let mut input = join_split();
+        input.created_notes[0].collateral[1] = CollateralPosition { asset: METH, amount: 1 };
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::MalformedCollateral);
+
+        // Moving the debt onto a 1 ETH note: $2,500 at 75% can't carry 5,000 USDC
+        let mut input = join_split();
+        input.created_notes[0].collateral = single_asset(METH, ETH);
+        input.created_notes[0].debt_amount = 5000 * USDC;
+        input.created_notes[1].collateral = single_asset(METH, 9 * ETH);
+        input.created_notes[1].debt_amount = 0;
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::LtvExceeded);
+
+        // Inflating the price to make the same move look safe
+        input.prices[0].price_usd *= 10;
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::InvalidPriceSignature);
+
+        // Splitting a debt-free note still needs signed prices (the vault can't tell it is debt-free)
+        let mut input = join_split();
+        input.prices[0] = SignedPrice::default();
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::InvalidPriceSignature);
+
+        // Dropping collateral or debt
+        let mut input = join_split();
+        input.created_notes[0].collateral = single_asset(METH, 2 * ETH);
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::NotConserved);
+
+        let mut input = join_split();
+        input.created_notes[1].debt_amount = 4999 * USDC;
+        assert_eq!(verify_join_split(&input).error_code, ErrorCode::NotConserved);
+    }
+
+    #[test]
+    fn test_disclosure_statements() {
+        let min_collateral = DisclosureStatement::MinCollateral { asset: METH, min_amount: 5 * ETH };
+        let output = verify_disclosure(&disclosure(min_collateral));
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!((output.statement, output.challenge), (min_collateral, [9u8; 32]));
+        assert_eq!(output.owner_key, derive_owner_key(&SECRET));
+
+        // 5,000 USDC against $25,000 is 20%
+        let max_ltv = |max_ltv_bps| DisclosureStatement::MaxLtv {
+            markets: markets(PRICE, 0),
+            max_ltv_bps,
+            debt_decimals: 6,
+            borrow_index: INDEX_SCALE,
+        };
+        assert_eq!(verify_disclosure(&disclosure(max_ltv(2000))).error_code, ErrorCode::Success);
+        assert_eq!(verify_disclosure(&disclosure(max_ltv(1999))).error_code, ErrorCode::StatementFalse);
+    }
+
+    #[test]
+    fn test_disclosure_rejections() {
+        let mut input = disclosure(DisclosureStatement::MinCollateral { asset: METH, min_amount: ETH });
+        input.debt_amount = 0;
+        assert_eq!(verify_disclosure(&input).error_code, ErrorCode::MembershipFailed);
+
+        let zero = DisclosureStatement::MinCollateral { asset: METH, min_amount: 0 };
+        assert_eq!(verify_disclosure(&disclosure(zero)).error_code, ErrorCode::ZeroAmount);
+
+        let too_much = DisclosureStatement::MinCollateral { asset: METH, min_amount: 11 * ETH };
+        assert_eq!(verify_disclosure(&disclosure(too_much)).error_code, ErrorCode::StatementFalse);
+
+        let other_asset = DisclosureStatement::MinCollateral { asset: WMNT, min_amount: 1 };
+        assert_eq!(verify_disclosure(&disclosure(other_asset)).error_code, ErrorCode::StatementFalse);
+    }
+
+    #[test]
+    fn test_migration_of_legacy_notes() {
+        let collateral = single_asset(METH, 10 * ETH);
+        let position = LegacyNote::Position { collateral, debt_amount: 5000 * USDC, borrow_index: INDEX_SCALE };
+        let legacy = hash_commitment_v0(&SECRET, &collateral, 5000 * USDC, INDEX_SCALE, &OLD_SALT);
+        let output = verify_migration(&migration(position, legacy));
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.nullifier_hash, hash_nullifier_v0(&derive_nullifier_key(&SECRET), &legacy, 1));
+        assert_eq!(
+            output.new_commitment_hash,
+            hash_commitment(&SECRET, &collateral, 5000 * USDC, INDEX_SCALE, &NEW_SALT)
+        );
+
+        // Balance notes keep their one-time owner key
+        let owner_key = derive_stealth_owner_key(&derive_owner_key(&SECRET), &[5u8; 32]);
+        let balance = LegacyNote::Balance { token: METH, amount: 100, shared_secret: [5u8; 32] };
+        let legacy = hash_balance_commitment_v0(&owner_key, &METH, 100, &OLD_SALT);
+        let output = verify_migration(&migration(balance, legacy));
+
+        assert_eq!(output.error_code, ErrorCode::Success);
+        assert_eq!(output.new_commitment_hash, hash_balance_commitment(&owner_key, &METH, 100, &NEW_SALT));
+    }
+
+    #[test]
+    fn test_migration_rejections() {
+        // A note already committed with the current layout is not a legacy note
+        let collateral = single_asset(METH, 10 * ETH);
+        let position = LegacyNote::Position { collateral, debt_amount: 0, borrow_index: INDEX_SCALE };
+        let current = hash_commitment(&SECRET, &collateral, 0, INDEX_SCALE, &OLD_SALT);
+        assert_eq!(verify_migration(&migration(position, current)).error_code, ErrorCode::MembershipFailed);
+
+        // Nor can another key migrate someone's legacy note
+        let legacy = hash_commitment_v0(&SECRET, &collateral, 0, INDEX_SCALE, &OLD_SALT);
+        let mut input = migration(position, legacy);
+        input.user_secret_key = [9u8; 32];
+        assert_eq!(verify_migration(&input).error_code, ErrorCode::MembershipFailed);
+    }
 }
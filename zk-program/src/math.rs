@@ -0,0 +1,454 @@
+/// Basis points in 100%
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Fixed-point scale of the borrow index (1e18 = no interest accrued)
+pub const INDEX_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// Largest decimals supported for any quantity (10^38 is the largest power of ten in a u128)
+pub const MAX_DECIMALS: u8 = 38;
+
+/// Arithmetic failures; callers must treat these as an invalid operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    /// Intermediate result doesn't fit in 256 bits
+    Overflow,
+    /// Decimals above MAX_DECIMALS
+    InvalidDecimals,
+    /// Division by zero
+    DivisionByZero,
+    /// Borrow index is zero or lower than the note's index
+    InvalidIndex,
+}
+
+/// Fixed-point scales of the quantities in a single-asset LTV check
+#[allow(dead_code)] // The program values notes with is_within_weighted_ltv
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimals {
+    /// Decimals of the collateral token amount (18 for ETH)
+    pub collateral: u8,
+    /// Decimals of the USD price per whole collateral token
+    pub price: u8,
+    /// Decimals of the debt token amount (6 for USDC)
+    pub debt: u8,
+}
+
+/// One collateral asset's contribution to a borrowing limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeightedCollateral {
+    /// Token amount in the asset's own decimals
+    pub amount: u128,
+    /// USD price per whole token
+    pub price: u128,
+    /// Share of the asset's value that may be borrowed against
+    pub weight_bps: u16,
+    /// Decimals of the token amount
+    pub decimals: u8,
+}
+
+/// 256-bit unsigned integer as two 128-bit limbs
+/// Field order makes the derived ordering compare `hi` first, then `lo`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct U256 {
+    hi: u128,
+    lo: u128,
+}
+
+impl U256 {
+    /// Widen a u128
+    pub fn from_u128(value: u128) -> Self {
+        U256 { hi: 0, lo: value }
+    }
+
+    /// Full 256-bit product of two u128 values (never overflows)
+    pub fn mul_u128(a: u128, b: u128) -> Self {
+        const MASK: u128 = u64::MAX as u128;
+
+        let (a_hi, a_lo) = (a >> 64, a & MASK);
+        let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+        let low = a_lo * b_lo;
+        let cross_1 = a_lo * b_hi;
+        let cross_2 = a_hi * b_lo;
+        let high = a_hi * b_hi;
+
+        // Middle 64-bit column plus carries (at most 3 * 2^64, no overflow)
+        let middle = (low >> 64) + (cross_1 & MASK) + (cross_2 & MASK);
+
+        U256 {
+            hi: high + (cross_1 >> 64) + (cross_2 >> 64) + (middle >> 64),
+            lo: (low & MASK) | (middle << 64),
+        }
+    }
+
+    /// Add two values, failing if the sum doesn't fit in 256 bits
+    pub fn checked_add(self, rhs: Self) -> Result<Self, MathError> {
+        let (lo, carry) = self.lo.overflowing_add(rhs.lo);
+        let hi = self
+            .hi
+            .checked_add(rhs.hi)
+            .and_then(|hi| hi.checked_add(carry as u128))
+            .ok_or(MathError::Overflow)?;
+
+        Ok(U256 { hi, lo })
+    }
+
+    /// Multiply by a u128, failing if the result doesn't fit in 256 bits
+    pub fn checked_mul_u128(self, rhs: u128) -> Result<Self, MathError> {
+        let low = U256::mul_u128(self.lo, rhs);
+        let high = U256::mul_u128(self.hi, rhs);
+
+        if high.hi != 0 {
+            return Err(MathError::Overflow);
+        }
+        let hi = high.lo.checked_add(low.hi).ok_or(MathError::Overflow)?;
+
+        Ok(U256 { hi, lo: low.lo })
+    }
+
+    /// Divide by a u128 rounding up, failing if the quotient doesn't fit in a u128
+    pub fn checked_div_ceil_u128(self, divisor: u128) -> Result<u128, MathError> {
+        if divisor == 0 {
+            return Err(MathError::DivisionByZero);
+        }
+        if self.hi >= divisor {
+            return Err(MathError::Overflow);
+        }
+
+        // Binary long division of the low limb; the remainder always stays below the divisor
+        let mut remainder = self.hi;
+        let mut quotient = 0u128;
+        for bit in (0..128).rev() {
+            let carry = remainder >> 127;
+            remainder = (remainder << 1) | ((self.lo >> bit) & 1);
+            quotient <<= 1;
+
+            if carry == 1 || remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient |= 1;
+            }
+        }
+
+        if remainder == 0 {
+            Ok(quotient)
+        } else {
+            quotient.checked_add(1).ok_or(MathError::Overflow)
+        }
+    }
+}
+
+/// 10^exp as a u128
+pub fn pow10(exp: u8) -> Result<u128, MathError> {
+    if exp > MAX_DECIMALS {
+        return Err(MathError::InvalidDecimals);
+    }
+
+    Ok(10u128.pow(exp as u32))
+}
+
+/// Exact check that debt is within `max_ltv_bps` of the collateral value
+///
+/// debt / 10^debt_dec <= (collateral / 10^coll_dec) * (price / 10^price_dec) * ltv / 10^4
+/// is cross-multiplied so no side is ever divided (and truncated):
+/// debt * 10^coll_dec * 10^price_dec * 10^4 <= collateral * price * ltv * 10^debt_dec
+#[allow(dead_code)] // Single-asset case of is_within_weighted_ltv
+pub fn is_within_ltv(
+    collateral_amount: u128,
+    collateral_price: u128,
+    debt: u128,
+    max_ltv_bps: u16,
+    decimals: &Decimals,
+) -> Result<bool, MathError> {
+    let collateral = WeightedCollateral {
+        amount: collateral_amount,
+        price: collateral_price,
+        weight_bps: max_ltv_bps,
+        decimals: decimals.collateral,
+    };
+
+    is_within_weighted_ltv(&[collateral], debt, decimals.price, decimals.debt)
+}
+
+/// Exact check that debt is within the summed, LTV-weighted value of several collateral assets
+///
+/// Every asset is first scaled to the largest collateral decimals `D` so the terms can be added:
+/// debt * 10^D * 10^price_dec * 10^4 <= sum(amount * price * weight * 10^(D - coll_dec)) * 10^debt_dec
+pub fn is_within_weighted_ltv(
+    collateral: &[WeightedCollateral],
+    debt: u128,
+    price_decimals: u8,
+    debt_decimals: u8,
+) -> Result<bool, MathError> {
+    let max_decimals = collateral.iter().map(|asset| asset.decimals).max().unwrap_or(0);
+
+    let debt_scaled = U256::from_u128(debt)
+        .checked_mul_u128(pow10(max_decimals)?)?
+        .checked_mul_u128(pow10(price_decimals)?)?
+        .checked_mul_u128(BPS_DENOMINATOR)?;
+
+    let mut weighted_value = U256::from_u128(0);
+    for asset in collateral {
+        let term = U256::mul_u128(asset.amount, asset.price)
+            .checked_mul_u128(asset.weight_bps as u128)?
+            .checked_mul_u128(pow10(max_decimals - asset.decimals)?)?;
+        weighted_value = weighted_value.checked_add(term)?;
+    }
+    let max_debt_scaled = weighted_value.checked_mul_u128(pow10(debt_decimals)?)?;
+
+    Ok(debt_scaled <= max_debt_scaled)
+}
+
+/// Current debt of a note recorded at `note_index` once the global index reaches `current_index`
+/// debt_now = ceil(debt * current_index / note_index), rounded up in the vault's favour
+pub fn accrue_debt(debt: u128, note_index: u128, current_index: u128) -> Result<u128, MathError> {
+    if note_index == 0 || current_index < note_index {
+        return Err(MathError::InvalidIndex);
+    }
+
+    U256::mul_u128(debt, current_index).checked_div_ceil_u128(note_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use proptest::prelude::*;
+
+    const ETH_USDC: Decimals = Decimals { collateral: 18, price: 6, debt: 6 };
+
+    fn to_big(value: U256) -> BigUint {
+        (BigUint::from(value.hi) << 128) + BigUint::from(value.lo)
+    }
+
+    /// Reference implementation of `is_within_weighted_ltv` on arbitrary-precision integers
+    fn reference_weighted_ltv(
+        collateral: &[WeightedCollateral],
+        debt: u128,
+        price_decimals: u8,
+        debt_decimals: u8,
+    ) -> Result<bool, MathError> {
+        let ten = BigUint::from(10u8);
+        let max_decimals = collateral.iter().map(|asset| asset.decimals).max().unwrap_or(0) as u32;
+
+        let lhs = BigUint::from(debt)
+            * ten.pow(max_decimals)
+            * ten.pow(price_decimals as u32)
+            * BigUint::from(BPS_DENOMINATOR);
+        let rhs = collateral
+            .iter()
+            .map(|asset| {
+                BigUint::from(asset.amount)
+                    * BigUint::from(asset.price)
+                    * BigUint::from(asset.weight_bps)
+                    * ten.pow(max_decimals - asset.decimals as u32)
+            })
+            .sum::<BigUint>()
+            * ten.pow(debt_decimals as u32);
+
+        Ok(lhs <= rhs)
+    }
+
+    /// Reference implementation of `is_within_ltv` on arbitrary-precision integers
+    fn reference_ltv(
+        collateral_amount: u128,
+        collateral_price: u128,
+        debt: u128,
+        max_ltv_bps: u16,
+        decimals: &Decimals,
+    ) -> Result<bool, MathError> {
+        let limit = BigUint::from(1u8) << 256;
+        let ten = BigUint::from(10u8);
+
+        let lhs = BigUint::from(debt)
+            * ten.pow(decimals.collateral as u32)
+            * ten.pow(decimals.price as u32)
+            * BigUint::from(BPS_DENOMINATOR);
+        let rhs = BigUint::from(collateral_amount)
+            * BigUint::from(collateral_price)
+            * BigUint::from(max_ltv_bps)
+            * ten.pow(decimals.debt as u32);
+
+        if lhs >= limit || rhs >= limit {
+            return Err(MathError::Overflow);
+        }
+        Ok(lhs <= rhs)
+    }
+
+    #[test]
+    fn test_fractional_collateral_counts() {
+        // 1.99 ETH @ $2000 = $3980; 75% LTV allows $2985
+        let collateral = 1_990_000_000_000_000_000u128;
+        assert_eq!(is_within_ltv(collateral, 2_000_000_000, 2_985_000_000, 7500, &ETH_USDC), Ok(true));
+        assert_eq!(is_within_ltv(collateral, 2_000_000_000, 2_985_000_001, 7500, &ETH_USDC), Ok(false));
+
+        // 0.5 ETH @ $2000 = $1000; used to count as zero collateral
+        let collateral = 500_000_000_000_000_000u128;
+        assert_eq!(is_within_ltv(collateral, 2_000_000_000, 750_000_000, 7500, &ETH_USDC), Ok(true));
+    }
+
+    #[test]
+    fn test_weighted_ltv_sums_assets() {
+        // 1 ETH @ $2000 at 75% = $1500, plus 0.1 WBTC (8 decimals) @ $60000 at 70% = $4200
+        let assets = [
+            WeightedCollateral {
+                amount: 1_000_000_000_000_000_000,
+                price: 2_000_000_000,
+                weight_bps: 7500,
+                decimals: 18,
+            },
+            WeightedCollateral {
+                amount: 10_000_000,
+                price: 60_000_000_000,
+                weight_bps: 7000,
+                decimals: 8,
+            },
+        ];
+        assert_eq!(is_within_weighted_ltv(&assets, 5_700_000_000, 6, 6), Ok(true));
+        assert_eq!(is_within_weighted_ltv(&assets, 5_700_000_001, 6, 6), Ok(false));
+
+        // Neither asset alone covers the debt
+        assert_eq!(is_within_weighted_ltv(&assets[..1], 5_700_000_000, 6, 6), Ok(false));
+        assert_eq!(is_within_weighted_ltv(&assets[1..], 5_700_000_000, 6, 6), Ok(false));
+
+        // No collateral only supports zero debt
+        assert_eq!(is_within_weighted_ltv(&[], 0, 6, 6), Ok(true));
+        assert_eq!(is_within_weighted_ltv(&[], 1, 6, 6), Ok(false));
+    }
+
+    #[test]
+    fn test_debt_accrues_with_index() {
+        // 5,000 USDC borrowed at index 1.0, now 1.1 => 5,500 USDC
+        let index_now = INDEX_SCALE + INDEX_SCALE / 10;
+        assert_eq!(accrue_debt(5_000_000_000, INDEX_SCALE, index_now), Ok(5_500_000_000));
+        assert_eq!(accrue_debt(5_000_000_000, INDEX_SCALE, INDEX_SCALE), Ok(5_000_000_000));
+
+        // Rounds up: 1 unit at 1.5x is 2 units
+        assert_eq!(accrue_debt(1, 2, 3), Ok(2));
+    }
+
+    #[test]
+    fn test_invalid_index_rejected() {
+        assert_eq!(accrue_debt(100, INDEX_SCALE, INDEX_SCALE - 1), Err(MathError::InvalidIndex));
+        assert_eq!(accrue_debt(100, 0, INDEX_SCALE), Err(MathError::InvalidIndex));
+    }
+
+    #[test]
+    fn test_overflow_is_reported() {
+        assert_eq!(
+            is_within_ltv(u128::MAX, u128::MAX, 1, 7500, &ETH_USDC),
+            Err(MathError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_invalid_decimals_rejected() {
+        let decimals = Decimals { collateral: MAX_DECIMALS + 1, price: 6, debt: 6 };
+        assert_eq!(is_within_ltv(1, 1, 1, 7500, &decimals), Err(MathError::InvalidDecimals));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_mul_u128_matches_bigint(a in any::<u128>(), b in any::<u128>()) {
+            prop_assert_eq!(to_big(U256::mul_u128(a, b)), BigUint::from(a) * BigUint::from(b));
+        }
+
+        #[test]
+        fn prop_checked_mul_matches_bigint(a in any::<u128>(), b in any::<u128>(), c in any::<u128>()) {
+            let expected = BigUint::from(a) * BigUint::from(b) * BigUint::from(c);
+            match U256::mul_u128(a, b).checked_mul_u128(c) {
+                Ok(product) => prop_assert_eq!(to_big(product), expected),
+                Err(error) => {
+                    prop_assert_eq!(error, MathError::Overflow);
+                    prop_assert!(expected >= BigUint::from(1u8) << 256);
+                }
+            }
+        }
+
+        #[test]
+        fn prop_ordering_matches_bigint(a in any::<u128>(), b in any::<u128>(), c in any::<u128>(), d in any::<u128>()) {
+            let x = U256::mul_u128(a, b);
+            let y = U256::mul_u128(c, d);
+            prop_assert_eq!(x.cmp(&y), to_big(x).cmp(&to_big(y)));
+        }
+
+        #[test]
+        fn prop_div_ceil_matches_bigint(a in any::<u128>(), b in any::<u128>(), divisor in 1u128..) {
+            let dividend = BigUint::from(a) * BigUint::from(b);
+            let divisor_big = BigUint::from(divisor);
+            let expected = (&dividend + &divisor_big - 1u8) / &divisor_big;
+
+            match U256::mul_u128(a, b).checked_div_ceil_u128(divisor) {
+                Ok(quotient) => prop_assert_eq!(BigUint::from(quotient), expected),
+                Err(error) => {
+                    prop_assert_eq!(error, MathError::Overflow);
+                    prop_assert!(expected > BigUint::from(u128::MAX));
+                }
+            }
+        }
+
+        #[test]
+        fn prop_accrued_debt_never_decreases(
+            debt in 0u128..1_000_000_000_000_000,
+            note_index in INDEX_SCALE..2 * INDEX_SCALE,
+            growth in 0u128..INDEX_SCALE,
+        ) {
+            let accrued = accrue_debt(debt, note_index, note_index + growth).unwrap();
+            prop_assert!(accrued >= debt);
+        }
+
+        #[test]
+        fn prop_ltv_matches_reference(
+            collateral in any::<u128>(),
+            price in any::<u128>(),
+            debt in any::<u128>(),
+            max_ltv_bps in 0u16..=10_000,
+            collateral_decimals in 0u8..=MAX_DECIMALS,
+            price_decimals in 0u8..=MAX_DECIMALS,
+            debt_decimals in 0u8..=MAX_DECIMALS,
+        ) {
+            let decimals = Decimals {
+                collateral: collateral_decimals,
+                price: price_decimals,
+                debt: debt_decimals,
+            };
+            prop_assert_eq!(
+                is_within_ltv(collateral, price, debt, max_ltv_bps, &decimals),
+                reference_ltv(collateral, price, debt, max_ltv_bps, &decimals)
+            );
+        }
+
+        #[test]
+        fn prop_weighted_ltv_matches_reference(
+            amounts in any::<[u64; 2]>(),
+            prices in any::<[u64; 2]>(),
+            weights in prop::array::uniform2(0u16..=10_000),
+            decimals in prop::array::uniform2(0u8..=18),
+            debt in any::<u64>(),
+        ) {
+            let assets = [0, 1].map(|i| WeightedCollateral {
+                amount: amounts[i] as u128,
+                price: prices[i] as u128,
+                weight_bps: weights[i],
+                decimals: decimals[i],
+            });
+            // Bounded inputs never overflow, so results must agree exactly
+            prop_assert_eq!(
+                is_within_weighted_ltv(&assets, debt as u128, 6, 6),
+                reference_weighted_ltv(&assets, debt as u128, 6, 6)
+            );
+        }
+
+        #[test]
+        fn prop_ltv_realistic_amounts_never_overflow(
+            collateral in 0u128..1_000_000_000_000_000_000_000_000, // up to 1M ETH
+            price in 0u128..1_000_000_000_000,                      // up to $1M
+            debt in 0u128..1_000_000_000_000_000,                   // up to $1B
+            max_ltv_bps in 0u16..=10_000,
+        ) {
+            prop_assert_eq!(
+                is_within_ltv(collateral, price, debt, max_ltv_bps, &ETH_USDC),
+                reference_ltv(collateral, price, debt, max_ltv_bps, &ETH_USDC)
+            );
+            prop_assert!(is_within_ltv(collateral, price, debt, max_ltv_bps, &ETH_USDC).is_ok());
+        }
+    }
+}
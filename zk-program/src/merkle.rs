@@ -0,0 +1,341 @@
+#[cfg(not(feature = "keccak"))]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "keccak")]
+use tiny_keccak::{Hasher, Keccak};
+
+/// Depth of the note commitment tree (supports 2^20 notes)
+pub const MERKLE_DEPTH: usize = 20;
+
+/// Hash two child nodes into their parent
+/// parent = sha256(left || right), matching `_hashPair` in AegisVault
+#[cfg(not(feature = "keccak"))]
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(left);
+    hasher.update(right);
+
+    let result = hasher.finalize();
+    result.into()
+}
+
+/// Hash two child nodes into their parent
+/// parent = keccak256(left || right), matching `_hashPair` in AegisVaultKeccak
+#[cfg(feature = "keccak")]
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+
+    hasher.update(left);
+    hasher.update(right);
+
+    let mut result = [0u8; 32];
+    hasher.finalize(&mut result);
+    result
+}
+
+/// Recompute the root from a leaf and its authentication path
+/// Bit `i` of `leaf_index` tells whether the node at level `i` is a right child
+pub fn compute_root(
+    leaf: &[u8; 32],
+    leaf_index: u64,
+    path: &[[u8; 32]; MERKLE_DEPTH],
+) -> [u8; 32] {
+    let mut node = *leaf;
+
+    for (level, sibling) in path.iter().enumerate() {
+        node = if (leaf_index >> level) & 1 == 0 {
+            hash_pair(&node, sibling)
+        } else {
+            hash_pair(sibling, &node)
+        };
+    }
+
+    node
+}
+
+/// Verify that `leaf` sits at `leaf_index` in the tree with the given root
+pub fn verify_merkle_path(
+    leaf: &[u8; 32],
+    leaf_index: u64,
+    path: &[[u8; 32]; MERKLE_DEPTH],
+    expected_root: &[u8; 32],
+) -> bool {
+    // Reject indices that don't fit in the tree (would alias another leaf)
+    if leaf_index >> MERKLE_DEPTH != 0 {
+        return false;
+    }
+
+    compute_root(leaf, leaf_index, path) == *expected_root
+}
+
+/// Roots of empty subtrees at every level (level 0 = empty leaf)
+pub fn zero_hashes() -> [[u8; 32]; MERKLE_DEPTH + 1] {
+    let mut zeros = [[0u8; 32]; MERKLE_DEPTH + 1];
+
+    for level in 0..MERKLE_DEPTH {
+        zeros[level + 1] = hash_pair(&zeros[level], &zeros[level]);
+    }
+
+    zeros
+}
+
+/// Append-only Merkle tree of note commitments
+/// Mirrors the vault's on-chain tree; the host uses it to build authentication paths
+#[allow(dead_code)] // The guest only needs the verifier half
+#[derive(Debug, Clone)]
+pub struct IncrementalMerkleTree {
+    /// Non-empty nodes per level (level 0 = leaves, level MERKLE_DEPTH = root)
+    levels: Vec<Vec<[u8; 32]>>,
+    /// Roots of empty subtrees, used for missing siblings
+    zeros: [[u8; 32]; MERKLE_DEPTH + 1],
+}
+
+#[allow(dead_code)]
+impl IncrementalMerkleTree {
+    /// Create an empty tree
+    pub fn new() -> Self {
+        Self {
+            levels: vec![Vec::new(); MERKLE_DEPTH + 1],
+            zeros: zero_hashes(),
+        }
+    }
+
+    /// Number of leaves inserted so far
+    pub fn len(&self) -> u64 {
+        self.levels[0].len() as u64
+    }
+
+    /// Whether no leaves have been inserted
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Append a leaf and return its index, or None if the tree is full
+    pub fn insert(&mut self, leaf: [u8; 32]) -> Option<u64> {
+        let index = self.len();
+        if index >> MERKLE_DEPTH != 0 {
+            return None;
+        }
+
+        self.levels[0].push(leaf);
+
+        // Recompute the ancestors of the new leaf
+        let mut position = index as usize;
+        for level in 0..MERKLE_DEPTH {
+            let parent = position / 2;
+            let left = self.node(level, parent * 2);
+            let right = self.node(level, parent * 2 + 1);
+            let hash = hash_pair(&left, &right);
+
+            if parent < self.levels[level + 1].len() {
+                self.levels[level + 1][parent] = hash;
+            } else {
+                self.levels[level + 1].push(hash);
+            }
+            position = parent;
+        }
+
+        Some(index)
+    }
+
+    /// Index of the first leaf equal to `leaf`, if present
+    pub fn position(&self, leaf: &[u8; 32]) -> Option<u64> {
+        self.levels[0]
+            .iter()
+            .position(|candidate| candidate == leaf)
+            .map(|index| index as u64)
+    }
+
+    /// Current root of the tree
+    pub fn root(&self) -> [u8; 32] {
+        self.node(MERKLE_DEPTH, 0)
+    }
+
+    /// Authentication path for the leaf at `index`, or None if it doesn't exist
+    pub fn path(&self, index: u64) -> Option<[[u8; 32]; MERKLE_DEPTH]> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut path = [[0u8; 32]; MERKLE_DEPTH];
+        let mut position = index as usize;
+        for (level, sibling) in path.iter_mut().enumerate() {
+            *sibling = self.node(level, position ^ 1);
+            position /= 2;
+        }
+
+        Some(path)
+    }
+
+    /// Node at a given level and position, falling back to the empty subtree root
+    fn node(&self, level: usize, position: usize) -> [u8; 32] {
+        self.levels[level]
+            .get(position)
+            .copied()
+            .unwrap_or(self.zeros[level])
+    }
+}
+
+impl Default for IncrementalMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a 32-byte hex string (golden vectors are shared with the Foundry tests)
+    fn from_hex(hex: &str) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        out
+    }
+
+    fn empty_path() -> [[u8; 32]; MERKLE_DEPTH] {
+        let zeros = zero_hashes();
+        let mut path = [[0u8; 32]; MERKLE_DEPTH];
+        path.copy_from_slice(&zeros[..MERKLE_DEPTH]);
+        path
+    }
+
+    #[test]
+    fn test_single_leaf_membership() {
+        let leaf = [7u8; 32];
+        let path = empty_path();
+        let root = compute_root(&leaf, 0, &path);
+
+        assert!(verify_merkle_path(&leaf, 0, &path, &root), "Leaf should be a member");
+    }
+
+    #[test]
+    fn test_wrong_leaf_rejected() {
+        let leaf = [7u8; 32];
+        let path = empty_path();
+        let root = compute_root(&leaf, 0, &path);
+
+        assert!(
+            !verify_merkle_path(&[8u8; 32], 0, &path, &root),
+            "Different leaf should not verify"
+        );
+    }
+
+    #[test]
+    fn test_wrong_index_rejected() {
+        let leaf = [7u8; 32];
+        let path = empty_path();
+        let root = compute_root(&leaf, 0, &path);
+
+        assert!(
+            !verify_merkle_path(&leaf, 1, &path, &root),
+            "Leaf should not verify at another position"
+        );
+        assert!(
+            !verify_merkle_path(&leaf, 1u64 << MERKLE_DEPTH, &path, &root),
+            "Out-of-range index should be rejected"
+        );
+    }
+
+    #[test]
+    fn test_empty_tree_root() {
+        let zeros = zero_hashes();
+        let root = compute_root(&[0u8; 32], 0, &empty_path());
+
+        assert_eq!(root, zeros[MERKLE_DEPTH], "Empty tree root should match zero hash");
+    }
+
+    #[cfg(not(feature = "keccak"))]
+    #[test]
+    fn test_golden_vectors() {
+        let zeros = zero_hashes();
+        assert_eq!(
+            zeros[1],
+            from_hex("f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a92759fb4b")
+        );
+
+        let mut tree = IncrementalMerkleTree::new();
+        assert_eq!(
+            tree.root(),
+            from_hex("cddba7b592e3133393c16194fac7431abf2f5485ed711db282183c819e08ebaa"),
+            "Empty root mismatch"
+        );
+
+        let expected_roots = [
+            "32fda0577aed68c184536c80f66155ad02a055795c58a9dfead941807cd56041",
+            "cdb37bdb53b5bea9676dc8276d9a29fd8ebbfee0837f807680004136e53a2006",
+            "0282acb72e7d08b2d19e1f628a296eb4901b9f37f26db76f708946f9f650947a",
+        ];
+        for (i, expected) in expected_roots.iter().enumerate() {
+            tree.insert([i as u8 + 1; 32]);
+            assert_eq!(tree.root(), from_hex(expected), "Root mismatch after {} leaves", i + 1);
+        }
+    }
+
+    #[cfg(feature = "keccak")]
+    #[test]
+    fn test_keccak_golden_vectors() {
+        // Shared with the AegisVaultKeccak Foundry test
+        let zeros = zero_hashes();
+        assert_eq!(
+            zeros[1],
+            from_hex("ad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5")
+        );
+
+        let mut tree = IncrementalMerkleTree::new();
+        assert_eq!(
+            tree.root(),
+            from_hex("c65e9645644786b620e2dd2ad648ddfcbf4a7e5b1a3a4ecfe7f64667a3f0b7e2"),
+            "Empty root mismatch"
+        );
+
+        let expected_roots = [
+            "19826032261ced570c978de1d7b0b68afff152212435511e9bdcaa85bcfb3338",
+            "6794826b979b3797f39b77ad2708a684307b05f2f0ef9084cff662f29964a352",
+            "20c1e3143b3494398cf6782a7895148ac5c2bd855fb539fc9a3accd409ec25c9",
+        ];
+        for (i, expected) in expected_roots.iter().enumerate() {
+            tree.insert([i as u8 + 1; 32]);
+            assert_eq!(tree.root(), from_hex(expected), "Root mismatch after {} leaves", i + 1);
+        }
+    }
+
+    #[test]
+    fn test_tree_paths_verify() {
+        let mut tree = IncrementalMerkleTree::new();
+        for i in 0..5u8 {
+            assert_eq!(tree.insert([i; 32]), Some(i as u64));
+        }
+
+        let root = tree.root();
+        for i in 0..5u8 {
+            let path = tree.path(i as u64).unwrap();
+            assert!(
+                verify_merkle_path(&[i; 32], i as u64, &path, &root),
+                "Path for leaf {} should verify",
+                i
+            );
+        }
+
+        assert!(tree.path(5).is_none(), "No path for a missing leaf");
+    }
+
+    #[test]
+    fn test_old_paths_fail_against_new_root() {
+        let mut tree = IncrementalMerkleTree::new();
+        tree.insert([1u8; 32]);
+        let old_root = tree.root();
+        let old_path = tree.path(0).unwrap();
+
+        tree.insert([2u8; 32]);
+
+        assert!(verify_merkle_path(&[1u8; 32], 0, &old_path, &old_root));
+        assert!(
+            !verify_merkle_path(&[1u8; 32], 0, &old_path, &tree.root()),
+            "Stale path should not verify against the new root"
+        );
+        assert!(verify_merkle_path(&[1u8; 32], 0, &tree.path(0).unwrap(), &tree.root()));
+    }
+}
@@ -0,0 +1,27 @@
+// Operation codes, shared with the host and the vault
+// Every operation is proven by the same ELF, so under the same verification key; the program
+// commits the code ahead of the operation's output so a proof of one operation can't be
+// submitted as another's
+
+/// Create a note from deposited collateral
+pub const OP_DEPOSIT: u8 = 0;
+/// Borrow against a note
+pub const OP_BORROW: u8 = 1;
+/// Spend a balance note into a recipient note and a change note
+pub const OP_TRANSFER: u8 = 2;
+/// Repay part of a note's debt
+pub const OP_REPAY: u8 = 3;
+/// Withdraw collateral from a note
+pub const OP_WITHDRAW: u8 = 4;
+/// Liquidate an under-collateralised note
+pub const OP_LIQUIDATE: u8 = 5;
+/// Merge and split a user's notes
+pub const OP_JOIN_SPLIT: u8 = 6;
+/// Prove a statement about a note to an auditor
+pub const OP_DISCLOSURE: u8 = 7;
+/// Move a version 0 note to the current hash layout
+pub const OP_MIGRATION: u8 = 8;
+/// Create many notes from deposits with one proof
+pub const OP_BATCH_DEPOSIT: u8 = 9;
+/// Lock tokens in a balance note, which transfers then spend
+pub const OP_SHIELD: u8 = 10;
@@ -0,0 +1,123 @@
+use k256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// Domain tag prefixed to every price message the oracle signs
+const PRICE_MESSAGE_TAG: &[u8] = b"AEGIS_ORACLE_PRICE_V1";
+
+/// Bytes the oracle signs for a price update
+/// message = "AEGIS_ORACLE_PRICE_V1" || price_usd || timestamp || asset
+pub fn price_message(price_usd: u128, timestamp: u64, asset: &[u8; 20]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(PRICE_MESSAGE_TAG.len() + 16 + 8 + 20);
+
+    message.extend_from_slice(PRICE_MESSAGE_TAG);
+    message.extend_from_slice(&price_usd.to_le_bytes());
+    message.extend_from_slice(&timestamp.to_le_bytes());
+    message.extend_from_slice(asset);
+
+    message
+}
+
+/// Verify the oracle's secp256k1 ECDSA signature (over SHA-256 of the message)
+/// Returns false for malformed keys, malformed or high-S signatures and bad signatures
+pub fn verify_price_signature(
+    price_usd: u128,
+    timestamp: u64,
+    asset: &[u8; 20],
+    oracle_public_key: &[u8],
+    signature: &[u8],
+) -> bool {
+    let Ok(verifying_key) = VerifyingKey::from_sec1_bytes(oracle_public_key) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+
+    verifying_key
+        .verify(&price_message(price_usd, timestamp, asset), &signature)
+        .is_ok()
+}
+
+/// Hash identifying the oracle key; the vault compares it with its configured oracle
+/// oracle_key_hash = hash(sec1_public_key)
+pub fn hash_oracle_key(oracle_public_key: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(oracle_public_key);
+
+    let result = hasher.finalize();
+    result.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::{signature::Signer, SigningKey};
+
+    const ASSET: [u8; 20] = [0xEE; 20];
+
+    fn oracle_key() -> SigningKey {
+        SigningKey::from_slice(&[7u8; 32]).unwrap()
+    }
+
+    fn sign(key: &SigningKey, price_usd: u128, timestamp: u64, asset: &[u8; 20]) -> Vec<u8> {
+        let signature: Signature = key.sign(&price_message(price_usd, timestamp, asset));
+        signature.to_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_valid_price_signature() {
+        let key = oracle_key();
+        let public_key = key.verifying_key().to_sec1_bytes();
+        let signature = sign(&key, 2000, 1_700_000_000, &ASSET);
+
+        assert!(
+            verify_price_signature(2000, 1_700_000_000, &ASSET, &public_key, &signature),
+            "Oracle signature should verify"
+        );
+    }
+
+    #[test]
+    fn test_tampered_price_rejected() {
+        let key = oracle_key();
+        let public_key = key.verifying_key().to_sec1_bytes();
+        let signature = sign(&key, 2000, 1_700_000_000, &ASSET);
+
+        assert!(!verify_price_signature(2001, 1_700_000_000, &ASSET, &public_key, &signature));
+        assert!(!verify_price_signature(2000, 1_700_000_001, &ASSET, &public_key, &signature));
+        assert!(!verify_price_signature(2000, 1_700_000_000, &[0u8; 20], &public_key, &signature));
+    }
+
+    #[test]
+    fn test_wrong_oracle_rejected() {
+        let key = oracle_key();
+        let other = SigningKey::from_slice(&[8u8; 32]).unwrap();
+        let signature = sign(&other, 2000, 1_700_000_000, &ASSET);
+
+        assert!(
+            !verify_price_signature(
+                2000,
+                1_700_000_000,
+                &ASSET,
+                &key.verifying_key().to_sec1_bytes(),
+                &signature
+            ),
+            "Signature from another key should not verify"
+        );
+        assert_ne!(
+            hash_oracle_key(&key.verifying_key().to_sec1_bytes()),
+            hash_oracle_key(&other.verifying_key().to_sec1_bytes())
+        );
+    }
+
+    #[test]
+    fn test_malformed_inputs_rejected() {
+        let key = oracle_key();
+        let public_key = key.verifying_key().to_sec1_bytes();
+        let signature = sign(&key, 2000, 1_700_000_000, &ASSET);
+
+        assert!(!verify_price_signature(2000, 1_700_000_000, &ASSET, &[0u8; 33], &signature));
+        assert!(!verify_price_signature(2000, 1_700_000_000, &ASSET, &public_key, &signature[..63]));
+        assert!(!verify_price_signature(2000, 1_700_000_000, &ASSET, &public_key, &[0u8; 64]));
+    }
+}
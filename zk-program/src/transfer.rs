@@ -1,121 +1,353 @@
 use serde::{Deserialize, Serialize};
 
+use crate::blocklist::{screen_address, BlocklistProof};
+use crate::crypto::{
+    derive_nullifier_key, derive_owner_key, derive_stealth_owner_key, hash_balance_commitment,
+    hash_nullifier,
+};
+use crate::error::{ensure, ErrorCode};
+use crate::merkle::{verify_merkle_path, MERKLE_DEPTH};
+
+/// Input for shielding tokens into a balance note (the only way balance notes are created)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShieldInput {
+    /// One-time stealth owner key of the note (the owner's own, or a recipient's)
+    pub owner_key: [u8; 32],
+    /// Token being shielded (public, the vault pulls it)
+    pub token_address: [u8; 20],
+    /// Amount being shielded (public, the vault pulls exactly this)
+    pub amount: u128,
+    /// Salt of the new note
+    pub note_salt: [u8; 32],
+    /// Address funding the shield (public, the vault checks it is the caller when screening)
+    pub depositor_address: [u8; 20],
+    /// Optional proof that the depositor is not on the sanctions blocklist
+    pub blocklist: Option<BlocklistProof>,
+}
+
+/// Public output from shield proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShieldOutput {
+    /// Commitment of the new balance note (to be stored on-chain)
+    pub commitment_hash: [u8; 32],
+    /// Token committed in the note
+    pub token_address: [u8; 20],
+    /// Amount committed in the note
+    pub amount: u128,
+    /// Address that was screened (vault checks it is the caller)
+    pub depositor_address: [u8; 20],
+    /// Blocklist root the depositor was screened against (zero if unscreened)
+    pub blocklist_root: [u8; 32],
+    /// Why the shield was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
+}
+
+/// Check a shield and generate the balance note's commitment
+pub fn verify_shield(input: &ShieldInput) -> ShieldOutput {
+    // Screen the depositor against the blocklist, if a proof was given
+    let (blocklist_root, is_screened) = screen_address(&input.depositor_address, &input.blocklist);
+
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(input.amount > 0, ErrorCode::ZeroAmount)?;
+        ensure(input.owner_key != [0u8; 32], ErrorCode::InvalidRecipient)?;
+        ensure(is_screened, ErrorCode::Blocklisted)
+    });
+
+    // The token and amount are public so the vault pulls exactly them; the owner stays hidden
+    let commitment_hash = if error_code.is_success() {
+        hash_balance_commitment(&input.owner_key, &input.token_address, input.amount, &input.note_salt)
+    } else {
+        [0u8; 32]
+    };
+
+    ShieldOutput {
+        commitment_hash,
+        token_address: input.token_address,
+        amount: input.amount,
+        depositor_address: input.depositor_address,
+        blocklist_root,
+        error_code,
+    }
+}
+
+/// Input for shielded transfer (spend a balance note, pay a recipient, keep the change)
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TransferInput {
-    // Sender info (private)
-    pub sender_secret: [u8; 32],
-    pub sender_balance: u128,
-    
-    // Transfer details (private)
-    pub transfer_amount: u128,
+    /// Sender's secret key (proves ownership of the spent note)
+    pub sender_secret_key: [u8; 32],
+    /// Token held by the spent note (hidden, carried over to both new notes)
     pub token_address: [u8; 20],
-    pub recipient_address: [u8; 20],
-    
-    // Metadata (optional, private)
-    pub memo: [u8; 32],
-    pub nonce: u64,
+    /// Balance of the spent note (hidden)
+    pub balance: u128,
+    /// Salt of the spent note
+    pub note_salt: [u8; 32],
+    /// Stealth shared secret of the spent note (found by scanning with the viewing key)
+    pub note_shared_secret: [u8; 32],
+    /// Amount sent to the recipient (hidden)
+    pub transfer_amount: u128,
+    /// One-time stealth owner key derived from the recipient's public address
+    /// (the recipient note is spendable only with the recipient's secret key)
+    pub recipient_owner_key: [u8; 32],
+    /// Salt for the recipient note
+    pub recipient_note_salt: [u8; 32],
+    /// Stealth shared secret for the sender's change note
+    pub change_shared_secret: [u8; 32],
+    /// Salt for the sender's change note
+    pub change_note_salt: [u8; 32],
+    /// Merkle root the spent note is proven against
+    pub merkle_root: [u8; 32],
+    /// Authentication path for the spent note's commitment
+    pub merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Position of the spent note's commitment in the tree
+    pub leaf_index: u64,
 }
 
+/// Public output from transfer proof
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TransferOutput {
-    // Public outputs (hashed for privacy)
-    pub transfer_hash: [u8; 32],      // Hash of all transfer data
-    pub sender_commitment: [u8; 32],  // Commitment to sender
-    pub is_valid: u8,                 // 1 if valid, 0 if not
+    /// Hash of the spent note (nullifier)
+    pub nullifier_hash: [u8; 32],
+    /// Commitment of the note paying the recipient
+    pub recipient_commitment_hash: [u8; 32],
+    /// Commitment of the sender's change note (balance minus the amount sent)
+    pub change_commitment_hash: [u8; 32],
+    /// Merkle root the spent note was proven against (checked against root history)
+    pub merkle_root: [u8; 32],
+    /// Why the transfer was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
 }
 
+/// Spend a committed balance note and split it into a recipient note and a change note
 pub fn verify_transfer(input: &TransferInput) -> TransferOutput {
-    use sha2::{Sha256, Digest};
-    
-    // Validation 1: Check sufficient balance
-    let has_sufficient_balance = input.sender_balance >= input.transfer_amount;
-    
-    // Validation 2: Check non-zero amount
-    let is_nonzero_amount = input.transfer_amount > 0;
-    
-    // Validation 3: Check recipient is not zero address
-    let is_valid_recipient = input.recipient_address != [0u8; 20];
-    
-    // Overall validity
-    let is_valid = if has_sufficient_balance && is_nonzero_amount && is_valid_recipient {
-        1u8
-    } else {
-        0u8
-    };
-    
-    // Create sender commitment (hash of sender secret + balance)
-    let mut sender_hasher = Sha256::new();
-    sender_hasher.update(&input.sender_secret);
-    sender_hasher.update(&input.sender_balance.to_le_bytes());
-    let sender_commitment: [u8; 32] = sender_hasher.finalize().into();
-    
-    // Create transfer hash (hash of all transfer details)
-    // This proves transfer happened without revealing details
-    let mut transfer_hasher = Sha256::new();
-    transfer_hasher.update(&sender_commitment);
-    transfer_hasher.update(&input.transfer_amount.to_le_bytes());
-    transfer_hasher.update(&input.token_address);
-    transfer_hasher.update(&input.recipient_address);
-    transfer_hasher.update(&input.memo);
-    transfer_hasher.update(&input.nonce.to_le_bytes());
-    let transfer_hash: [u8; 32] = transfer_hasher.finalize().into();
-    
+    // Step 1: Recompute the spent note's commitment under its one-time owner key
+    // and prove it is in the tree
+    let sender_owner_key = derive_owner_key(&input.sender_secret_key);
+    let commitment_hash = hash_balance_commitment(
+        &derive_stealth_owner_key(&sender_owner_key, &input.note_shared_secret),
+        &input.token_address,
+        input.balance,
+        &input.note_salt,
+    );
+    let is_member = verify_merkle_path(
+        &commitment_hash,
+        input.leaf_index,
+        &input.merkle_path,
+        &input.merkle_root,
+    );
+
+    // Step 2: Generate nullifier for the spent note (marks it as spent)
+    let nullifier_key = derive_nullifier_key(&input.sender_secret_key);
+    let nullifier_hash = hash_nullifier(&nullifier_key, &commitment_hash, input.leaf_index);
+
+    // Step 3: Amount must be non-zero, covered by the note, and sent to a real owner key
+    let error_code = ErrorCode::from_checks(|| {
+        ensure(is_member, ErrorCode::MembershipFailed)?;
+        ensure(input.transfer_amount > 0, ErrorCode::ZeroAmount)?;
+        ensure(input.transfer_amount <= input.balance, ErrorCode::InsufficientBalance)?;
+        ensure(input.recipient_owner_key != [0u8; 32], ErrorCode::InvalidRecipient)
+    });
+
+    // Step 4: Recipient note is keyed to the recipient, change stays with the sender
+    let recipient_commitment_hash = hash_balance_commitment(
+        &input.recipient_owner_key,
+        &input.token_address,
+        input.transfer_amount,
+        &input.recipient_note_salt,
+    );
+    let change_commitment_hash = hash_balance_commitment(
+        &derive_stealth_owner_key(&sender_owner_key, &input.change_shared_secret),
+        &input.token_address,
+        input.balance.saturating_sub(input.transfer_amount),
+        &input.change_note_salt,
+    );
+
     TransferOutput {
-        transfer_hash,
-        sender_commitment,
-        is_valid,
+        nullifier_hash,
+        recipient_commitment_hash,
+        change_commitment_hash,
+        merkle_root: input.merkle_root,
+        error_code,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::{
+        derive_ecdh_public_key, derive_public_address, derive_stealth_shared_secret,
+        derive_viewing_key,
+    };
+    use crate::merkle::IncrementalMerkleTree;
+
+    const TOKEN: [u8; 20] = [0x1u8; 20];
+    const SENDER: [u8; 32] = [1u8; 32];
+    const RECIPIENT: [u8; 32] = [9u8; 32];
+    const EPHEMERAL: [u8; 32] = [5u8; 32];
+
+    /// One-time owner key for the owner of `secret`, as the sender derives it from their address
+    fn stealth_owner_key(secret: &[u8; 32], shared_secret: &[u8; 32]) -> [u8; 32] {
+        derive_stealth_owner_key(&derive_public_address(secret).owner_key, shared_secret)
+    }
+
+    /// Transfer of `amount` out of a committed note holding `balance`
+    fn transfer(balance: u128, amount: u128) -> TransferInput {
+        let commitment =
+            hash_balance_commitment(&stealth_owner_key(&SENDER, &[6u8; 32]), &TOKEN, balance, &[2u8; 32]);
+        let mut tree = IncrementalMerkleTree::new();
+        let leaf_index = tree.insert(commitment).unwrap();
+
+        TransferInput {
+            sender_secret_key: SENDER,
+            token_address: TOKEN,
+            balance,
+            note_salt: [2u8; 32],
+            note_shared_secret: [6u8; 32],
+            transfer_amount: amount,
+            recipient_owner_key: stealth_owner_key(&RECIPIENT, &[7u8; 32]),
+            recipient_note_salt: [3u8; 32],
+            change_shared_secret: [8u8; 32],
+            change_note_salt: [4u8; 32],
+            merkle_root: tree.root(),
+            merkle_path: tree.path(leaf_index).unwrap(),
+            leaf_index,
+        }
+    }
 
     #[test]
-    fn test_valid_transfer() {
-        let input = TransferInput {
-            sender_secret: [1u8; 32],
-            sender_balance: 1000,
-            transfer_amount: 100,
-            token_address: [0x1u8; 20],
-            recipient_address: [0x2u8; 20],
-            memo: [0u8; 32],
-            nonce: 1,
+    fn test_shielded_note_can_be_spent() {
+        let input = ShieldInput {
+            owner_key: stealth_owner_key(&SENDER, &[6u8; 32]),
+            token_address: TOKEN,
+            amount: 1000,
+            note_salt: [2u8; 32],
+            depositor_address: [0xAAu8; 20],
+            blocklist: None,
         };
-        
-        let output = verify_transfer(&input);
-        assert_eq!(output.is_valid, 1);
+        let shielded = verify_shield(&input);
+        assert_eq!(shielded.error_code, ErrorCode::Success);
+        assert_eq!(shielded.amount, 1000);
+        assert_eq!(shielded.token_address, TOKEN);
+
+        // The note the vault inserts is the one a transfer spends
+        let transfer = transfer(1000, 100);
+        let mut tree = IncrementalMerkleTree::new();
+        tree.insert(shielded.commitment_hash).unwrap();
+        assert_eq!(tree.root(), transfer.merkle_root);
+        assert_eq!(verify_transfer(&transfer).error_code, ErrorCode::Success);
     }
 
     #[test]
-    fn test_insufficient_balance() {
-        let input = TransferInput {
-            sender_secret: [1u8; 32],
-            sender_balance: 50,
-            transfer_amount: 100,
-            token_address: [0x1u8; 20],
-            recipient_address: [0x2u8; 20],
-            memo: [0u8; 32],
-            nonce: 1,
+    fn test_shield_rejects_empty_or_unowned_notes() {
+        let input = ShieldInput {
+            owner_key: stealth_owner_key(&SENDER, &[6u8; 32]),
+            token_address: TOKEN,
+            amount: 0,
+            note_salt: [2u8; 32],
+            depositor_address: [0xAAu8; 20],
+            blocklist: None,
         };
-        
+        let output = verify_shield(&input);
+        assert_eq!(output.error_code, ErrorCode::ZeroAmount);
+        assert_eq!(output.commitment_hash, [0u8; 32]);
+
+        let input = ShieldInput { amount: 1000, owner_key: [0u8; 32], ..input };
+        assert_eq!(verify_shield(&input).error_code, ErrorCode::InvalidRecipient);
+    }
+
+    #[test]
+    fn test_valid_transfer() {
+        let input = transfer(1000, 100);
         let output = verify_transfer(&input);
-        assert_eq!(output.is_valid, 0);
+        assert_eq!(output.error_code, ErrorCode::Success);
+
+        // The recipient can open their note, the sender keeps the change
+        assert_eq!(
+            output.recipient_commitment_hash,
+            hash_balance_commitment(&stealth_owner_key(&RECIPIENT, &[7u8; 32]), &TOKEN, 100, &[3u8; 32])
+        );
+        assert_eq!(
+            output.change_commitment_hash,
+            hash_balance_commitment(&stealth_owner_key(&SENDER, &[8u8; 32]), &TOKEN, 900, &[4u8; 32])
+        );
+        assert_eq!(output.merkle_root, input.merkle_root);
+    }
+
+    #[test]
+    fn test_insufficient_balance() {
+        let output = verify_transfer(&transfer(50, 100));
+        assert_eq!(output.error_code, ErrorCode::InsufficientBalance);
     }
 
     #[test]
     fn test_zero_amount() {
-        let input = TransferInput {
-            sender_secret: [1u8; 32],
-            sender_balance: 1000,
-            transfer_amount: 0,
-            token_address: [0x1u8; 20],
-            recipient_address: [0x2u8; 20],
-            memo: [0u8; 32],
-            nonce: 1,
-        };
-        
-        let output = verify_transfer(&input);
-        assert_eq!(output.is_valid, 0);
+        let output = verify_transfer(&transfer(1000, 0));
+        assert_eq!(output.error_code, ErrorCode::ZeroAmount);
+    }
+
+    #[test]
+    fn test_zero_recipient_key() {
+        let mut input = transfer(1000, 100);
+        input.recipient_owner_key = [0u8; 32];
+        assert_eq!(verify_transfer(&input).error_code, ErrorCode::InvalidRecipient);
+    }
+
+    #[test]
+    fn test_claimed_balance_must_be_committed() {
+        // Claiming more than the committed note holds breaks membership
+        let mut input = transfer(50, 100);
+        input.balance = 1000;
+        assert_eq!(verify_transfer(&input).error_code, ErrorCode::MembershipFailed);
+
+        // So does spending someone else's note
+        let mut input = transfer(1000, 100);
+        input.sender_secret_key = RECIPIENT;
+        assert_eq!(verify_transfer(&input).error_code, ErrorCode::MembershipFailed);
+
+        // Or the right note with the wrong shared secret
+        let mut input = transfer(1000, 100);
+        input.note_shared_secret = [7u8; 32];
+        assert_eq!(verify_transfer(&input).error_code, ErrorCode::MembershipFailed);
+    }
+
+    #[test]
+    fn test_recipient_spends_received_note() {
+        // Sender pays the recipient's public address with a fresh ephemeral key
+        let address = derive_public_address(&RECIPIENT);
+        let shared_secret = derive_stealth_shared_secret(&EPHEMERAL, &address.viewing_public_key).unwrap();
+        let mut input = transfer(1000, 100);
+        input.recipient_owner_key = derive_stealth_owner_key(&address.owner_key, &shared_secret);
+        let sent = verify_transfer(&input);
+        assert_eq!(sent.error_code, ErrorCode::Success);
+
+        // Recipient recovers the shared secret from the announced ephemeral key and spends
+        let ephemeral_public_key = derive_ecdh_public_key(&EPHEMERAL).unwrap();
+        let found = derive_stealth_shared_secret(&derive_viewing_key(&RECIPIENT), &ephemeral_public_key).unwrap();
+        let mut tree = IncrementalMerkleTree::new();
+        let leaf_index = tree.insert(sent.recipient_commitment_hash).unwrap();
+        let mut spend = transfer(100, 40);
+        spend.sender_secret_key = RECIPIENT;
+        spend.note_salt = input.recipient_note_salt;
+        spend.note_shared_secret = found;
+        spend.merkle_root = tree.root();
+        spend.merkle_path = tree.path(leaf_index).unwrap();
+        spend.leaf_index = leaf_index;
+        assert_eq!(verify_transfer(&spend).error_code, ErrorCode::Success);
+
+        // The sender knows the shared secret but not the recipient's spending key
+        spend.sender_secret_key = SENDER;
+        assert_eq!(verify_transfer(&spend).error_code, ErrorCode::MembershipFailed);
+    }
+
+    #[test]
+    fn test_nullifier_independent_of_recipient() {
+        let first = verify_transfer(&transfer(1000, 100));
+        let mut input = transfer(1000, 200);
+        input.recipient_owner_key = derive_owner_key(&[7u8; 32]);
+        let second = verify_transfer(&input);
+
+        assert_eq!(
+            first.nullifier_hash, second.nullifier_hash,
+            "Any spend of the same note must reveal the same nullifier"
+        );
     }
 }
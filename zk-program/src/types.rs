@@ -1,14 +1,52 @@
 use serde::{Deserialize, Serialize};
 
+use crate::blocklist::BlocklistProof;
+use crate::collateral::{CollateralMarket, CollateralPosition, MAX_COLLATERAL_ASSETS};
+use crate::error::ErrorCode;
+use crate::merkle::MERKLE_DEPTH;
+
+/// Most notes a join-split can spend or create
+pub const MAX_SPLIT_NOTES: usize = 2;
+
+/// Most deposits one batch deposit proof can create
+/// Must match MAX_BATCH_DEPOSITS in contracts/src/AegisVault.sol
+pub const MAX_BATCH_DEPOSITS: usize = 16;
+
 /// Input for deposit operation (initial commitment creation)
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DepositInput {
     /// User's secret key (proves ownership)
     pub user_secret_key: [u8; 32],
-    /// Amount of collateral being deposited (hidden)
-    pub collateral_amount: u128,
+    /// Collateral assets and amounts being deposited (public, the vault pulls exactly these)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
     /// Random salt for commitment uniqueness
     pub note_salt: [u8; 32],
+    /// Address funding the deposit (public, the vault checks it is the caller when screening)
+    pub depositor_address: [u8; 20],
+    /// Optional proof that the depositor is not on the sanctions blocklist
+    pub blocklist: Option<BlocklistProof>,
+}
+
+/// Input for batch deposit operation (many initial commitments in one proof)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchDepositInput {
+    /// Deposits to create (1 to MAX_BATCH_DEPOSITS), all funded by the same address
+    pub deposits: Vec<DepositInput>,
+}
+
+/// Oracle-signed collateral price; unused market slots have a zero asset and no signature
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SignedPrice {
+    /// Price of collateral in USD (e.g., mETH price)
+    pub price_usd: u128,
+    /// Unix time the oracle signed the price (public, vault enforces freshness)
+    pub timestamp: u64,
+    /// Asset the price is quoted for (public, vault checks it against its collateral table)
+    pub asset: [u8; 20],
+    /// Oracle's SEC1-encoded secp256k1 public key (33 bytes compressed)
+    pub oracle_public_key: Vec<u8>,
+    /// Oracle's ECDSA signature over the price message, r || s (64 bytes)
+    pub signature: Vec<u8>,
 }
 
 /// Input for borrow operation (full private lending)
@@ -16,22 +54,271 @@ pub struct DepositInput {
 pub struct BorrowInput {
     /// User's secret key (proves ownership of old note)
     pub user_secret_key: [u8; 32],
-    /// Amount of collateral (hidden)
-    pub collateral_amount: u128,
-    /// Price of collateral in USD (e.g., mETH price)
-    pub collateral_price_usd: u128,
-    /// Existing debt amount
+    /// Collateral assets and amounts on the note (hidden)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Oracle-signed price of each collateral market, all from the same oracle
+    pub prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
+    /// Debt on the old note, as of its borrow index
     pub existing_debt: u128,
+    /// Borrow index the old note's debt was recorded at
+    pub note_borrow_index: u128,
+    /// Current global borrow index (public, vault checks it)
+    pub borrow_index: u128,
     /// New amount to borrow
     pub new_borrow_amount: u128,
-    /// Maximum LTV ratio in basis points (7500 = 75%)
-    pub max_ltv_bps: u16,
+    /// LTV weight of each collateral market in basis points (7500 = 75%, public)
+    pub ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    /// Decimals of each collateral market's token (public, vault checks its tokens)
+    pub collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    /// Decimals of the debt token (public, vault checks its token)
+    pub debt_decimals: u8,
     /// Salt from old note
     pub old_note_salt: [u8; 32],
     /// Salt for new note
     pub new_note_salt: [u8; 32],
     /// Recipient address for borrowed funds
     pub recipient_address: [u8; 20],
+    /// Merkle root the old note is proven against
+    pub merkle_root: [u8; 32],
+    /// Authentication path for the old note's commitment
+    pub merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Position of the old note's commitment in the tree
+    pub leaf_index: u64,
+    /// Optional proof that the recipient is not on the sanctions blocklist
+    pub blocklist: Option<BlocklistProof>,
+}
+
+/// Input for repay operation (reduce debt on a note)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepayInput {
+    /// User's secret key (proves ownership of old note)
+    pub user_secret_key: [u8; 32],
+    /// Collateral on the note (hidden, carried over to the new note)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Debt on the old note, as of its borrow index
+    pub existing_debt: u128,
+    /// Borrow index the old note's debt was recorded at
+    pub note_borrow_index: u128,
+    /// Current global borrow index (public, vault checks it)
+    pub borrow_index: u128,
+    /// Amount being repaid (public, transferred to the vault)
+    pub repay_amount: u128,
+    /// Salt from old note
+    pub old_note_salt: [u8; 32],
+    /// Salt for new note
+    pub new_note_salt: [u8; 32],
+    /// Merkle root the old note is proven against
+    pub merkle_root: [u8; 32],
+    /// Authentication path for the old note's commitment
+    pub merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Position of the old note's commitment in the tree
+    pub leaf_index: u64,
+}
+
+/// Input for withdraw operation (release collateral from a note)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WithdrawInput {
+    /// User's secret key (proves ownership of old note)
+    pub user_secret_key: [u8; 32],
+    /// Collateral on the old note (hidden)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Debt on the old note, as of its borrow index (carried over with interest)
+    pub existing_debt: u128,
+    /// Borrow index the old note's debt was recorded at
+    pub note_borrow_index: u128,
+    /// Current global borrow index (public, vault checks it)
+    pub borrow_index: u128,
+    /// Collateral asset being withdrawn (public)
+    pub withdraw_asset: [u8; 20],
+    /// Amount of collateral being withdrawn (public)
+    pub withdraw_amount: u128,
+    /// Oracle-signed price of each collateral market, all from the same oracle
+    pub prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
+    /// LTV weight of each collateral market in basis points (public, vault checks it)
+    pub ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    /// Decimals of each collateral market's token (public, vault checks its tokens)
+    pub collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    /// Decimals of the debt token (public, vault checks its token)
+    pub debt_decimals: u8,
+    /// Salt from old note
+    pub old_note_salt: [u8; 32],
+    /// Salt for new note
+    pub new_note_salt: [u8; 32],
+    /// Recipient address for withdrawn collateral
+    pub recipient_address: [u8; 20],
+    /// Merkle root the old note is proven against
+    pub merkle_root: [u8; 32],
+    /// Authentication path for the old note's commitment
+    pub merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Position of the old note's commitment in the tree
+    pub leaf_index: u64,
+}
+
+/// Input for liquidation operation (prove a note is under-collateralised)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LiquidationInput {
+    /// Note owner's secret key (part of the note opening)
+    pub user_secret_key: [u8; 32],
+    /// Collateral on the note (hidden)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Debt on the note as of its borrow index (hidden)
+    pub debt_amount: u128,
+    /// Borrow index the note's debt was recorded at
+    pub note_borrow_index: u128,
+    /// Current global borrow index (public, vault checks it)
+    pub borrow_index: u128,
+    /// Salt of the note being liquidated
+    pub note_salt: [u8; 32],
+    /// Oracle-signed current price of each collateral market, all from the same oracle
+    pub prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
+    /// Liquidation threshold of each collateral market in basis points (8000 = 80%, public)
+    pub liquidation_thresholds_bps: [u16; MAX_COLLATERAL_ASSETS],
+    /// Decimals of each collateral market's token (public, vault checks its tokens)
+    pub collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    /// Decimals of the debt token (public, vault checks its token)
+    pub debt_decimals: u8,
+    /// Keeper address receiving the seized collateral
+    pub recipient_address: [u8; 20],
+    /// Merkle root the note is proven against
+    pub merkle_root: [u8; 32],
+    /// Authentication path for the note's commitment
+    pub merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Position of the note's commitment in the tree
+    pub leaf_index: u64,
+}
+
+/// Opening of a note spent by a join-split
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpentNote {
+    /// Collateral on the note (hidden)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Debt on the note, as of its borrow index (hidden)
+    pub debt_amount: u128,
+    /// Borrow index the note's debt was recorded at
+    pub note_borrow_index: u128,
+    /// Salt of the note
+    pub salt: [u8; 32],
+    /// Authentication path for the note's commitment
+    pub merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Position of the note's commitment in the tree
+    pub leaf_index: u64,
+}
+
+/// Contents of a note created by a join-split, recorded at the current borrow index
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreatedNote {
+    /// Collateral on the new note (hidden)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Debt on the new note (hidden)
+    pub debt_amount: u128,
+    /// Salt for the new note
+    pub salt: [u8; 32],
+}
+
+/// Input for join-split operation (merge and/or split a user's notes)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JoinSplitInput {
+    /// User's secret key (owns every spent and created note)
+    pub user_secret_key: [u8; 32],
+    /// Number of notes being spent (1 or 2)
+    pub spent_count: u8,
+    /// Notes being spent; entries past `spent_count` are ignored
+    pub spent_notes: [SpentNote; MAX_SPLIT_NOTES],
+    /// Number of notes being created (1 or 2)
+    pub created_count: u8,
+    /// Notes being created; entries past `created_count` are ignored
+    pub created_notes: [CreatedNote; MAX_SPLIT_NOTES],
+    /// Current global borrow index (public, vault checks it)
+    pub borrow_index: u128,
+    /// Oracle-signed current price of each collateral market (for new notes carrying debt)
+    pub prices: [SignedPrice; MAX_COLLATERAL_ASSETS],
+    /// Max LTV of each collateral market in basis points (public, vault checks its collateral table)
+    pub ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    /// Decimals of each collateral market's token (public, vault checks its tokens)
+    pub collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    /// Decimals of the debt token (public, vault checks its token)
+    pub debt_decimals: u8,
+    /// Merkle root the spent notes are proven against
+    pub merkle_root: [u8; 32],
+}
+
+/// Fact about a note proven to an auditor; nothing else about the note is revealed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisclosureStatement {
+    /// The note holds at least `min_amount` of `asset`
+    MinCollateral {
+        asset: [u8; 20],
+        min_amount: u128,
+    },
+    /// The note's debt, accrued to `borrow_index`, is at most `max_ltv_bps` of its collateral
+    /// valued at the `markets` prices (market weights are replaced by `max_ltv_bps`)
+    MaxLtv {
+        markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+        max_ltv_bps: u16,
+        debt_decimals: u8,
+        borrow_index: u128,
+    },
+}
+
+/// Input for disclosure operation (prove a statement about a note without spending it)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisclosureInput {
+    /// User's secret key (proves ownership of the note)
+    pub user_secret_key: [u8; 32],
+    /// Collateral on the note (hidden)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Debt on the note, as of its borrow index (hidden)
+    pub debt_amount: u128,
+    /// Borrow index the note's debt was recorded at
+    pub note_borrow_index: u128,
+    /// Salt of the note
+    pub note_salt: [u8; 32],
+    /// Statement being proven (public)
+    pub statement: DisclosureStatement,
+    /// Auditor-chosen value binding the proof to one request (public)
+    pub challenge: [u8; 32],
+    /// Merkle root the note is proven against
+    pub merkle_root: [u8; 32],
+    /// Authentication path for the note's commitment
+    pub merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Position of the note's commitment in the tree
+    pub leaf_index: u64,
+}
+
+/// Contents of a note committed with the version 0 hash layout
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyNote {
+    /// Lending position note
+    Position {
+        collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+        debt_amount: u128,
+        borrow_index: u128,
+    },
+    /// Token balance note, committed to the one-time owner key `shared_secret` derives
+    Balance {
+        token: [u8; 20],
+        amount: u128,
+        shared_secret: [u8; 32],
+    },
+}
+
+/// Input for migration operation (re-commit a version 0 note with the current hash layout)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrationInput {
+    /// User's secret key (proves ownership of the note)
+    pub user_secret_key: [u8; 32],
+    /// Contents of the legacy note, carried over unchanged (hidden)
+    pub note: LegacyNote,
+    /// Salt of the legacy note
+    pub note_salt: [u8; 32],
+    /// Salt for the migrated note
+    pub new_note_salt: [u8; 32],
+    /// Merkle root the legacy note is proven against
+    pub merkle_root: [u8; 32],
+    /// Authentication path for the legacy note's commitment
+    pub merkle_path: [[u8; 32]; MERKLE_DEPTH],
+    /// Position of the legacy note's commitment in the tree
+    pub leaf_index: u64,
 }
 
 /// Public output from deposit proof
@@ -39,8 +326,31 @@ pub struct BorrowInput {
 pub struct DepositOutput {
     /// Commitment hash (to be stored on-chain)
     pub commitment_hash: [u8; 32],
-    /// Whether the deposit is valid
-    pub is_valid: u8,
+    /// Collateral committed in the note (vault pulls these amounts of these tokens)
+    pub collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Address that was screened (vault checks it is the caller)
+    pub depositor_address: [u8; 20],
+    /// Blocklist root the depositor was screened against (zero if unscreened)
+    pub blocklist_root: [u8; 32],
+    /// Why the deposit was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
+}
+
+/// Public output from batch deposit proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchDepositOutput {
+    /// Commitment of each deposit, in input order (zero for unused entries)
+    pub commitment_hashes: [[u8; 32]; MAX_BATCH_DEPOSITS],
+    /// Number of commitments created
+    pub deposit_count: u8,
+    /// Total collateral across the batch (vault pulls these amounts once)
+    pub total_collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Address that funds every deposit (vault checks it is the caller when screening)
+    pub depositor_address: [u8; 20],
+    /// Blocklist root every deposit was screened against (zero if unscreened)
+    pub blocklist_root: [u8; 32],
+    /// Why the batch was rejected (Success if every deposit is valid)
+    pub error_code: ErrorCode,
 }
 
 /// Public output from borrow proof
@@ -54,15 +364,148 @@ pub struct BorrowOutput {
     pub recipient_address: [u8; 20],
     /// Amount being borrowed
     pub borrow_amount: u128,
-    /// Whether the borrow is valid (LTV safe)
-    pub is_valid: u8,
+    /// Merkle root the old note was proven against (checked against root history)
+    pub merkle_root: [u8; 32],
+    /// Hash of the oracle key that signed the prices (vault checks it is trusted)
+    pub oracle_key_hash: [u8; 32],
+    /// Time the oldest price was signed (vault rejects stale prices)
+    pub price_timestamp: u64,
+    /// Collateral markets the note was valued in (vault checks its collateral table)
+    pub collateral_assets: [[u8; 20]; MAX_COLLATERAL_ASSETS],
+    /// LTV weight of each collateral market
+    pub ltv_weights_bps: [u16; MAX_COLLATERAL_ASSETS],
+    /// Collateral token decimals used for the LTV check
+    pub collateral_decimals: [u8; MAX_COLLATERAL_ASSETS],
+    /// Debt token decimals used for the LTV check
+    pub debt_decimals: u8,
+    /// Global borrow index the debt was accrued to (vault checks it is current)
+    pub borrow_index: u128,
+    /// Blocklist root the recipient was screened against (zero if unscreened)
+    pub blocklist_root: [u8; 32],
+    /// Why the borrow was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
 }
 
-/// Represents a private note (commitment)
+/// Public output from repay proof
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Note {
-    pub user_secret_key: [u8; 32],
-    pub collateral_amount: u128,
-    pub debt_amount: u128,
-    pub salt: [u8; 32],
+pub struct RepayOutput {
+    /// Hash of the old note being spent (nullifier)
+    pub nullifier_hash: [u8; 32],
+    /// New commitment hash (with reduced debt)
+    pub new_commitment_hash: [u8; 32],
+    /// Amount being repaid
+    pub repay_amount: u128,
+    /// Merkle root the old note was proven against (checked against root history)
+    pub merkle_root: [u8; 32],
+    /// Global borrow index the debt was accrued to (vault checks it is current)
+    pub borrow_index: u128,
+    /// Why the repay was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
+}
+
+/// Public output from withdraw proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WithdrawOutput {
+    /// Hash of the old note being spent (nullifier)
+    pub nullifier_hash: [u8; 32],
+    /// New commitment hash (with remaining collateral)
+    pub new_commitment_hash: [u8; 32],
+    /// Address to receive withdrawn collateral
+    pub recipient_address: [u8; 20],
+    /// Collateral asset being withdrawn
+    pub withdraw_asset: [u8; 20],
+    /// Amount of collateral being withdrawn
+    pub withdraw_amount: u128,
+    /// Merkle root the old note was proven against (checked against root history)
+    pub merkle_root: [u8; 32],
+    /// Hash of the oracle key that signed the prices (vault checks it is trusted)
+    pub oracle_key_hash: [u8; 32],
+    /// Time the oldest price was signed (vault rejects stale prices)
+    pub price_timestamp: u64,
+    /// Prices, max LTVs and decimals the remaining collateral was valued at (vault checks its collateral table)
+    pub markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+    /// Debt token decimals used for the LTV check
+    pub debt_decimals: u8,
+    /// Global borrow index the debt was accrued to (vault checks it is current)
+    pub borrow_index: u128,
+    /// Why the withdraw was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
+}
+
+/// Public output from liquidation proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LiquidationOutput {
+    /// Hash of the liquidated note (nullifier)
+    pub nullifier_hash: [u8; 32],
+    /// Collateral the keeper may seize
+    pub seized_collateral: [CollateralPosition; MAX_COLLATERAL_ASSETS],
+    /// Debt the keeper must repay
+    pub debt_to_repay: u128,
+    /// Prices and thresholds the note was evaluated at (vault checks the thresholds against its config)
+    pub markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+    /// Debt token decimals used for the LTV check
+    pub debt_decimals: u8,
+    /// Hash of the oracle key that signed the prices (vault checks it is trusted)
+    pub oracle_key_hash: [u8; 32],
+    /// Time the oldest price was signed (vault rejects stale prices)
+    pub price_timestamp: u64,
+    /// Keeper address receiving the seized collateral
+    pub recipient_address: [u8; 20],
+    /// Merkle root the note was proven against (checked against root history)
+    pub merkle_root: [u8; 32],
+    /// Global borrow index the debt was accrued to (vault checks it is current)
+    pub borrow_index: u128,
+    /// Why the liquidation was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
+}
+
+/// Public output from join-split proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JoinSplitOutput {
+    /// Nullifiers of the spent notes (zero for unused entries)
+    pub nullifier_hashes: [[u8; 32]; MAX_SPLIT_NOTES],
+    /// Commitments of the created notes (zero for unused entries)
+    pub new_commitment_hashes: [[u8; 32]; MAX_SPLIT_NOTES],
+    /// Prices and max LTVs new notes with debt were checked at (vault checks its collateral table)
+    pub markets: [CollateralMarket; MAX_COLLATERAL_ASSETS],
+    /// Debt token decimals used for the LTV check
+    pub debt_decimals: u8,
+    /// Hash of the oracle key that signed the prices (vault checks it is trusted)
+    pub oracle_key_hash: [u8; 32],
+    /// Time the oldest price was signed (vault rejects stale prices)
+    pub price_timestamp: u64,
+    /// Merkle root the spent notes were proven against (checked against root history)
+    pub merkle_root: [u8; 32],
+    /// Global borrow index the debt was accrued to (vault checks it is current)
+    pub borrow_index: u128,
+    /// Why the join-split was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
+}
+
+/// Public output from disclosure proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisclosureOutput {
+    /// Statement that holds for the note
+    pub statement: DisclosureStatement,
+    /// Owner key of the note's holder (their public address, not linkable to the note)
+    pub owner_key: [u8; 32],
+    /// Auditor's challenge the proof answers
+    pub challenge: [u8; 32],
+    /// Merkle root the note was proven against (the auditor checks it is a vault root)
+    pub merkle_root: [u8; 32],
+    /// Why the disclosure was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
+}
+
+/// Public output from migration proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrationOutput {
+    /// Version 0 nullifier of the legacy note (it can't be spent or migrated again)
+    pub nullifier_hash: [u8; 32],
+    /// Current-version commitment of the same note under its new salt
+    pub new_commitment_hash: [u8; 32],
+    /// Merkle root the legacy note was proven against (checked against root history)
+    pub merkle_root: [u8; 32],
+    /// Why the migration was rejected (Success if it is valid)
+    pub error_code: ErrorCode,
 }